@@ -1,7 +1,9 @@
-use std::io::Write;
-use std::iter;
+use std::io::{self, Write};
+use std::{iter, thread};
 
-use brotlic::{BrotliEncoderOptions, Quality, WindowSize};
+use brotlic::{
+    BrotliDecoder, BrotliEncoderOptions, CompressionMode, DecompressorWriter, Quality, WindowSize,
+};
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use rand::{Rng, RngCore, SeedableRng};
 use rand_pcg::Pcg32;
@@ -16,8 +18,10 @@ fn brotli_compress(input: &[u8]) -> Vec<u8> {
 
 fn brotlic_compress(input: &[u8]) -> Vec<u8> {
     let encoder = BrotliEncoderOptions::new()
-        .quality(Quality::new(11).unwrap())
-        .window_size(WindowSize::new(24).unwrap())
+        .quality(11)
+        .unwrap()
+        .window_size(24)
+        .unwrap()
         .build()
         .unwrap();
 
@@ -28,12 +32,71 @@ fn brotlic_compress(input: &[u8]) -> Vec<u8> {
     compressor.into_inner().unwrap()
 }
 
+fn brotlic_compress_oneshot(input: &[u8]) -> Vec<u8> {
+    brotlic::compress_to_vec(
+        input,
+        Quality::new(11).unwrap(),
+        WindowSize::new(24).unwrap(),
+        CompressionMode::Generic,
+    )
+    .unwrap()
+}
+
+/// Writer that performs a system call on every write, similar to a file or a
+/// socket.
+#[derive(Debug)]
+struct SyscallWriter(Vec<u8>);
+
+impl Write for SyscallWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        thread::yield_now();
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn brotlic_compress_buffered(input: &[u8], capacity: usize) -> Vec<u8> {
+    let encoder = BrotliEncoderOptions::new()
+        .quality(1)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let inner = SyscallWriter(Vec::with_capacity(input.len()));
+    let mut compressor =
+        brotlic::CompressorWriter::with_encoder_and_capacity(encoder, inner, capacity);
+
+    for chunk in input.chunks(1024) {
+        compressor.write_all(chunk).unwrap();
+    }
+
+    compressor.into_inner().unwrap().0
+}
+
+fn brotlic_decompress_buffered(input: &[u8], capacity: usize, output_size: usize) -> Vec<u8> {
+    let inner = SyscallWriter(Vec::with_capacity(output_size));
+    let mut decompressor =
+        DecompressorWriter::with_decoder_and_capacity(BrotliDecoder::new(), inner, capacity);
+
+    for chunk in input.chunks(1024) {
+        decompressor.write_all(chunk).unwrap();
+    }
+
+    decompressor.into_inner().unwrap().0
+}
+
 pub fn bench(c: &mut Criterion) {
     bench_entropy(c, "min_entropy", gen_min_entropy);
     bench_entropy(c, "low_entropy", gen_low_entropy);
     bench_entropy(c, "medium_entropy", gen_medium_entropy);
     bench_entropy(c, "high_entropy", gen_high_entropy);
     bench_entropy(c, "max_entropy", gen_max_entropy);
+    bench_oneshot(c, "oneshot_medium_entropy", gen_medium_entropy);
+    bench_buffered(c, "buffered_medium_entropy", gen_medium_entropy);
+    bench_thread_local(c, "thread_local_medium_entropy", gen_medium_entropy);
 }
 
 pub fn bench_entropy(c: &mut Criterion, name: &str, entropy_source: fn(usize) -> Vec<u8>) {
@@ -70,6 +133,93 @@ pub fn bench_entropy(c: &mut Criterion, name: &str, entropy_source: fn(usize) ->
     }
 }
 
+pub fn bench_oneshot(c: &mut Criterion, name: &str, entropy_source: fn(usize) -> Vec<u8>) {
+    let input_sizes = { iter::successors(Some(1usize << 5), |x| (*x).checked_shl(5)) };
+
+    let mut group = c.benchmark_group(name);
+
+    for input_size in input_sizes.take(4) {
+        let input = entropy_source(input_size);
+
+        group.throughput(Throughput::Bytes(input_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("streaming", input_size),
+            &input_size,
+            |b, &_size| {
+                b.iter(|| brotlic_compress(&input));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("oneshot", input_size),
+            &input_size,
+            |b, &_size| {
+                b.iter(|| brotlic_compress_oneshot(&input));
+            },
+        );
+    }
+}
+
+pub fn bench_buffered(c: &mut Criterion, name: &str, entropy_source: fn(usize) -> Vec<u8>) {
+    let input_size = 1 << 20;
+    let input = entropy_source(input_size);
+    let compressed = brotlic_compress_buffered(&input, 0);
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(input_size as u64));
+
+    for capacity in [0, 4 * 1024, 64 * 1024, 1024 * 1024] {
+        group.bench_with_input(
+            BenchmarkId::new("compress", capacity),
+            &capacity,
+            |b, &capacity| {
+                b.iter(|| brotlic_compress_buffered(&input, capacity));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("decompress", capacity),
+            &capacity,
+            |b, &capacity| {
+                b.iter(|| brotlic_decompress_buffered(&compressed, capacity, input_size));
+            },
+        );
+    }
+}
+
+pub fn bench_thread_local(c: &mut Criterion, name: &str, entropy_source: fn(usize) -> Vec<u8>) {
+    let input_sizes = { iter::successors(Some(1usize << 5), |x| (*x).checked_shl(5)) };
+    let options = BrotliEncoderOptions::for_http_response();
+    let mut compress_pooled = brotlic::thread_local_encoder(&options);
+
+    let mut group = c.benchmark_group(name);
+
+    for input_size in input_sizes.take(3) {
+        let input = entropy_source(input_size);
+
+        group.throughput(Throughput::Bytes(input_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("fresh", input_size),
+            &input_size,
+            |b, &_size| {
+                b.iter(|| {
+                    let mut output = Vec::new();
+                    brotlic::compress_to_writer(&input, &mut output, &options).unwrap();
+                    output
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("pooled", input_size),
+            &input_size,
+            |b, &_size| {
+                b.iter(|| compress_pooled(&input).unwrap());
+            },
+        );
+    }
+}
+
 fn gen_min_entropy(len: usize) -> Vec<u8> {
     vec![0; len]
 }
@@ -1,9 +1,9 @@
 #![allow(nonstandard_style)]
 #![allow(rustdoc::broken_intra_doc_links)]
+#![no_std]
 
-use std::ffi::c_void;
-use std::marker;
-use std::os::raw::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_void};
+use core::marker;
 
 pub const BROTLI_TRUE: BROTLI_BOOL = 1;
 pub const BROTLI_FALSE: BROTLI_BOOL = 0;
@@ -667,7 +667,7 @@ extern "C" {
     pub fn BrotliDecoderCreateInstance(
         alloc_func: brotli_alloc_func,
         free_func: brotli_free_func,
-        opaque: *mut ::std::os::raw::c_void,
+        opaque: *mut ::core::ffi::c_void,
     ) -> *mut BrotliDecoderState;
 
     #[doc = " Deinitializes and frees ::BrotliDecoderState instance."]
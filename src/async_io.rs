@@ -0,0 +1,140 @@
+//! Shared implementation of the asynchronous readers and writers.
+//!
+//! The asynchronous I/O traits of each supported runtime are mapped onto
+//! [`AsyncWrite`] and [`AsyncBufRead`], so that the stream types implement
+//! their asynchronous logic once. The type parameter `F` selects the runtime,
+//! which keeps the blanket implementations for different runtimes from
+//! overlapping.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Selects the `tokio` I/O traits.
+#[cfg(feature = "tokio")]
+pub(crate) enum Tokio {}
+
+/// Selects the `futures-io` I/O traits.
+#[cfg(feature = "futures-io")]
+pub(crate) enum FuturesIo {}
+
+/// An asynchronous writer of runtime `F`.
+pub(crate) trait AsyncWrite<F> {
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+/// An asynchronous buffered reader of runtime `F`.
+pub(crate) trait AsyncBufRead<F> {
+    fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>>;
+
+    fn consume(&mut self, amt: usize);
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin> AsyncWrite<Tokio> for T {
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(self), cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(self), cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_shutdown(Pin::new(self), cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncBufRead + Unpin> AsyncBufRead<Tokio> for T {
+    fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        tokio::io::AsyncBufRead::poll_fill_buf(Pin::new(self), cx)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        tokio::io::AsyncBufRead::consume(Pin::new(self), amt)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncWrite + Unpin> AsyncWrite<FuturesIo> for T {
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        futures_io::AsyncWrite::poll_write(Pin::new(self), cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        futures_io::AsyncWrite::poll_flush(Pin::new(self), cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        futures_io::AsyncWrite::poll_close(Pin::new(self), cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncBufRead + Unpin> AsyncBufRead<FuturesIo> for T {
+    fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        futures_io::AsyncBufRead::poll_fill_buf(Pin::new(self), cx)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        futures_io::AsyncBufRead::consume(Pin::new(self), amt)
+    }
+}
+
+/// An operation issued to an encoder whose output has not been written to the
+/// underlying writer yet.
+///
+/// Polling a writer again after it returned pending resumes the operation
+/// instead of issuing and counting it again.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum PendingOp {
+    #[default]
+    None,
+    Flush,
+    Finish,
+}
+
+/// Output taken from an encoder or decoder that has not been written to the
+/// underlying writer yet.
+///
+/// Output can only be taken from an encoder or decoder once, so it is kept
+/// here while the underlying writer is not ready to accept all of it.
+#[derive(Debug, Default)]
+pub(crate) struct PendingOutput {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl PendingOutput {
+    /// Replaces the pending output with `output`.
+    ///
+    /// Must only be called once all previous output has been written.
+    pub(crate) fn set(&mut self, output: &[u8]) {
+        debug_assert_eq!(self.pos, self.buf.len());
+
+        self.buf.clear();
+        self.buf.extend_from_slice(output);
+        self.pos = 0;
+    }
+
+    /// Writes all pending output to `inner`.
+    pub(crate) fn poll_write_to<W: AsyncWrite<F>, F>(
+        &mut self,
+        inner: &mut W,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.pos < self.buf.len() {
+            match ready!(inner.poll_write(cx, &self.buf[self.pos..]))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                n => self.pos += n,
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -6,14 +6,37 @@
 //! [`Read`]: https://doc.rust-lang.org/stable/std/io/trait.Read.html
 //! [`Write`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html
 
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::alloc::GlobalAlloc;
+use core::ffi::CStr;
+#[cfg(feature = "std")]
+use core::marker::PhantomData;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use core::pin::Pin;
+use core::str::Utf8Error;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use core::task::{ready, Context, Poll};
+use core::{fmt, slice};
+#[cfg(feature = "std")]
+use core::{mem, ptr};
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::ffi::CStr;
-use std::io::{BufRead, Read, Write};
-use std::{fmt, io, ptr, slice};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, IoSlice, Read, Write};
 
 use brotlic_sys::*;
 
-use crate::{IntoInnerError, SetParameterError};
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use crate::async_io::{AsyncBufRead, AsyncWrite, PendingOutput};
+use crate::{
+    BrotliAllocator, DecompressError, DictionaryError, SetParameterError, SharedDictionaryType,
+    WindowSize,
+};
+#[cfg(feature = "std")]
+use crate::{IntoInnerError, OutputBuffer};
 
 /// A reference to a brotli decoder.
 ///
@@ -21,8 +44,49 @@ use crate::{IntoInnerError, SetParameterError};
 /// wrapper intended to be used for people who are familiar with the C API. For
 /// higher level abstractions, see [`DecompressorReader`] and
 /// [`DecompressorWriter`].
+///
+/// `BrotliDecoder` implements [`Read`] by reading the output the decoder
+/// holds, which requires giving it input with e.g. [`give_input`] first.
+/// Reading returns `Ok(0)` once the decoder is finished and all output has
+/// been read. If the decoder holds no output but is not finished either, an
+/// error of kind [`WouldBlock`] is returned, meaning more input is needed or
+/// decoding has to be resumed with [`give_input`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+///
+/// use brotlic::{compress_to_vec, BrotliDecoder, CompressionMode, Quality, WindowSize};
+///
+/// let compressed = compress_to_vec(
+///     b"hello world",
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// let mut decoder = BrotliDecoder::new();
+/// decoder.give_input(&compressed)?;
+///
+/// let mut decompressed = String::new();
+/// decoder.read_to_string(&mut decompressed)?;
+///
+/// assert_eq!(decompressed, "hello world");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`give_input`]: BrotliDecoder::give_input
+/// [`WouldBlock`]: io::ErrorKind::WouldBlock
 pub struct BrotliDecoder {
     state: *mut BrotliDecoderState,
+    _alloc: Option<BrotliAllocator>,
+    dictionaries: Vec<(SharedDictionaryType, Arc<[u8]>)>,
+    serialized_dictionaries: u32,
+    params: Vec<(BrotliDecoderParameter, u32)>,
+    max_window_bits: Option<u8>,
+    window_checked: bool,
+    total_out: usize,
 }
 
 unsafe impl Send for BrotliDecoder {}
@@ -36,21 +100,180 @@ impl BrotliDecoder {
     /// Panics if the decoder fails to be allocated or initialized
     #[doc(alias = "BrotliDecoderCreateInstance")]
     pub fn new() -> Self {
-        let instance = unsafe { BrotliDecoderCreateInstance(None, None, ptr::null_mut()) };
+        Self::create(None)
+    }
+
+    /// Constructs a new brotli decoder instance using the custom allocator
+    /// `alloc`.
+    ///
+    /// The allocator is kept alive for as long as the decoder exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::alloc::System;
+    ///
+    /// use brotlic::BrotliDecoder;
+    ///
+    /// let decoder = BrotliDecoder::new_in(System);
+    /// ```
+    #[doc(alias = "BrotliDecoderCreateInstance")]
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(alloc: A) -> Self {
+        Self::create(Some(BrotliAllocator::new(alloc)))
+    }
+
+    fn create(alloc: Option<BrotliAllocator>) -> Self {
+        BrotliDecoder {
+            state: Self::create_instance(alloc.as_ref()),
+            _alloc: alloc,
+            dictionaries: Vec::new(),
+            serialized_dictionaries: 0,
+            params: Vec::new(),
+            max_window_bits: None,
+            window_checked: false,
+            total_out: 0,
+        }
+    }
+
+    fn create_instance(alloc: Option<&BrotliAllocator>) -> *mut BrotliDecoderState {
+        let (alloc_func, free_func, opaque) = BrotliAllocator::funcs(alloc);
+        let instance = unsafe { BrotliDecoderCreateInstance(alloc_func, free_func, opaque) };
 
         if !instance.is_null() {
-            BrotliDecoder { state: instance }
+            instance
         } else {
             panic!("BrotliDecoderCreateInstance returned NULL: failed to allocate or initialize");
         }
     }
 
+    /// Resets the decoder to its initial state, so that it can be used to
+    /// decompress a new stream.
+    ///
+    /// The internal decoder state is recreated using the same allocator, all
+    /// parameters are reset to their defaults and attached dictionaries are
+    /// detached. Afterwards the decoder is no longer [used]. Use
+    /// [`reset_with_options`] to apply parameters afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoder;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    ///
+    /// decoder.give_input(&[0x0b])?;
+    /// assert!(decoder.is_used());
+    ///
+    /// decoder.reset();
+    /// assert!(!decoder.is_used());
+    /// # Ok::<(), brotlic::decode::DecodeError>(())
+    /// ```
+    ///
+    /// [used]: BrotliDecoder::is_used
+    /// [`reset_with_options`]: BrotliDecoder::reset_with_options
+    #[doc(alias = "BrotliDecoderDestroyInstance")]
+    #[doc(alias = "BrotliDecoderCreateInstance")]
+    pub fn reset(&mut self) {
+        let instance = Self::create_instance(self._alloc.as_ref());
+
+        unsafe {
+            BrotliDecoderDestroyInstance(self.state);
+        }
+
+        self.state = instance;
+        self.dictionaries.clear();
+        self.serialized_dictionaries = 0;
+        self.params.clear();
+        self.max_window_bits = None;
+        self.window_checked = false;
+        self.total_out = 0;
+    }
+
+    /// Resets the decoder like [`reset`](Self::reset), but keeps its
+    /// parameters, window size limit and attached dictionaries, so that the
+    /// next stream is decoded the same way as the previous one.
+    fn restart(&mut self) {
+        let instance = Self::create_instance(self._alloc.as_ref());
+
+        unsafe {
+            BrotliDecoderDestroyInstance(self.state);
+        }
+
+        self.state = instance;
+        self.window_checked = false;
+        self.total_out = 0;
+
+        for &(param, value) in &self.params {
+            let r = unsafe { BrotliDecoderSetParameter(self.state, param, value) };
+            assert!(r != 0, "BrotliDecoderSetParameter failed on a new decoder");
+        }
+
+        for (dict_type, data) in &self.dictionaries {
+            let r = unsafe {
+                BrotliDecoderAttachDictionary(
+                    self.state,
+                    *dict_type as BrotliSharedDictionaryType,
+                    data.len(),
+                    data.as_ptr(),
+                )
+            };
+            assert!(
+                r != 0,
+                "BrotliDecoderAttachDictionary failed on a new decoder"
+            );
+        }
+    }
+
+    /// Resets the decoder like [`reset`] and applies `options` to it.
+    ///
+    /// The decoder afterwards behaves like one built using
+    /// [`BrotliDecoderOptions::build`], without allocating a new decoder.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    ///
+    /// [`reset`]: BrotliDecoder::reset
+    #[doc(alias = "BrotliDecoderSetParameter")]
+    pub fn reset_with_options(
+        &mut self,
+        options: &BrotliDecoderOptions,
+    ) -> Result<(), SetParameterError> {
+        self.reset();
+
+        options.configure(self)
+    }
+
     /// Checks if the decoder instance reached its final state.
     #[doc(alias = "BrotliDecoderIsFinished")]
     pub fn is_finished(&self) -> bool {
         unsafe { BrotliDecoderIsFinished(self.state) != 0 }
     }
 
+    /// Checks if the decoder instance has consumed any input yet.
+    ///
+    /// A decoder that has not been used is in its pristine state and may still
+    /// be [reconfigured] or have dictionaries attached to it.
+    ///
+    /// [reconfigured]: BrotliDecoder::reconfigure
+    #[doc(alias = "BrotliDecoderIsUsed")]
+    pub fn is_used(&self) -> bool {
+        unsafe { BrotliDecoderIsUsed(self.state) != 0 }
+    }
+
     /// Decompresses the input stream to the output stream.
     ///
     /// This is a low-level API, for higher level abstractions see
@@ -61,7 +284,10 @@ impl BrotliDecoder {
     /// it could be passed to the next consumer after decoding is complete.
     /// Bytes are written to `output`, the number of bytes written is returned
     /// in the `bytes_written` field of the result. The `info` field of the
-    /// result communicates the state of the decoding process.
+    /// result communicates the state of the decoding process. The
+    /// `pending_output` field indicates whether the decoder still holds output
+    /// in its internal buffer, which can be retrieved with
+    /// [`Self::take_output`].
     ///
     /// if `info` is [`DecoderInfo::NeedsMoreInput`], more input is required to
     /// continue decoding. Likewise, if `info` is
@@ -74,10 +300,21 @@ impl BrotliDecoder {
         input: &[u8],
         output: &mut [u8],
     ) -> Result<DecodeResult, DecodeError> {
+        if let (Some(max_window_bits), false) = (self.max_window_bits, self.window_checked) {
+            if let Some(&header) = input.first() {
+                if stream_window_bits(header) > max_window_bits {
+                    return Err(DecodeError::FormatWindowBits);
+                }
+
+                self.window_checked = true;
+            }
+        }
+
         let mut input_ptr = input.as_ptr();
         let mut input_len = input.len();
         let mut output_ptr = output.as_mut_ptr();
         let mut output_len = output.len();
+        let mut total_out = self.total_out;
 
         let result = unsafe {
             BrotliDecoderDecompressStream(
@@ -86,16 +323,18 @@ impl BrotliDecoder {
                 &mut input_ptr,
                 &mut output_len,
                 &mut output_ptr,
-                ptr::null_mut(),
+                &mut total_out,
             )
         };
 
+        self.total_out = total_out;
+
         let bytes_read = input.len() - input_len;
         let bytes_written = output.len() - output_len;
 
         #[allow(non_upper_case_globals)]
         let info = match result {
-            BrotliDecoderResult_BROTLI_DECODER_RESULT_ERROR => return Err(self.last_error()),
+            BrotliDecoderResult_BROTLI_DECODER_RESULT_ERROR => return Err(self.decode_error()),
             BrotliDecoderResult_BROTLI_DECODER_RESULT_SUCCESS => DecoderInfo::Finished,
             BrotliDecoderResult_BROTLI_DECODER_RESULT_NEEDS_MORE_INPUT => {
                 DecoderInfo::NeedsMoreInput
@@ -109,6 +348,7 @@ impl BrotliDecoder {
         Ok(DecodeResult {
             bytes_read,
             bytes_written,
+            pending_output: self.has_output(),
             info,
         })
     }
@@ -121,7 +361,161 @@ impl BrotliDecoder {
         Ok((res.bytes_read, res.info))
     }
 
+    /// Decompresses `input`, appending all decompressed data to `output`.
+    ///
+    /// Unlike [`Self::decompress`], this keeps giving input to the decoder and
+    /// taking its output until the decoder needs more input or the stream is
+    /// finished. The `bytes_written` field of the result is the number of bytes
+    /// appended to `output`. Input following the end of the compression
+    /// stream is not consumed, see the `bytes_read` field of the result.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if decoding fails. Output produced before
+    /// the error occurred has already been appended to `output`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::decode::DecoderInfo;
+    /// use brotlic::{compress_to_vec, BrotliDecoder, CompressionMode, Quality, WindowSize};
+    ///
+    /// let compressed = compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let mut decompressed = Vec::new();
+    ///
+    /// let (first, second) = compressed.split_at(compressed.len() / 2);
+    /// let res = decoder.decompress_all_into(first, &mut decompressed)?;
+    /// assert_eq!(res.info, DecoderInfo::NeedsMoreInput);
+    ///
+    /// let res = decoder.decompress_all_into(second, &mut decompressed)?;
+    /// assert_eq!(res.info, DecoderInfo::Finished);
+    /// assert_eq!(decompressed, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn decompress_all_into(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<DecodeResult, DecodeError> {
+        let len = output.len();
+        let mut bytes_read = 0;
+
+        let info = loop {
+            let res = self.decompress(&input[bytes_read..], &mut [])?;
+            bytes_read += res.bytes_read;
+
+            while let Some(buf) = unsafe { self.take_output() } {
+                output.extend_from_slice(buf);
+            }
+
+            if res.info != DecoderInfo::NeedsMoreOutput {
+                break res.info;
+            }
+        };
+
+        Ok(DecodeResult {
+            bytes_read,
+            bytes_written: output.len() - len,
+            pending_output: self.has_output(),
+            info,
+        })
+    }
+
+    /// Decompresses `input` and appends the decompressed text to `output`.
+    ///
+    /// All output the decoder is able to produce from `input` is collected and
+    /// validated as UTF-8 before it is appended to `output`. The
+    /// `bytes_written` field of the result is the number of bytes appended to
+    /// `output`. Since a multi-byte character may be split across calls, the
+    /// validation only succeeds reliably if `input` contains the remainder of
+    /// the compression stream.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if decoding fails or the decompressed data
+    /// is not valid UTF-8. In the latter case the decompressed data is
+    /// discarded and `output` is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::decode::DecoderInfo;
+    /// use brotlic::{BrotliDecoder, CompressorWriter};
+    ///
+    /// let mut compressor = CompressorWriter::new(Vec::new());
+    /// compressor.write_all(b"hello world")?;
+    /// let compressed = compressor.into_inner()?;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let mut text = String::new();
+    /// let res = decoder.decompress_to_string(&compressed, &mut text)?;
+    ///
+    /// assert_eq!(res.info, DecoderInfo::Finished);
+    /// assert_eq!(text, "hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn decompress_to_string(
+        &mut self,
+        input: &[u8],
+        output: &mut String,
+    ) -> Result<DecodeResult, DecodeToStringError> {
+        let mut decompressed = Vec::new();
+        let res = self.decompress_all_into(input, &mut decompressed)?;
+
+        let text = String::from_utf8(decompressed).map_err(|e| e.utf8_error())?;
+        output.push_str(&text);
+
+        Ok(res)
+    }
+
+    /// Returns the total number of decompressed bytes produced by the decoder.
+    ///
+    /// This counts all output since the decoder was created or [reset],
+    /// whether it was written by [`Self::decompress`] or taken using
+    /// [`Self::take_output`]. It can be used to limit the size of the
+    /// decompressed data of untrusted input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{compress_to_vec, BrotliDecoder, CompressionMode, Quality, WindowSize};
+    ///
+    /// let input = vec![0; 1024];
+    /// let compressed = compress_to_vec(
+    ///     &input,
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let mut output = vec![0; 100];
+    ///
+    /// decoder.decompress(&compressed, &mut output)?;
+    /// assert_eq!(decoder.total_out(), 100);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [reset]: Self::reset
+    pub fn total_out(&self) -> usize {
+        self.total_out
+    }
+
     /// Checks if the decoder has more output.
+    ///
+    /// Output is only buffered by [`Self::decompress`], so a newly created
+    /// decoder initially has no buffered output and this returns `false`
+    /// before the first call to [`Self::decompress`]. It is therefore not
+    /// suitable as the sole condition of a decoding loop.
     #[doc(alias = "BrotliDecoderHasMoreOutput")]
     pub fn has_output(&self) -> bool {
         unsafe { BrotliDecoderHasMoreOutput(self.state) != 0 }
@@ -140,63 +534,441 @@ impl BrotliDecoder {
     /// invalidated.
     #[doc(alias = "BrotliDecoderTakeOutput")]
     pub unsafe fn take_output(&mut self) -> Option<&[u8]> {
+        self.take_output_at_most(0)
+    }
+
+    /// Like [`Self::take_output`], but returns at most `max_len` bytes, leaving
+    /// the rest in the decoder. A `max_len` of zero returns all available
+    /// output.
+    unsafe fn take_output_at_most(&mut self, max_len: usize) -> Option<&[u8]> {
         if self.has_output() {
-            let mut len: usize = 0;
+            let mut len = max_len;
             let output = BrotliDecoderTakeOutput(self.state, &mut len as _);
 
+            self.total_out += len;
+
             Some(slice::from_raw_parts(output, len))
         } else {
             None
         }
     }
 
-    /// Returns the version of the C brotli decoder library.
-    #[doc(alias = "BrotliDecoderVersion")]
-    pub fn version() -> u32 {
-        unsafe { BrotliDecoderVersion() }
+    /// Takes all output currently buffered in the decoder into a newly
+    /// allocated [`Vec<u8>`].
+    ///
+    /// This is a safe alternative to [`Self::take_output`] at the cost of an
+    /// additional allocation. An empty vector is returned if the decoder has
+    /// no output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{compress_to_vec, BrotliDecoder, CompressionMode, Quality, WindowSize};
+    ///
+    /// let compressed = compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let mut input = compressed.as_slice();
+    /// let mut decompressed = Vec::new();
+    ///
+    /// while !decoder.is_finished() {
+    ///     let (bytes_read, _) = decoder.give_input(input)?;
+    ///     input = &input[bytes_read..];
+    ///     decompressed.extend(decoder.take_output_to_vec());
+    /// }
+    ///
+    /// assert_eq!(decompressed, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "BrotliDecoderTakeOutput")]
+    pub fn take_output_to_vec(&mut self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        while let Some(buf) = unsafe { self.take_output() } {
+            output.extend_from_slice(buf);
+        }
+
+        output
     }
 
-    fn set_param(
-        &mut self,
-        param: BrotliDecoderParameter,
-        value: u32,
-    ) -> Result<(), SetParameterError> {
-        let r = unsafe { BrotliDecoderSetParameter(self.state, param, value) };
+    /// Writes all output currently buffered in the decoder to `writer`.
+    ///
+    /// This is a safe alternative to [`Self::take_output`] that avoids the
+    /// intermediate allocation of [`Self::take_output_to_vec`]. Returns the
+    /// number of decompressed bytes written, which is zero if the decoder has
+    /// no output.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if writing to `writer` fails. Output is
+    /// taken from the decoder in bounded chunks, so only the chunk that failed
+    /// to be written is lost while the remainder stays with the decoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{compress_to_vec, BrotliDecoder, CompressionMode, Quality, WindowSize};
+    ///
+    /// let compressed = compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let mut input = compressed.as_slice();
+    /// let mut decompressed = Vec::new();
+    ///
+    /// while !decoder.is_finished() {
+    ///     let (bytes_read, _) = decoder.give_input(input)?;
+    ///     input = &input[bytes_read..];
+    ///     decoder.drain_to_writer(&mut decompressed)?;
+    /// }
+    ///
+    /// assert_eq!(decompressed, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn drain_to_writer<W: Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<usize> {
+        let mut total = 0;
 
-        if r != 0 {
-            Ok(())
-        } else {
-            Err(SetParameterError::Generic)
+        while let Some(output) = unsafe { self.take_output_at_most(64 * 1024) } {
+            writer.write_all(output)?;
+            total += output.len();
         }
+
+        Ok(total)
     }
 
-    fn last_error(&self) -> DecodeError {
-        let ec = unsafe { BrotliDecoderGetErrorCode(self.state) };
+    /// Attaches a dictionary to the decoder.
+    ///
+    /// Raw LZ77 prefix dictionaries may be attached multiple times, whereas at
+    /// most one serialized dictionary may be attached. The `data` is copied and
+    /// kept alive by the decoder for as long as it exists. Dictionaries must be
+    /// attached before decoding starts.
+    ///
+    /// Serialized dictionaries are not supported by the brotli library built
+    /// by `brotlic-sys` and are rejected with [`DictionaryError::Unsupported`].
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if:
+    ///
+    /// * a serialized dictionary was already attached
+    /// * `dict_type` is [`SharedDictionaryType::Serialized`]
+    /// * the dictionary is corrupted or the dictionary count limit is reached
+    /// * decoding has already started
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn attach_dictionary(
+        &mut self,
+        dict_type: SharedDictionaryType,
+        data: &[u8],
+    ) -> Result<(), DictionaryError> {
+        self.attach(dict_type, Arc::from(data))
+    }
 
-        #[allow(non_upper_case_globals)]
-        match ec {
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_EXUBERANT_NIBBLE => {
-                DecodeError::FormatExuberantNibble
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_RESERVED => {
-                DecodeError::FormatReserved
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_EXUBERANT_META_NIBBLE => {
-                DecodeError::FormatExuberantMetaNibble
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_SIMPLE_HUFFMAN_ALPHABET => {
-                DecodeError::FormatSimpleHuffmanAlphabet
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_SIMPLE_HUFFMAN_SAME => {
-                DecodeError::FormatSimpleHuffmanSame
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_CL_SPACE => {
-                DecodeError::FormatClSpace
-            }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_HUFFMAN_SPACE => {
-                DecodeError::FormatHuffmanSpace
+    /// Attaches a raw LZ77 prefix dictionary to the decoder.
+    ///
+    /// This is equivalent to `attach_dictionary(SharedDictionaryType::Raw,
+    /// data)`, see [`Self::attach_dictionary`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if the dictionary count limit is reached or
+    /// decoding has already started.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoder;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// decoder.attach_raw_dictionary(b"hello world")?;
+    /// # Ok::<(), brotlic::DictionaryError>(())
+    /// ```
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn attach_raw_dictionary(&mut self, data: &[u8]) -> Result<(), DictionaryError> {
+        self.attach_dictionary(SharedDictionaryType::Raw, data)
+    }
+
+    /// Attaches a serialized dictionary to the decoder.
+    ///
+    /// This is equivalent to
+    /// `attach_dictionary(SharedDictionaryType::Serialized, data)`, see
+    /// [`Self::attach_dictionary`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if:
+    ///
+    /// * a serialized dictionary was already attached
+    /// * serialized dictionaries are not supported, see
+    ///   [`DictionaryError::Unsupported`]
+    /// * decoding has already started
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn attach_serialized_dictionary(&mut self, data: &[u8]) -> Result<(), DictionaryError> {
+        self.attach_dictionary(SharedDictionaryType::Serialized, data)
+    }
+
+    /// Attaches all dictionaries of a [`SharedDictionary`] to the decoder.
+    ///
+    /// Unlike [`Self::attach_dictionary`], the dictionary data is not copied
+    /// but shared with `dictionary`. Dictionaries must be attached before
+    /// decoding starts. Either all dictionaries of `dictionary` are attached,
+    /// or none of them are if attaching any of them fails.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if:
+    ///
+    /// * a serialized dictionary was already attached
+    /// * the dictionary is corrupted or the dictionary count limit is reached
+    /// * decoding has already started
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn attach_shared_dictionary(
+        &mut self,
+        dictionary: &SharedDictionary,
+    ) -> Result<(), DictionaryError> {
+        let attached = self.dictionaries.len();
+        let serialized_dictionaries = self.serialized_dictionaries;
+
+        for (dict_type, data) in &dictionary.dictionaries {
+            if let Err(err) = self.attach(*dict_type, Arc::clone(data)) {
+                // dictionaries can't be detached from the C decoder, so recreate
+                // it with only the dictionaries attached before this call.
+                if self.dictionaries.len() > attached {
+                    self.dictionaries.truncate(attached);
+                    self.serialized_dictionaries = serialized_dictionaries;
+                    self.restart();
+                }
+
+                return Err(err);
             }
-            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_CONTEXT_MAP_REPEAT => {
+        }
+
+        Ok(())
+    }
+
+    fn attach(
+        &mut self,
+        dict_type: SharedDictionaryType,
+        data: Arc<[u8]>,
+    ) -> Result<(), DictionaryError> {
+        if self.is_used() {
+            return Err(DictionaryError::DecodingStarted);
+        }
+
+        let serialized = dict_type == SharedDictionaryType::Serialized;
+
+        if serialized && self.serialized_dictionaries > 0 {
+            return Err(DictionaryError::TooManySerializedDictionaries);
+        }
+
+        // the C library rejects serialized dictionaries unless it is built with
+        // `BROTLI_EXPERIMENTAL`, which `brotlic-sys` does not enable.
+        if serialized {
+            return Err(DictionaryError::Unsupported);
+        }
+
+        let r = unsafe {
+            BrotliDecoderAttachDictionary(
+                self.state,
+                dict_type as BrotliSharedDictionaryType,
+                data.len(),
+                data.as_ptr(),
+            )
+        };
+
+        if r == 0 {
+            return Err(DictionaryError::Generic);
+        }
+
+        if serialized {
+            self.serialized_dictionaries += 1;
+        }
+
+        self.dictionaries.push((dict_type, data));
+
+        Ok(())
+    }
+
+    /// Applies `options` to this decoder.
+    ///
+    /// This allows to reuse an existing decoder instance instead of building
+    /// a new one with [`BrotliDecoderOptions::build`]. Parameters that are not
+    /// set in `options` are left unchanged. Options can only be applied before
+    /// decoding starts.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if any of the preconditions of the
+    /// parameters are violated. If decoding has already started,
+    /// [`SetParameterError::InUse`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliDecoder, BrotliDecoderOptions};
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// let options = BrotliDecoderOptions::for_untrusted_input(16);
+    ///
+    /// decoder.reconfigure(&options)?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BrotliDecoderSetParameter")]
+    pub fn reconfigure(&mut self, options: &BrotliDecoderOptions) -> Result<(), SetParameterError> {
+        if self.is_used() {
+            return Err(SetParameterError::InUse);
+        }
+
+        options.configure(self)
+    }
+
+    /// Decompresses all of `input` into `output` in one go, returning how many
+    /// bytes were written.
+    ///
+    /// This does not require a decoder instance and is equivalent to calling
+    /// [`decompress`]. `input` must contain a complete compression stream and
+    /// the uncompressed data must fit into `output`.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if:
+    ///
+    /// * `input` is corrupted
+    /// * memory allocation failed
+    /// * `output` is not large enough to hold uncompressed `input`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{compress, BrotliDecoder, CompressionMode, Quality, WindowSize};
+    ///
+    /// let input = vec![0; 1024];
+    /// let mut encoded = vec![0; 1024];
+    /// let mut decoded = vec![0; 1024];
+    ///
+    /// let bytes_written = compress(
+    ///     &input,
+    ///     &mut encoded,
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let bytes_written = BrotliDecoder::decompress_oneshot(&encoded[..bytes_written], &mut decoded)?;
+    ///
+    /// assert_eq!(input, &decoded[..bytes_written]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`decompress`]: crate::decompress
+    pub fn decompress_oneshot(input: &[u8], output: &mut [u8]) -> Result<usize, DecompressError> {
+        crate::decompress(input, output)
+    }
+
+    /// Returns the version of the C brotli decoder library.
+    ///
+    /// The version is encoded as `(major << 24) | (minor << 12) | patch`. Use
+    /// [`decoder_library_version`] to get the unpacked version instead.
+    ///
+    /// [`decoder_library_version`]: crate::decoder_library_version
+    #[doc(alias = "BrotliDecoderVersion")]
+    pub fn version() -> u32 {
+        unsafe { BrotliDecoderVersion() }
+    }
+
+    fn set_param(
+        &mut self,
+        param: BrotliDecoderParameter,
+        value: u32,
+    ) -> Result<(), SetParameterError> {
+        let r = unsafe { BrotliDecoderSetParameter(self.state, param, value) };
+
+        if r == 0 {
+            return Err(SetParameterError::Generic);
+        }
+
+        match self.params.iter_mut().find(|(key, _)| *key == param) {
+            Some((_, old)) => *old = value,
+            None => self.params.push((param, value)),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the raw error code of the decoder.
+    ///
+    /// The returned value is only meaningful after a failed call to
+    /// [`Self::decompress`] and as long as the decoder has not been reset
+    /// since. Negative values indicate an error, the corresponding
+    /// [`DecodeError`] was already returned by the failed call.
+    #[doc(alias = "BrotliDecoderGetErrorCode")]
+    pub fn last_error_code(&self) -> BrotliDecoderErrorCode {
+        unsafe { BrotliDecoderGetErrorCode(self.state) }
+    }
+
+    /// Returns the error that caused the last call to [`Self::decompress`] to
+    /// fail.
+    ///
+    /// This allows recovering the cause of an error after it was converted,
+    /// e.g. into an [`io::Error`] by [`DecompressorWriter`]. Returns `None` if
+    /// no error occurred or the decoder has been reset since.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoder;
+    ///
+    /// let mut decoder = BrotliDecoder::new();
+    /// assert_eq!(decoder.last_error(), None);
+    ///
+    /// let err = decoder.decompress(&[0xff; 16], &mut [0; 16]).unwrap_err();
+    /// assert_eq!(decoder.last_error(), Some(err));
+    /// ```
+    #[doc(alias = "BrotliDecoderGetErrorCode")]
+    pub fn last_error(&self) -> Option<DecodeError> {
+        if self.last_error_code() < 0 {
+            Some(self.decode_error())
+        } else {
+            None
+        }
+    }
+
+    fn decode_error(&self) -> DecodeError {
+        let ec = self.last_error_code();
+
+        #[allow(non_upper_case_globals)]
+        match ec {
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_EXUBERANT_NIBBLE => {
+                DecodeError::FormatExuberantNibble
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_RESERVED => {
+                DecodeError::FormatReserved
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_EXUBERANT_META_NIBBLE => {
+                DecodeError::FormatExuberantMetaNibble
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_SIMPLE_HUFFMAN_ALPHABET => {
+                DecodeError::FormatSimpleHuffmanAlphabet
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_SIMPLE_HUFFMAN_SAME => {
+                DecodeError::FormatSimpleHuffmanSame
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_CL_SPACE => {
+                DecodeError::FormatClSpace
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_HUFFMAN_SPACE => {
+                DecodeError::FormatHuffmanSpace
+            }
+            BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_CONTEXT_MAP_REPEAT => {
                 DecodeError::FormatContextMapRepeat
             }
             BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_FORMAT_BLOCK_LENGTH_1 => {
@@ -270,6 +1042,24 @@ impl Default for BrotliDecoder {
     }
 }
 
+#[cfg(feature = "std")]
+impl Read for BrotliDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(output) = unsafe { self.take_output_at_most(buf.len()) } {
+            buf[..output.len()].copy_from_slice(output);
+            Ok(output.len())
+        } else if self.is_finished() {
+            Ok(0)
+        } else {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
 impl Drop for BrotliDecoder {
     fn drop(&mut self) {
         unsafe {
@@ -278,6 +1068,83 @@ impl Drop for BrotliDecoder {
     }
 }
 
+/// A set of dictionaries that can be attached to many decoders.
+///
+/// The dictionary data is immutable once added and is reference counted, so
+/// attaching it to a decoder via [`BrotliDecoder::attach_shared_dictionary`]
+/// does not copy it. A `SharedDictionary` is [`Send`] and [`Sync`] and can be
+/// shared between threads, for example by wrapping it in an [`Arc`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use brotlic::decode::SharedDictionary;
+/// use brotlic::{BrotliDecoder, SharedDictionaryType};
+///
+/// let mut dictionary = SharedDictionary::new();
+/// dictionary.add(SharedDictionaryType::Raw, b"hello world")?;
+///
+/// let dictionary = Arc::new(dictionary);
+/// let handle = thread::spawn({
+///     let dictionary = Arc::clone(&dictionary);
+///     move || BrotliDecoder::new().attach_shared_dictionary(&dictionary)
+/// });
+///
+/// handle.join().unwrap()?;
+/// # Ok::<(), brotlic::DictionaryError>(())
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SharedDictionary {
+    dictionaries: Vec<(SharedDictionaryType, Arc<[u8]>)>,
+}
+
+impl SharedDictionary {
+    /// Creates a new empty set of dictionaries.
+    pub fn new() -> Self {
+        SharedDictionary {
+            dictionaries: Vec::new(),
+        }
+    }
+
+    /// Adds a dictionary to the set.
+    ///
+    /// Raw LZ77 prefix dictionaries may be added multiple times, whereas at
+    /// most one serialized dictionary may be added. The `data` is copied once
+    /// and then shared by every decoder the set is attached to.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `data` is empty or if a serialized
+    /// dictionary was already added.
+    pub fn add(
+        &mut self,
+        dict_type: SharedDictionaryType,
+        data: &[u8],
+    ) -> Result<&mut Self, DictionaryError> {
+        if data.is_empty() {
+            return Err(DictionaryError::Empty);
+        }
+
+        let serialized = dict_type == SharedDictionaryType::Serialized;
+
+        if serialized
+            && self
+                .dictionaries
+                .iter()
+                .any(|(t, _)| *t == SharedDictionaryType::Serialized)
+        {
+            return Err(DictionaryError::TooManySerializedDictionaries);
+        }
+
+        self.dictionaries.push((dict_type, Arc::from(data)));
+
+        Ok(self)
+    }
+}
+
 /// Decompression options to be used for a [`BrotliDecoder`].
 ///
 /// # Examples
@@ -286,12 +1153,20 @@ impl Drop for BrotliDecoder {
 /// ```
 /// use brotlic::BrotliDecoderOptions;
 ///
-/// let encoder = BrotliDecoderOptions::new().large_window_size(true).build();
+/// let decoder = BrotliDecoderOptions::new()
+///     .with_large_window_size(true)
+///     .build()?;
+/// # Ok::<(), brotlic::SetParameterError>(())
 /// ```
-#[derive(Debug, Clone)]
+///
+/// Every setter has a consuming `with_*` variant that takes and returns the
+/// options by value, next to the setters taking `&mut self`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct BrotliDecoderOptions {
     disable_ring_buffer_reallocation: Option<bool>,
     large_window_size: Option<bool>,
+    max_window_bits: Option<u8>,
+    dictionaries: Vec<SharedDictionary>,
 }
 
 impl BrotliDecoderOptions {
@@ -303,13 +1178,65 @@ impl BrotliDecoderOptions {
         BrotliDecoderOptions {
             disable_ring_buffer_reallocation: None,
             large_window_size: None,
+            max_window_bits: None,
+            dictionaries: Vec::new(),
         }
     }
 
+    /// Creates decoder options suitable for decompressing untrusted input.
+    ///
+    /// The ring buffer is allocated upfront according to the window size of
+    /// the stream, and streams that request a window larger than
+    /// `max_window_bits` are rejected with [`DecodeError::FormatWindowBits`].
+    /// This bounds the memory used by the decoder to approximately
+    /// `1 << max_window_bits` bytes, at the cost of rejecting streams that were
+    /// compressed with a larger window size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoderOptions;
+    ///
+    /// // limit the decoder to a window of approximately 64 KiB
+    /// let decoder = BrotliDecoderOptions::for_untrusted_input(16).build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub fn for_untrusted_input(max_window_bits: u8) -> Self {
+        BrotliDecoderOptions::new()
+            .with_disable_ring_buffer_reallocation(true)
+            .with_max_window_bits(max_window_bits)
+    }
+
+    /// Creates decoder options for decompressing HTTP responses.
+    ///
+    /// Disables the large window brotli extension, which is not part of RFC
+    /// 7932 and therefore never used by conforming HTTP servers. All other
+    /// settings keep their default values. Counterpart to
+    /// [`BrotliEncoderOptions::for_http_response`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoderOptions;
+    ///
+    /// let decoder = BrotliDecoderOptions::for_http_response().build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [`BrotliEncoderOptions::for_http_response`]: crate::BrotliEncoderOptions::for_http_response
+    pub fn for_http_response() -> Self {
+        BrotliDecoderOptions::new().with_large_window_size(false)
+    }
+
     /// Disable "canny" ring buffer allocation strategy.
     ///
+    /// Corresponds to the
+    /// `BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION` decoder
+    /// parameter.
+    ///
     /// Ring buffer is allocated according to window size, despite the real size
     /// of the content.
+    #[doc(alias = "BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION")]
     pub fn disable_ring_buffer_reallocation(
         &mut self,
         disable_ring_buffer_reallocation: bool,
@@ -324,12 +1251,125 @@ impl BrotliDecoderOptions {
     /// specification, this flag must be enabled. For more information see
     /// [`LargeWindowSize`].
     ///
+    /// Corresponds to the `BROTLI_DECODER_PARAM_LARGE_WINDOW` decoder
+    /// parameter.
+    ///
     /// [`LargeWindowSize`]: crate::LargeWindowSize
+    #[doc(alias = "BROTLI_DECODER_PARAM_LARGE_WINDOW")]
     pub fn large_window_size(&mut self, large_window_size: bool) -> &mut Self {
         self.large_window_size = Some(large_window_size);
         self
     }
 
+    /// Rejects streams whose window size exceeds `max_window_bits`.
+    ///
+    /// The window size is read from the stream header before any data is
+    /// decompressed. Must be in the range of a [`WindowSize`] and cannot be
+    /// combined with [`Self::large_window_size`].
+    ///
+    /// [`WindowSize`]: crate::WindowSize
+    pub fn max_window_bits(&mut self, max_window_bits: u8) -> &mut Self {
+        self.max_window_bits = Some(max_window_bits);
+        self
+    }
+
+    /// Attaches the dictionaries of `dictionary` to decoders built from these
+    /// options.
+    ///
+    /// May be called multiple times to attach multiple sets of dictionaries.
+    /// The dictionary data is shared, not copied. See
+    /// [`BrotliDecoder::attach_shared_dictionary`] for more information.
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn dictionary(&mut self, dictionary: &SharedDictionary) -> &mut Self {
+        self.dictionaries.push(dictionary.clone());
+        self
+    }
+
+    /// Attaches a raw LZ77 prefix dictionary to decoders built from these
+    /// options.
+    ///
+    /// The `data` is copied once and shared by every decoder built from these
+    /// options. See [`BrotliDecoder::attach_raw_dictionary`] for more
+    /// information.
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn raw_dictionary(&mut self, data: &[u8]) -> &mut Self {
+        self.single_dictionary(SharedDictionaryType::Raw, data)
+    }
+
+    /// Attaches a serialized dictionary to decoders built from these options.
+    ///
+    /// The `data` is copied once and shared by every decoder built from these
+    /// options. See [`BrotliDecoder::attach_serialized_dictionary`] for more
+    /// information.
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn serialized_dictionary(&mut self, data: &[u8]) -> &mut Self {
+        self.single_dictionary(SharedDictionaryType::Serialized, data)
+    }
+
+    fn single_dictionary(&mut self, dict_type: SharedDictionaryType, data: &[u8]) -> &mut Self {
+        self.dictionaries.push(SharedDictionary {
+            dictionaries: vec![(dict_type, Arc::from(data))],
+        });
+        self
+    }
+
+    /// Consuming variant of
+    /// [`disable_ring_buffer_reallocation`](Self::disable_ring_buffer_reallocation).
+    #[doc(alias = "BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION")]
+    pub fn with_disable_ring_buffer_reallocation(
+        mut self,
+        disable_ring_buffer_reallocation: bool,
+    ) -> Self {
+        self.disable_ring_buffer_reallocation(disable_ring_buffer_reallocation);
+        self
+    }
+
+    /// Consuming variant of [`large_window_size`](Self::large_window_size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliDecoderOptions;
+    ///
+    /// let decoder = BrotliDecoderOptions::new()
+    ///     .with_large_window_size(true)
+    ///     .build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BROTLI_DECODER_PARAM_LARGE_WINDOW")]
+    pub fn with_large_window_size(mut self, large_window_size: bool) -> Self {
+        self.large_window_size(large_window_size);
+        self
+    }
+
+    /// Consuming variant of [`max_window_bits`](Self::max_window_bits).
+    pub fn with_max_window_bits(mut self, max_window_bits: u8) -> Self {
+        self.max_window_bits(max_window_bits);
+        self
+    }
+
+    /// Consuming variant of [`dictionary`](Self::dictionary).
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn with_dictionary(mut self, dictionary: &SharedDictionary) -> Self {
+        self.dictionary(dictionary);
+        self
+    }
+
+    /// Consuming variant of [`raw_dictionary`](Self::raw_dictionary).
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn with_raw_dictionary(mut self, data: &[u8]) -> Self {
+        self.raw_dictionary(data);
+        self
+    }
+
+    /// Consuming variant of
+    /// [`serialized_dictionary`](Self::serialized_dictionary).
+    #[doc(alias = "BrotliDecoderAttachDictionary")]
+    pub fn with_serialized_dictionary(mut self, data: &[u8]) -> Self {
+        self.serialized_dictionary(data);
+        self
+    }
+
     /// Creates a brotli decoder using the specified settings.
     ///
     /// # Errors
@@ -345,21 +1385,104 @@ impl BrotliDecoderOptions {
         Ok(decoder)
     }
 
-    fn configure(&self, decoder: &mut BrotliDecoder) -> Result<(), SetParameterError> {
-        if let Some(disable_ring_buffer_reallocation) = self.disable_ring_buffer_reallocation {
-            let key = BrotliDecoderParameter_BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION;
-            let value = disable_ring_buffer_reallocation as u32;
+    /// Creates a brotli decoder using the specified settings and allocator
+    /// `alloc`.
+    ///
+    /// The allocator is kept alive for as long as the decoder exists.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::alloc::System;
+    ///
+    /// use brotlic::BrotliDecoderOptions;
+    ///
+    /// let decoder = BrotliDecoderOptions::new()
+    ///     .with_large_window_size(true)
+    ///     .build_in(System)?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BrotliDecoderCreateInstance")]
+    pub fn build_in<A: GlobalAlloc + Send + Sync + 'static>(
+        &self,
+        alloc: A,
+    ) -> Result<BrotliDecoder, SetParameterError> {
+        let mut decoder = BrotliDecoder::new_in(alloc);
 
-            decoder.set_param(key, value)?;
-        }
+        self.configure(&mut decoder)?;
 
-        if let Some(large_window_size) = self.large_window_size {
-            let key = BrotliDecoderParameter_BROTLI_DECODER_PARAM_LARGE_WINDOW;
+        Ok(decoder)
+    }
+
+    /// Checks the settings without creating a decoder.
+    ///
+    /// Performs the same parameter validation as [`build`](Self::build).
+    /// Dictionaries are only checked when they are attached to a decoder, so
+    /// `build` may still fail if the options contain dictionaries.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliDecoderOptions, SetParameterError};
+    ///
+    /// let options = BrotliDecoderOptions::for_untrusted_input(25);
+    ///
+    /// assert_eq!(
+    ///     options.validate(),
+    ///     Err(SetParameterError::InvalidWindowSize)
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), SetParameterError> {
+        self.validate_params()
+    }
+
+    fn validate_params(&self) -> Result<(), SetParameterError> {
+        if let Some(max_window_bits) = self.max_window_bits {
+            if WindowSize::new(max_window_bits).is_err() || self.large_window_size == Some(true) {
+                return Err(SetParameterError::InvalidWindowSize);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn configure(&self, decoder: &mut BrotliDecoder) -> Result<(), SetParameterError> {
+        self.validate_params()?;
+
+        if let Some(max_window_bits) = self.max_window_bits {
+            decoder.max_window_bits = Some(max_window_bits);
+        }
+
+        if let Some(disable_ring_buffer_reallocation) = self.disable_ring_buffer_reallocation {
+            let key = BrotliDecoderParameter_BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION;
+            let value = disable_ring_buffer_reallocation as u32;
+
+            decoder.set_param(key, value)?;
+        }
+
+        if let Some(large_window_size) = self.large_window_size {
+            let key = BrotliDecoderParameter_BROTLI_DECODER_PARAM_LARGE_WINDOW;
             let value = large_window_size as u32;
 
             decoder.set_param(key, value)?;
         }
 
+        for dictionary in &self.dictionaries {
+            decoder
+                .attach_shared_dictionary(dictionary)
+                .map_err(|_| SetParameterError::InvalidDictionary)?;
+        }
+
         Ok(())
     }
 }
@@ -370,6 +1493,20 @@ impl Default for BrotliDecoderOptions {
     }
 }
 
+/// Reads the window size from the first byte of a stream header as specified
+/// by RFC7932 section 9.1.
+fn stream_window_bits(header: u8) -> u8 {
+    if header & 0x01 == 0 {
+        16
+    } else if (header >> 1) & 0x07 != 0 {
+        17 + ((header >> 1) & 0x07)
+    } else if (header >> 4) & 0x07 != 0 {
+        8 + ((header >> 4) & 0x07)
+    } else {
+        17
+    }
+}
+
 /// A struct used by [`BrotliDecoder::decompress`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct DecodeResult {
@@ -377,6 +1514,9 @@ pub struct DecodeResult {
     pub bytes_read: usize,
     /// The number of bytes written to `output`.
     pub bytes_written: usize,
+    /// Whether the decoder has more output pending in its internal buffer.
+    /// Equivalent to calling [`BrotliDecoder::has_output`] after decompressing.
+    pub pending_output: bool,
     /// Information the decoder gave on whether its finished or needs more input
     /// or output.
     pub info: DecoderInfo,
@@ -434,6 +1574,7 @@ pub enum DecodeError {
     Unreachable = BrotliDecoderErrorCode_BROTLI_DECODER_ERROR_UNREACHABLE as isize,
 }
 
+#[cfg(feature = "std")]
 impl Error for DecodeError {}
 
 impl fmt::Display for DecodeError {
@@ -455,12 +1596,61 @@ impl fmt::Display for DecodeError {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<DecodeError> for io::Error {
     fn from(err: DecodeError) -> Self {
         io::Error::new(io::ErrorKind::Other, err)
     }
 }
 
+/// An error returned by [`BrotliDecoder::decompress_to_string`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DecodeToStringError {
+    /// The compressed input could not be decoded.
+    Decode(DecodeError),
+    /// The decompressed data is not valid UTF-8.
+    Utf8(Utf8Error),
+}
+
+#[cfg(feature = "std")]
+impl Error for DecodeToStringError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeToStringError::Decode(err) => Some(err),
+            DecodeToStringError::Utf8(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for DecodeToStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeToStringError::Decode(err) => err.fmt(f),
+            DecodeToStringError::Utf8(err) => write!(f, "decompressed data is not utf-8: {}", err),
+        }
+    }
+}
+
+impl From<DecodeError> for DecodeToStringError {
+    fn from(err: DecodeError) -> Self {
+        DecodeToStringError::Decode(err)
+    }
+}
+
+impl From<Utf8Error> for DecodeToStringError {
+    fn from(err: Utf8Error) -> Self {
+        DecodeToStringError::Utf8(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<DecodeToStringError> for io::Error {
+    fn from(err: DecodeToStringError) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
 /// Wraps a reader and decompresses its output.
 ///
 /// # Examples
@@ -483,23 +1673,117 @@ impl From<DecodeError> for io::Error {
 ///
 /// # Ok::<(), std::io::Error>(())
 /// ```
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct DecompressorReader<R: BufRead> {
+pub struct DecompressorReader<R, S: StreamMode = SingleStream> {
     inner: R,
     decoder: BrotliDecoder,
+    stats: DecoderStats,
+    stream_mode: PhantomData<S>,
 }
 
-impl<R: BufRead> DecompressorReader<R> {
+/// A [`DecompressorReader`] that decompresses any number of concatenated
+/// streams.
+///
+/// See [`DecompressorReader::multi_stream`].
+#[cfg(feature = "std")]
+pub type MultiStreamDecompressorReader<R> = DecompressorReader<R, MultiStream>;
+
+#[cfg(feature = "std")]
+impl<R> DecompressorReader<R> {
     /// Creates a new `DecompressorReader<R>` with a newly created decoder.
     ///
     /// # Panics
     ///
     /// Panics if the decoder fails to be allocated or initialized
     pub fn new(inner: R) -> Self {
-        DecompressorReader {
-            inner,
-            decoder: BrotliDecoder::new(),
-        }
+        DecompressorReader::create(BrotliDecoder::new(), inner)
+    }
+
+    /// Creates a new `DecompressorReader<R>` with a newly created decoder
+    /// using the custom allocator `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(inner: R, alloc: A) -> Self {
+        DecompressorReader::create(BrotliDecoder::new_in(alloc), inner)
+    }
+
+    /// Creates a new `DecompressorReader<R, MultiStream>` that decompresses
+    /// any number of concatenated streams.
+    ///
+    /// Once a stream is finished and the underlying reader has more input, the
+    /// decoder is [reset] and the input is decompressed as a new stream. The
+    /// parameters and dictionaries of the decoder are kept across streams.
+    /// Reading only returns `Ok(0)` once a stream finished at the end of the
+    /// underlying reader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    ///
+    /// use brotlic::{CompressorWriter, DecompressorReader};
+    ///
+    /// let mut concatenated = Vec::new();
+    ///
+    /// for part in [&b"hello "[..], &b"world"[..]] {
+    ///     let mut compressor = CompressorWriter::new(Vec::new());
+    ///     compressor.write_all(part)?;
+    ///     concatenated.extend(compressor.into_inner()?);
+    /// }
+    ///
+    /// let mut decompressor = DecompressorReader::multi_stream(concatenated.as_slice());
+    /// let mut decompressed = String::new();
+    /// decompressor.read_to_string(&mut decompressed)?;
+    ///
+    /// assert_eq!(decompressed, "hello world");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [reset]: BrotliDecoder::reset
+    pub fn multi_stream(inner: R) -> DecompressorReader<R, MultiStream> {
+        DecompressorReader::create(BrotliDecoder::new(), inner)
+    }
+
+    /// Creates a new `DecompressorReader<R, MultiStream>` with a specified
+    /// decoder that decompresses any number of concatenated streams.
+    ///
+    /// Works like [`multi_stream`], except that every stream is decoded using
+    /// the parameters, window size limit and dictionaries of `decoder`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// use brotlic::{BrotliDecoderOptions, DecompressorReader};
+    ///
+    /// let decoder = BrotliDecoderOptions::for_untrusted_input(22).build()?;
+    ///
+    /// let source = [11, 2, 128, 104, 101, 108, 108, 111, 3]; // decompresses to "hello"
+    /// let concatenated = [source, source].concat();
+    ///
+    /// let mut decompressor =
+    ///     DecompressorReader::multi_stream_with_decoder(decoder, concatenated.as_slice());
+    /// let mut decompressed = String::new();
+    /// decompressor.read_to_string(&mut decompressed)?;
+    ///
+    /// assert_eq!(decompressed, "hellohello");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`multi_stream`]: DecompressorReader::multi_stream
+    pub fn multi_stream_with_decoder(
+        decoder: BrotliDecoder,
+        inner: R,
+    ) -> DecompressorReader<R, MultiStream> {
+        DecompressorReader::create(decoder, inner)
     }
 
     /// Creates a new `DecompressorReader<R>` with a specified decoder.
@@ -512,7 +1796,7 @@ impl<R: BufRead> DecompressorReader<R> {
     /// use brotlic::{BrotliDecoderOptions, DecompressorReader};
     ///
     /// let decoder = BrotliDecoderOptions::new()
-    ///     .disable_ring_buffer_reallocation(true)
+    ///     .with_disable_ring_buffer_reallocation(true)
     ///     .build()?;
     ///
     /// let source = [11, 2, 128, 104, 101, 108, 108, 111, 3]; // decompresses to "hello"
@@ -520,7 +1804,19 @@ impl<R: BufRead> DecompressorReader<R> {
     /// # Ok::<(), brotlic::SetParameterError>(())
     /// ```
     pub fn with_decoder(decoder: BrotliDecoder, inner: R) -> Self {
-        DecompressorReader { inner, decoder }
+        DecompressorReader::create(decoder, inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R, S: StreamMode> DecompressorReader<R, S> {
+    fn create(decoder: BrotliDecoder, inner: R) -> Self {
+        DecompressorReader {
+            inner,
+            decoder,
+            stats: DecoderStats::default(),
+            stream_mode: PhantomData,
+        }
     }
 
     /// Gets a reference to the underlying reader
@@ -535,13 +1831,86 @@ impl<R: BufRead> DecompressorReader<R> {
         &mut self.inner
     }
 
+    /// Gets a reference to the underlying decoder.
+    ///
+    /// This can be used to inspect the state of the decoder, e.g. whether it
+    /// is finished, while the reader is in use.
+    pub fn get_decoder(&self) -> &BrotliDecoder {
+        &self.decoder
+    }
+
+    /// Gets a mutable reference to the underlying decoder.
+    ///
+    /// It is inadvisable to directly use the underlying decoder. Giving it
+    /// input or taking its output breaks the reader, as the decompressed stream
+    /// would then be incomplete or corrupted.
+    pub fn get_decoder_mut(&mut self) -> &mut BrotliDecoder {
+        &mut self.decoder
+    }
+
+    /// Returns the total number of decompressed bytes produced by the decoder.
+    ///
+    /// See [`BrotliDecoder::total_out`].
+    pub fn total_out(&self) -> usize {
+        self.decoder.total_out()
+    }
+
+    /// Returns statistics about the decompression performed by this reader.
+    ///
+    /// The statistics accumulate over the lifetime of the reader, also across
+    /// concatenated streams in [`MultiStream`] mode. As [`into_parts`] does not
+    /// decompress any further, the statistics obtained right before calling
+    /// it are final.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// use brotlic::{CompressionMode, DecompressorReader, Quality, WindowSize};
+    ///
+    /// let compressed = brotlic::compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    /// let mut decompressed = Vec::new();
+    /// decompressor.read_to_end(&mut decompressed)?;
+    ///
+    /// let stats = *decompressor.stats();
+    /// assert_eq!(stats.bytes_in, compressed.len() as u64);
+    /// assert_eq!(stats.bytes_out, 11);
+    /// assert_eq!(stats.finish_count, 1);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`into_parts`]: Self::into_parts
+    pub fn stats(&self) -> &DecoderStats {
+        &self.stats
+    }
+
+    /// Checks if the decompression stream has been fully decoded.
+    ///
+    /// Once [`read`] returned `Ok(0)` for a non-empty buffer, the stream is
+    /// finished and this returns `true`. In [`MultiStream`] mode, this refers
+    /// to the stream currently being decoded.
+    ///
+    /// [`read`]: DecompressorReader::read
+    #[doc(alias = "BrotliDecoderIsFinished")]
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
     /// Unwraps this `DecompressorReader<R>`, returning the underlying reader.
     ///
     /// # Errors
     ///
     /// An [`Err`] will be returned if the decompression stream has not been
     /// finished.
-    pub fn into_inner(self) -> Result<R, IntoInnerError<DecompressorReader<R>>> {
+    pub fn into_inner(self) -> Result<R, IntoInnerError<DecompressorReader<R, S>>> {
         if self.decoder.is_finished() {
             Ok(self.inner)
         } else {
@@ -560,36 +1929,128 @@ impl<R: BufRead> DecompressorReader<R> {
     pub fn into_parts(self) -> (R, BrotliDecoder) {
         (self.inner, self.decoder)
     }
+
+    /// Decompresses `input` into `buf`, returning the number of bytes consumed
+    /// and, once reading should return, the number of bytes written.
+    fn read_step(
+        decoder: &mut BrotliDecoder,
+        stats: &mut DecoderStats,
+        input: &[u8],
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Option<usize>)> {
+        let eof = input.is_empty();
+
+        if S::MULTI && decoder.is_finished() {
+            if eof {
+                return Ok((0, Some(0)));
+            }
+
+            decoder.restart();
+        }
+
+        let finished = decoder.is_finished();
+        let DecodeResult {
+            bytes_read,
+            bytes_written,
+            info,
+            ..
+        } = decoder.decompress(input, buf)?;
+
+        stats.bytes_in += bytes_read as u64;
+        stats.bytes_out += bytes_written as u64;
+
+        if !finished && decoder.is_finished() {
+            stats.finish_count += 1;
+        }
+
+        let written = match info {
+            _ if bytes_written > 0 => Some(bytes_written),
+            // look for a concatenated stream before reporting the end
+            DecoderInfo::Finished if S::MULTI && !buf.is_empty() => None,
+            DecoderInfo::Finished => Some(0),
+            DecoderInfo::NeedsMoreInput if eof => {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            DecoderInfo::NeedsMoreInput => None,
+            DecoderInfo::NeedsMoreOutput if buf.is_empty() => Some(0),
+            DecoderInfo::NeedsMoreOutput => panic!(
+                "decoder needs output despite not giving any while having the chance to do so"
+            ),
+        };
+
+        Ok((bytes_read, written))
+    }
 }
 
-impl<R: BufRead> Read for DecompressorReader<R> {
+#[cfg(feature = "std")]
+impl<R: BufRead, S: StreamMode> Read for DecompressorReader<R, S> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         loop {
             let input = self.inner.fill_buf()?;
-            let eof = input.is_empty();
-            let DecodeResult {
-                bytes_read,
-                bytes_written,
-                info,
-            } = self.decoder.decompress(input, buf)?;
+            let (bytes_read, written) =
+                Self::read_step(&mut self.decoder, &mut self.stats, input, buf)?;
             self.inner.consume(bytes_read);
 
-            match info {
-                _ if bytes_written > 0 => return Ok(bytes_written),
-                DecoderInfo::Finished => return Ok(0),
-                DecoderInfo::NeedsMoreInput if eof => {
-                    return Err(io::ErrorKind::UnexpectedEof.into());
-                }
-                DecoderInfo::NeedsMoreInput => continue,
-                DecoderInfo::NeedsMoreOutput if buf.is_empty() => return Ok(0),
-                DecoderInfo::NeedsMoreOutput => panic!(
-                    "decoder needs output despite not giving any while having the chance to do so"
-                ),
-            };
+            if let Some(bytes_written) = written {
+                return Ok(bytes_written);
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<R, S: StreamMode> DecompressorReader<R, S> {
+    fn poll_read_with<F>(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>
+    where
+        R: AsyncBufRead<F>,
+    {
+        loop {
+            let input = ready!(self.inner.poll_fill_buf(cx))?;
+            let (bytes_read, written) =
+                Self::read_step(&mut self.decoder, &mut self.stats, input, buf)?;
+            self.inner.consume(bytes_read);
+
+            if let Some(bytes_written) = written {
+                return Poll::Ready(Ok(bytes_written));
+            }
         }
     }
 }
 
+#[cfg(feature = "tokio")]
+impl<R, S> tokio::io::AsyncRead for DecompressorReader<R, S>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    S: StreamMode + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let bytes_written = ready!(this.poll_read_with(cx, buf.initialize_unfilled()))?;
+
+        buf.advance(bytes_written);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R, S> futures_io::AsyncRead for DecompressorReader<R, S>
+where
+    R: futures_io::AsyncBufRead + Unpin,
+    S: StreamMode + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_with(cx, buf)
+    }
+}
+
 /// Wraps a writer and decompresses its output.
 ///
 /// `DecompressorWriter<R>` wraps a writer and adds brotli decompression to the
@@ -614,26 +2075,155 @@ impl<R: BufRead> Read for DecompressorReader<R> {
 /// # Ok::<(), io::Error>(())
 /// ```
 ///
+/// With the `tokio` or `futures-io` feature enabled, `DecompressorWriter<W>`
+/// implements the respective `AsyncWrite` trait for writers implementing it.
+/// Shutting the writer down fails if the decompression stream has not been
+/// finished.
+///
 /// [`CompressorWriter`]: crate::encode::CompressorWriter
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct DecompressorWriter<W: Write> {
+pub struct DecompressorWriter<W, S: StreamMode = SingleStream> {
     inner: W,
     decoder: BrotliDecoder,
     panicked: bool,
+    stats: DecoderStats,
+    finished: bool,
+    stream_mode: PhantomData<S>,
+    buffer: Option<Box<OutputBuffer>>,
+    buffer_flush: Option<fn(&mut Self) -> io::Result<()>>,
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pending: PendingOutput,
 }
 
-impl<W: Write> DecompressorWriter<W> {
+/// Marker trait for the stream mode of a [`DecompressorReader`] or
+/// [`DecompressorWriter`].
+///
+/// A brotli stream has a definite end, after which a decompressor in
+/// [`SingleStream`] mode stops consuming input. In [`MultiStream`] mode, any
+/// input following the end of a stream is decompressed as a new, concatenated
+/// stream. It is implemented by [`SingleStream`] and
+/// [`MultiStream`].
+pub trait StreamMode: private::Sealed + fmt::Debug {}
+
+/// Stream mode of decompressors that decompress exactly one stream.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SingleStream {}
+
+/// Stream mode of decompressors that decompress any number of concatenated
+/// streams.
+///
+/// See [`DecompressorReader::multi_stream`] and
+/// [`DecompressorWriter::multi_stream`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MultiStream {}
+
+impl StreamMode for SingleStream {}
+impl StreamMode for MultiStream {}
+
+mod private {
+    pub trait Sealed {
+        /// Whether a new stream is started once the previous one finished.
+        const MULTI: bool;
+    }
+
+    impl Sealed for super::SingleStream {
+        const MULTI: bool = false;
+    }
+
+    impl Sealed for super::MultiStream {
+        const MULTI: bool = true;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W> DecompressorWriter<W> {
     /// Creates a new `DecompressorWriter<W>` with a newly created decoder.
     ///
     /// # Panics
     ///
     /// Panics if the decoder fails to be allocated or initialized
     pub fn new(inner: W) -> DecompressorWriter<W> {
-        DecompressorWriter {
-            inner,
-            decoder: BrotliDecoder::new(),
-            panicked: false,
-        }
+        DecompressorWriter::create(BrotliDecoder::new(), inner)
+    }
+
+    /// Creates a new `DecompressorWriter<W>` with a newly created decoder
+    /// using the custom allocator `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(inner: W, alloc: A) -> Self {
+        DecompressorWriter::create(BrotliDecoder::new_in(alloc), inner)
+    }
+
+    /// Creates a new `DecompressorWriter<W, MultiStream>` that decompresses
+    /// any number of concatenated streams.
+    ///
+    /// Once a stream is finished, any further input is decompressed as a new
+    /// stream. The decoder is reset in between, keeping its parameters and
+    /// dictionaries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{CompressorWriter, DecompressorWriter};
+    ///
+    /// let mut concatenated = Vec::new();
+    ///
+    /// for part in [&b"hello "[..], &b"world"[..]] {
+    ///     let mut compressor = CompressorWriter::new(Vec::new());
+    ///     compressor.write_all(part)?;
+    ///     concatenated.extend(compressor.into_inner()?);
+    /// }
+    ///
+    /// let mut decompressor = DecompressorWriter::multi_stream(Vec::new());
+    /// decompressor.write_all(&concatenated)?;
+    ///
+    /// assert_eq!(decompressor.into_inner()?, b"hello world");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn multi_stream(inner: W) -> DecompressorWriter<W, MultiStream> {
+        DecompressorWriter::create(BrotliDecoder::new(), inner)
+    }
+
+    /// Creates a new `DecompressorWriter<W, MultiStream>` with a specified
+    /// decoder that decompresses any number of concatenated streams.
+    ///
+    /// Works like [`multi_stream`], except that every stream is decoded using
+    /// the parameters, window size limit and dictionaries of `decoder`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{BrotliDecoderOptions, DecompressorWriter};
+    ///
+    /// let decoder = BrotliDecoderOptions::for_untrusted_input(22).build()?;
+    ///
+    /// let source = [11, 2, 128, 104, 101, 108, 108, 111, 3]; // decompresses to "hello"
+    /// let concatenated = [source, source].concat();
+    ///
+    /// let mut decompressor = DecompressorWriter::multi_stream_with_decoder(decoder, Vec::new());
+    /// decompressor.write_all(&concatenated)?;
+    ///
+    /// assert_eq!(decompressor.into_inner()?, b"hellohello");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`multi_stream`]: DecompressorWriter::multi_stream
+    pub fn multi_stream_with_decoder(
+        decoder: BrotliDecoder,
+        inner: W,
+    ) -> DecompressorWriter<W, MultiStream> {
+        DecompressorWriter::create(decoder, inner)
     }
 
     /// Creates a new `DecompressorWriter<W>` with a specified decoder.
@@ -646,17 +2236,31 @@ impl<W: Write> DecompressorWriter<W> {
     /// use brotlic::{BrotliDecoderOptions, DecompressorReader, DecompressorWriter};
     ///
     /// let decoder = BrotliDecoderOptions::new()
-    ///     .large_window_size(true)
+    ///     .with_large_window_size(true)
     ///     .build()?;
     ///
-    /// let mut writer = DecompressorWriter::with_decoder(decoder, Vec::new());
+    /// let mut writer = DecompressorWriter::with_decoder(decoder, Vec::<u8>::new());
     /// # Ok::<(), brotlic::SetParameterError>(())
     /// ```
     pub fn with_decoder(decoder: BrotliDecoder, inner: W) -> Self {
+        DecompressorWriter::create(decoder, inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W, S: StreamMode> DecompressorWriter<W, S> {
+    fn create(decoder: BrotliDecoder, inner: W) -> Self {
         DecompressorWriter {
             inner,
             decoder,
             panicked: false,
+            stats: DecoderStats::default(),
+            finished: false,
+            stream_mode: PhantomData,
+            buffer: None,
+            buffer_flush: None,
+            #[cfg(any(feature = "tokio", feature = "futures-io"))]
+            pending: PendingOutput::default(),
         }
     }
 
@@ -672,83 +2276,514 @@ impl<W: Write> DecompressorWriter<W> {
         &mut self.inner
     }
 
-    /// Unwraps this `DecompressorWriter<W>`, returning the underlying writer.
+    /// Gets a reference to the underlying decoder.
     ///
-    /// If the decompression stream is validated before finishing and will
-    /// return an [`Err`] otherwise. The `DecompressorWriter<W>` will not
-    /// overcome its input, if an adjacent second compression stream follows it
-    /// can be read by another `DecompressorWriter<W>` without length-prefixing.
+    /// This can be used to inspect the state of the decoder, e.g. whether it
+    /// is finished, while the writer is in use.
+    pub fn get_decoder(&self) -> &BrotliDecoder {
+        &self.decoder
+    }
+
+    /// Gets a mutable reference to the underlying decoder.
     ///
-    /// # Errors
+    /// It is inadvisable to directly use the underlying decoder. Giving it
+    /// input or taking its output breaks the writer, as the decompressed stream
+    /// would then be incomplete or corrupted.
+    pub fn get_decoder_mut(&mut self) -> &mut BrotliDecoder {
+        &mut self.decoder
+    }
+
+    /// Returns the error that caused decompression to fail.
     ///
-    /// An [`Err`] will be returned if the decompression stream has not been
-    /// finished.
-    pub fn into_inner(self) -> Result<W, IntoInnerError<DecompressorWriter<W>>> {
-        if self.decoder.is_finished() {
-            Ok(self.into_parts().0)
-        } else {
-            Err(IntoInnerError::new(
+    /// When writing fails because the decoder rejected the input, its error is
+    /// converted into an [`io::Error`]. This returns the original
+    /// [`DecodeError`] instead, see [`BrotliDecoder::last_error`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::DecompressorWriter;
+    ///
+    /// let mut decompressor = DecompressorWriter::new(Vec::new());
+    ///
+    /// assert!(decompressor.write_all(&[0xff; 16]).is_err());
+    /// assert!(decompressor.decode_error().is_some());
+    /// ```
+    pub fn decode_error(&self) -> Option<DecodeError> {
+        self.decoder.last_error()
+    }
+
+    /// Returns the total number of decompressed bytes produced by the decoder.
+    ///
+    /// Decompressed bytes count as produced once they have been taken from the
+    /// decoder, even if writing them to the underlying writer failed. See
+    /// [`BrotliDecoder::total_out`].
+    pub fn total_out(&self) -> usize {
+        self.decoder.total_out()
+    }
+
+    /// Returns statistics about the decompression performed by this writer.
+    ///
+    /// The statistics accumulate over the lifetime of the writer, also across
+    /// concatenated streams in [`MultiStream`] mode, and are not reset when it
+    /// is flushed. The final statistics are returned by [`into_parts`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{CompressionMode, DecompressorWriter, Quality, WindowSize};
+    ///
+    /// let compressed = brotlic::compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decompressor = DecompressorWriter::new(Vec::new());
+    /// decompressor.write_all(&compressed)?;
+    ///
+    /// let stats = *decompressor.stats();
+    /// assert_eq!(stats.bytes_in, compressed.len() as u64);
+    /// assert_eq!(stats.bytes_out, 11);
+    /// assert_eq!(stats.finish_count, 1);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`into_parts`]: Self::into_parts
+    pub fn stats(&self) -> &DecoderStats {
+        &self.stats
+    }
+
+    /// Gives `buf` to the decoder, updating the statistics.
+    fn give_input(&mut self, buf: &[u8]) -> Result<(usize, DecoderInfo), DecodeError> {
+        let (bytes_read, info) = self.decoder.give_input(buf)?;
+        self.stats.bytes_in += bytes_read as u64;
+        self.update_finished();
+
+        Ok((bytes_read, info))
+    }
+
+    /// Checks whether a stream has been started but not finished.
+    ///
+    /// In [`MultiStream`] mode, a decoder that has not been given any input
+    /// since the previous stream finished is complete.
+    fn is_incomplete(&self) -> bool {
+        !self.decoder.is_finished() && (!S::MULTI || self.decoder.is_used())
+    }
+
+    /// Counts the current stream as finished once the decoder reaches its
+    /// final state, which happens after its last output has been taken.
+    fn update_finished(&mut self) {
+        let finished = self.decoder.is_finished();
+
+        if finished && !self.finished {
+            self.stats.finish_count += 1;
+        }
+
+        self.finished = finished;
+    }
+
+    /// Unwraps this `DecompressorWriter<W>`, returning the underlying writer.
+    ///
+    /// If the decompression stream is validated before finishing and will
+    /// return an [`Err`] otherwise. The `DecompressorWriter<W>` will not
+    /// overcome its input, if an adjacent second compression stream follows it
+    /// can be read by another `DecompressorWriter<W>` without length-prefixing.
+    ///
+    /// In [`MultiStream`] mode, only the stream currently being decompressed
+    /// is validated, as there is no way to tell whether another concatenated
+    /// stream would have followed.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if the decompression stream has not been
+    /// finished.
+    // the writer is returned on error so that it can be recovered, like
+    // `std::io::BufWriter::into_inner` does
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(mut self) -> Result<W, IntoInnerError<DecompressorWriter<W, S>>> {
+        if self.is_incomplete() {
+            return Err(IntoInnerError::new(
                 self,
                 io::ErrorKind::UnexpectedEof.into(),
-            ))
+            ));
+        }
+
+        if let Some(flush) = self.buffer_flush {
+            if let Err(e) = flush(&mut self) {
+                return Err(IntoInnerError::new(self, e));
+            }
         }
+
+        Ok(self.into_parts().0)
+    }
+
+    /// Unwraps this `DecompressorWriter<W>`, returning the underlying writer
+    /// without validating that the decompression stream finished.
+    ///
+    /// This is useful to abandon a decompression stream whose output is going
+    /// to be discarded anyway, e.g. because of an error elsewhere. Output
+    /// buffered by this writer is discarded. This is equivalent to
+    /// `into_parts().0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{CompressionMode, DecompressorWriter, Quality, WindowSize};
+    ///
+    /// let compressed = brotlic::compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut decompressor = DecompressorWriter::new(Vec::new());
+    /// decompressor.write_all(&compressed[..compressed.len() / 2])?;
+    ///
+    /// let partial = decompressor.into_inner_without_finish();
+    /// assert!(partial.len() < 11);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn into_inner_without_finish(self) -> W {
+        self.into_parts().0
     }
 
     /// Disassembles this `DecompressorWriter<W>`, returning the underlying
-    /// writer and decoder.
+    /// writer, the decoder and the final statistics.
     ///
     /// If the underlying writer panicked, it is not known what portion of the
     /// data was written. In this case, we return `WriterPanicked` to get the
     /// encoder back.
     ///
     /// `into_parts` makes no attempt to validate that the decompression stream
-    /// finished and cannot fail.
-    pub fn into_parts(self) -> (W, Result<BrotliDecoder, WriterPanicked>) {
-        let inner = self.inner;
-        let decoder = self.decoder;
-
-        let decoder = if !self.panicked {
+    /// finished and cannot fail. Output buffered by a writer created with
+    /// [`with_decoder_and_capacity`] is discarded, [`flush`] the writer
+    /// beforehand to keep it.
+    ///
+    /// [`with_decoder_and_capacity`]: DecompressorWriter::with_decoder_and_capacity
+    /// [`flush`]: Write::flush
+    pub fn into_parts(self) -> (W, Result<BrotliDecoder, WriterPanicked>, DecoderStats) {
+        let inner = unsafe { ptr::read(&self.inner) };
+        let decoder = unsafe { ptr::read(&self.decoder) };
+        drop(unsafe { ptr::read(&self.buffer) });
+        #[cfg(any(feature = "tokio", feature = "futures-io"))]
+        drop(unsafe { ptr::read(&self.pending) });
+        let panicked = self.panicked;
+        let stats = self.stats;
+        mem::forget(self);
+
+        let decoder = if !panicked {
             Ok(decoder)
         } else {
             Err(WriterPanicked { decoder })
         };
 
-        (inner, decoder)
+        (inner, decoder, stats)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<W, S: StreamMode> DecompressorWriter<W, S> {
+    fn poll_flush_decoder_output<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        loop {
+            ready!(self.pending.poll_write_to(&mut self.inner, cx))?;
+
+            match unsafe { self.decoder.take_output() } {
+                Some(output) => {
+                    self.stats.bytes_out += output.len() as u64;
+                    self.pending.set(output);
+                }
+                None => {
+                    self.update_finished();
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
     }
 
+    fn poll_write_with<F>(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>
+    where
+        W: AsyncWrite<F>,
+    {
+        loop {
+            ready!(self.poll_flush_decoder_output(cx))?;
+
+            if S::MULTI && !buf.is_empty() && self.decoder.is_finished() {
+                self.decoder.restart();
+                self.finished = false;
+            }
+
+            // output is written on the next call, so that no input is consumed
+            // by a call that returns pending. If the decoder did not accept any
+            // input, its output is written first to make room for more.
+            match self.give_input(buf)? {
+                (0, DecoderInfo::NeedsMoreOutput) => {}
+                (0, DecoderInfo::Finished) if S::MULTI && !buf.is_empty() => {}
+                (bytes_read, _) => return Poll::Ready(Ok(bytes_read)),
+            }
+        }
+    }
+
+    fn poll_flush_with<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        ready!(self.poll_flush_decoder_output(cx))?;
+
+        self.inner.poll_flush(cx)
+    }
+
+    fn poll_close_with<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        ready!(self.poll_flush_decoder_output(cx))?;
+
+        if self.is_incomplete() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        self.inner.poll_close(cx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> DecompressorWriter<W> {
+    /// Creates a new `DecompressorWriter<W>` with a specified decoder that
+    /// buffers up to `capacity` bytes of decompressed output.
+    ///
+    /// Decompressed output is only written to the underlying writer once the
+    /// buffer is full, when the writer is flushed, unwrapped using
+    /// [`into_inner`] or dropped. This reduces the number of writes to the
+    /// underlying writer, which is useful if each write is expensive, such as
+    /// a write to a file or socket. A `capacity` of zero disables buffering,
+    /// which is what [`with_decoder`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{BrotliDecoder, CompressionMode, DecompressorWriter, Quality, WindowSize};
+    ///
+    /// let compressed = brotlic::compress_to_vec(
+    ///     b"hello world",
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut writer =
+    ///     DecompressorWriter::with_decoder_and_capacity(BrotliDecoder::new(), Vec::new(), 4096);
+    ///
+    /// writer.write_all(&compressed)?;
+    ///
+    /// assert!(writer.get_ref().is_empty());
+    /// assert_eq!(writer.into_inner()?, b"hello world");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`into_inner`]: DecompressorWriter::into_inner
+    /// [`with_decoder`]: DecompressorWriter::with_decoder
+    pub fn with_decoder_and_capacity(decoder: BrotliDecoder, inner: W, capacity: usize) -> Self {
+        let mut writer = DecompressorWriter::create(decoder, inner);
+        writer.buffer = OutputBuffer::new(capacity);
+
+        if writer.buffer.is_some() {
+            writer.buffer_flush = Some(Self::flush_buffer);
+        }
+
+        writer
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write, S: StreamMode> DecompressorWriter<W, S> {
     fn flush_decoder_output(&mut self) -> io::Result<()> {
-        while let Some(output) = unsafe { self.decoder.take_output() } {
-            self.panicked = true;
-            let r = self.inner.write_all(output);
-            self.panicked = false;
-            r?;
+        let total_out = self.decoder.total_out();
+        let r = self.write_decoder_output();
+        self.stats.bytes_out += (self.decoder.total_out() - total_out) as u64;
+        self.update_finished();
+        r
+    }
+
+    fn write_decoder_output(&mut self) -> io::Result<()> {
+        while let Some(buffer) = self.buffer.as_deref_mut() {
+            let spare_capacity = buffer.spare_capacity();
+
+            if spare_capacity == 0 {
+                self.flush_buffer()?;
+                continue;
+            }
+
+            match unsafe { self.decoder.take_output_at_most(spare_capacity) } {
+                Some(output) => buffer.extend(output),
+                None => return Ok(()),
+            }
         }
 
-        Ok(())
+        self.panicked = true;
+        let r = self.decoder.drain_to_writer(&mut self.inner);
+        self.panicked = false;
+        r.map(|_| ())
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        match &mut self.buffer {
+            Some(buffer) => {
+                self.panicked = true;
+                let r = buffer.write_to(&mut self.inner);
+                self.panicked = false;
+                r
+            }
+            None => Ok(()),
+        }
     }
 }
 
-impl<W: Write> Write for DecompressorWriter<W> {
+#[cfg(feature = "std")]
+impl<W: Write, S: StreamMode> Write for DecompressorWriter<W, S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let (bytes_read, _decoder_result) = self.decoder.give_input(buf)?;
-        self.flush_decoder_output()?;
+        let mut bytes_read = 0;
+
+        // the decoder may produce output from already consumed input before
+        // accepting more, keep going so a non-empty buf never yields zero.
+        loop {
+            if S::MULTI && bytes_read < buf.len() && self.decoder.is_finished() {
+                self.decoder.restart();
+                self.finished = false;
+            }
+
+            let (n, info) = self.give_input(&buf[bytes_read..])?;
+            bytes_read += n;
+            self.flush_decoder_output()?;
+
+            match info {
+                DecoderInfo::NeedsMoreOutput => {}
+                DecoderInfo::Finished if S::MULTI && bytes_read < buf.len() => {}
+                _ => return Ok(bytes_read),
+            }
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let mut bytes_read = 0;
+
+        for buf in bufs {
+            let n = self.write(buf)?;
+            bytes_read += n;
+
+            // the stream ended before the end of this slice
+            if n < buf.len() {
+                break;
+            }
+        }
 
         Ok(bytes_read)
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+
         self.inner.flush()
     }
 }
 
+#[cfg(feature = "std")]
+impl<W, S: StreamMode> Drop for DecompressorWriter<W, S> {
+    fn drop(&mut self) {
+        if let Some(flush) = self.buffer_flush {
+            if !self.panicked {
+                let _r = flush(self);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W, S> tokio::io::AsyncWrite for DecompressorWriter<W, S>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    S: StreamMode + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_with(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_with(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_with(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W, S> futures_io::AsyncWrite for DecompressorWriter<W, S>
+where
+    W: futures_io::AsyncWrite + Unpin,
+    S: StreamMode + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_with(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_with(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_with(cx)
+    }
+}
+
+/// Statistics about the decompression performed by a [`DecompressorReader`] or
+/// [`DecompressorWriter`].
+///
+/// Obtained using [`DecompressorReader::stats`] or
+/// [`DecompressorWriter::stats`].
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct DecoderStats {
+    /// Number of compressed bytes accepted by the decoder.
+    pub bytes_in: u64,
+    /// Number of decompressed bytes taken from the decoder.
+    pub bytes_out: u64,
+    /// Number of compression streams that were decoded to completion.
+    pub finish_count: u32,
+}
+
 /// Error returned from [`DecompressorWriter::into_inner`], when the underlying
 /// writer has previously panicked. Contains the decoder that was used for
 /// decompression.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct WriterPanicked {
     decoder: BrotliDecoder,
 }
 
+#[cfg(feature = "std")]
 impl WriterPanicked {
     /// Returns the decoder that was used for decompression. It is unknown what
     /// data was fed to the decoder, so simply using it to finish it is not a
@@ -758,8 +2793,10 @@ impl WriterPanicked {
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for WriterPanicked {}
 
+#[cfg(feature = "std")]
 impl fmt::Display for WriterPanicked {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(
@@ -767,3 +2804,743 @@ impl fmt::Display for WriterPanicked {
         )
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn compress_with_window(input: &[u8], window_size: crate::WindowSize) -> Vec<u8> {
+        let mut compressed =
+            vec![0; crate::compress_bound(input.len(), crate::Quality::default()).unwrap()];
+        let size = crate::compress(
+            input,
+            &mut compressed,
+            crate::Quality::default(),
+            window_size,
+            crate::CompressionMode::Generic,
+        )
+        .unwrap();
+
+        compressed.truncate(size);
+        compressed
+    }
+
+    #[test]
+    fn no_output_before_decompress() {
+        let decoder = BrotliDecoder::new();
+
+        assert!(!decoder.has_output());
+    }
+
+    fn decompress_all(decoder: &mut BrotliDecoder, mut input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !decoder.is_finished() {
+            let res = decoder.decompress(input, &mut buf).unwrap();
+
+            input = &input[res.bytes_read..];
+            output.extend_from_slice(&buf[..res.bytes_written]);
+        }
+
+        output
+    }
+
+    #[test]
+    fn reset_matches_fresh_decoder() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let compressed = compress_with_window(&input, crate::WindowSize::default());
+
+        let mut decoder = BrotliDecoder::new();
+        decoder.give_input(&compressed[..10]).unwrap();
+        decoder.reset();
+
+        assert!(!decoder.is_used());
+        assert_eq!(decompress_all(&mut decoder, &compressed), input);
+
+        decoder.reset();
+
+        assert!(!decoder.is_used());
+        assert_eq!(decompress_all(&mut decoder, &compressed), input);
+    }
+
+    #[test]
+    fn total_out_counts_taken_output() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let compressed = compress_with_window(&input, crate::WindowSize::default());
+
+        let mut decoder = BrotliDecoder::new();
+        let res = decoder.decompress(&compressed, &mut [0; 16]).unwrap();
+
+        assert_eq!(res.bytes_written, 16);
+        assert_eq!(decoder.total_out(), 16);
+        assert_eq!(
+            decompress_all(&mut decoder, &compressed[res.bytes_read..]).len(),
+            input.len() - 16
+        );
+        assert_eq!(decoder.total_out(), input.len());
+
+        decoder.reset();
+
+        assert_eq!(decoder.total_out(), 0);
+    }
+
+    #[test]
+    fn take_output_to_vec_matches_compressor_writer() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let compressed = {
+            let mut writer = crate::CompressorWriter::new(Vec::new());
+            writer.write_all(&input).unwrap();
+            writer.into_inner().unwrap()
+        };
+
+        let mut decoder = BrotliDecoder::new();
+        let mut remaining = compressed.as_slice();
+        let mut output = Vec::new();
+
+        assert!(decoder.take_output_to_vec().is_empty());
+
+        while !decoder.is_finished() {
+            let (bytes_read, _) = decoder.give_input(remaining).unwrap();
+            remaining = &remaining[bytes_read..];
+            output.extend(decoder.take_output_to_vec());
+        }
+
+        assert_eq!(output, input);
+        assert!(!decoder.has_output());
+    }
+
+    #[test]
+    fn reset_with_options_applies_options() {
+        let input = b"hello world".repeat(64);
+        let small = compress_with_window(&input, crate::WindowSize::new(16).unwrap());
+        let large = compress_with_window(&input, crate::WindowSize::new(22).unwrap());
+        let options = BrotliDecoderOptions::for_untrusted_input(16);
+
+        let mut decoder = BrotliDecoder::new_in(std::alloc::System);
+        decompress_all(&mut decoder, &small);
+        decoder.reset_with_options(&options).unwrap();
+
+        assert_eq!(decompress_all(&mut decoder, &small), input);
+
+        decoder.reset_with_options(&options).unwrap();
+
+        assert_eq!(
+            decoder.decompress(&large, &mut [0; 1024]).unwrap_err(),
+            DecodeError::FormatWindowBits
+        );
+    }
+
+    #[test]
+    fn is_used_after_decompress() {
+        let mut decoder = BrotliDecoder::new();
+        let mut output = [0; 16];
+
+        assert!(!decoder.is_used());
+
+        let result = decoder.decompress(&[0x0B], &mut output).unwrap();
+
+        assert_eq!(result.bytes_read, 1);
+        assert!(decoder.is_used());
+    }
+
+    #[test]
+    fn stream_window_bits_matches_encoder() {
+        let input = vec![0xAB; 4096];
+
+        for bits in 10..=24 {
+            let window_size = crate::WindowSize::new(bits).unwrap();
+            let compressed = compress_with_window(&input, window_size);
+
+            assert_eq!(stream_window_bits(compressed[0]), bits);
+        }
+    }
+
+    #[test]
+    fn untrusted_input_limits_window_size() {
+        let input = vec![0xAB; 4096];
+        let compressed = compress_with_window(&input, crate::WindowSize::new(22).unwrap());
+        let mut output = vec![0; input.len()];
+
+        let mut decoder = BrotliDecoderOptions::for_untrusted_input(16)
+            .build()
+            .unwrap();
+        assert_eq!(
+            decoder.decompress(&compressed, &mut output),
+            Err(DecodeError::FormatWindowBits)
+        );
+
+        let mut decoder = BrotliDecoderOptions::for_untrusted_input(22)
+            .build()
+            .unwrap();
+        let res = decoder.decompress(&compressed, &mut output).unwrap();
+        assert_eq!(res.info, DecoderInfo::Finished);
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn consuming_setters_match_mut_setters() {
+        let mut expected = BrotliDecoderOptions::new();
+        expected
+            .disable_ring_buffer_reallocation(true)
+            .large_window_size(false)
+            .max_window_bits(16);
+
+        let options = BrotliDecoderOptions::new()
+            .with_disable_ring_buffer_reallocation(true)
+            .with_large_window_size(false)
+            .with_max_window_bits(16);
+
+        assert_eq!(options, expected);
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn options_as_hash_map_key() {
+        use std::collections::HashMap;
+
+        let mut cache = HashMap::new();
+        cache.insert(BrotliDecoderOptions::new(), "default");
+        cache.insert(BrotliDecoderOptions::for_untrusted_input(16), "untrusted");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache[&BrotliDecoderOptions::for_untrusted_input(16)],
+            "untrusted"
+        );
+    }
+
+    #[test]
+    fn untrusted_input_rejects_invalid_limits() {
+        assert!(BrotliDecoderOptions::for_untrusted_input(9)
+            .build()
+            .is_err());
+        assert!(BrotliDecoderOptions::for_untrusted_input(25)
+            .build()
+            .is_err());
+        assert!(BrotliDecoderOptions::for_untrusted_input(16)
+            .large_window_size(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn validate_matches_build() {
+        let invalid = [
+            BrotliDecoderOptions::for_untrusted_input(9),
+            BrotliDecoderOptions::for_untrusted_input(25),
+            BrotliDecoderOptions::new()
+                .with_max_window_bits(16)
+                .with_large_window_size(true),
+        ];
+
+        for options in invalid {
+            assert_eq!(
+                options.validate(),
+                Err(SetParameterError::InvalidWindowSize)
+            );
+            assert_eq!(
+                options.build().unwrap_err(),
+                SetParameterError::InvalidWindowSize
+            );
+        }
+
+        let valid = BrotliDecoderOptions::for_untrusted_input(24).with_large_window_size(false);
+
+        assert_eq!(valid.validate(), Ok(()));
+        assert!(valid.build().is_ok());
+    }
+
+    #[test]
+    fn for_http_response_disables_large_window() {
+        let options = BrotliDecoderOptions::for_http_response();
+
+        assert_eq!(
+            options,
+            BrotliDecoderOptions::new().with_large_window_size(false)
+        );
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn decompress_reports_pending_output() {
+        let input = [0; 1024];
+        let mut compressed = [0; 64];
+        let size = crate::compress(
+            &input,
+            &mut compressed,
+            crate::Quality::default(),
+            crate::WindowSize::default(),
+            crate::CompressionMode::Generic,
+        )
+        .unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        let res = decoder.decompress(&compressed[..size], &mut []).unwrap();
+
+        assert_eq!(res.info, DecoderInfo::NeedsMoreOutput);
+        assert!(res.pending_output);
+        assert_eq!(res.pending_output, decoder.has_output());
+
+        let mut output = [0; 1024];
+        let res = decoder.decompress(&[], &mut output).unwrap();
+
+        assert_eq!(res.info, DecoderInfo::Finished);
+        assert!(!res.pending_output);
+    }
+
+    #[test]
+    fn last_error_code_after_failure() {
+        let mut decoder = BrotliDecoder::new();
+
+        assert_eq!(
+            decoder.last_error_code(),
+            BrotliDecoderErrorCode_BROTLI_DECODER_NO_ERROR
+        );
+
+        let res = decoder.decompress(&[0xff; 16], &mut [0; 16]);
+
+        assert!(res.is_err());
+        assert!(decoder.last_error_code() < 0);
+        assert_eq!(decoder.last_error(), Some(res.unwrap_err()));
+
+        decoder.reset();
+
+        assert_eq!(decoder.last_error(), None);
+    }
+
+    #[test]
+    fn decompress_to_string_rejects_invalid_utf8() {
+        let input = [0xff; 64];
+        let mut compressed = [0; 128];
+        let size = crate::compress(
+            &input,
+            &mut compressed,
+            crate::Quality::default(),
+            crate::WindowSize::default(),
+            crate::CompressionMode::Generic,
+        )
+        .unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        let mut output = String::from("unchanged");
+        let res = decoder.decompress_to_string(&compressed[..size], &mut output);
+
+        assert!(matches!(res, Err(DecodeToStringError::Utf8(_))));
+        assert_eq!(output, "unchanged");
+    }
+
+    #[test]
+    fn reconfigure_before_and_after_decoding() {
+        let input = vec![0xAB; 4096];
+        let compressed = compress_with_window(&input, crate::WindowSize::new(22).unwrap());
+        let mut output = vec![0; input.len()];
+
+        let mut decoder = BrotliDecoder::new();
+        decoder
+            .reconfigure(&BrotliDecoderOptions::for_untrusted_input(16))
+            .unwrap();
+
+        assert_eq!(
+            decoder.decompress(&compressed, &mut output),
+            Err(DecodeError::FormatWindowBits)
+        );
+
+        let mut decoder = BrotliDecoder::new();
+        decoder.decompress(&compressed[..1], &mut output).unwrap();
+
+        assert_eq!(
+            decoder.reconfigure(&BrotliDecoderOptions::new()),
+            Err(SetParameterError::InUse)
+        );
+    }
+
+    #[test]
+    fn reconfigure_replaces_parameters() {
+        let key = BrotliDecoderParameter_BROTLI_DECODER_PARAM_DISABLE_RING_BUFFER_REALLOCATION;
+        let mut decoder = BrotliDecoder::new();
+
+        for disable in [true, false, true, false] {
+            decoder
+                .reconfigure(
+                    &BrotliDecoderOptions::new().with_disable_ring_buffer_reallocation(disable),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(decoder.params, [(key, 0)]);
+
+        let input = b"hello world".repeat(100);
+        let compressed = compress_with_window(&input, crate::WindowSize::new(16).unwrap());
+        let mut output = vec![0; input.len()];
+
+        decoder.decompress(&compressed, &mut output).unwrap();
+        decoder.restart();
+
+        assert_eq!(decoder.params, [(key, 0)]);
+    }
+
+    #[test]
+    fn multiple_raw_dictionaries() {
+        let mut decoder = BrotliDecoder::new();
+
+        assert!(decoder
+            .attach_dictionary(SharedDictionaryType::Raw, b"hello")
+            .is_ok());
+        assert!(decoder
+            .attach_dictionary(SharedDictionaryType::Raw, b"world")
+            .is_ok());
+    }
+
+    #[test]
+    fn shared_dictionary_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedDictionary>();
+
+        let input = b"hello world, hello brotli, hello world".repeat(16);
+        let hello_world = crate::encode::PreparedDictionary::from_raw_data(
+            b"hello world",
+            crate::Quality::best(),
+        )
+        .unwrap();
+        let hello_brotli = crate::encode::PreparedDictionary::from_raw_data(
+            b"hello brotli",
+            crate::Quality::best(),
+        )
+        .unwrap();
+        let mut encoder = crate::BrotliEncoder::new();
+        encoder.attach_prepared_dictionary(&hello_world).unwrap();
+        encoder.attach_prepared_dictionary(&hello_brotli).unwrap();
+        let compressed = {
+            let mut output = vec![0; 1024];
+            let res = encoder
+                .compress(&input, &mut output, crate::encode::BrotliOperation::Finish)
+                .unwrap();
+            output.truncate(res.bytes_written);
+            output
+        };
+
+        let mut dictionary = SharedDictionary::new();
+        dictionary
+            .add(SharedDictionaryType::Raw, b"hello world")
+            .unwrap()
+            .add(SharedDictionaryType::Raw, b"hello brotli")
+            .unwrap();
+
+        let dictionary = Arc::new(dictionary);
+        let compressed = Arc::new(compressed);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let dictionary = Arc::clone(&dictionary);
+                let compressed = Arc::clone(&compressed);
+
+                std::thread::spawn(move || {
+                    let mut decoder = BrotliDecoder::new();
+                    decoder.attach_shared_dictionary(&dictionary).unwrap();
+
+                    let mut output = vec![0; 1024];
+                    let res = decoder.decompress(&compressed, &mut output).unwrap();
+                    assert_eq!(res.info, DecoderInfo::Finished);
+
+                    output.truncate(res.bytes_written);
+                    output
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn attach_shared_dictionary_is_all_or_nothing() {
+        let raw = b"hello world, hello brotli";
+        let input = b"hello world, hello brotli, hello world".repeat(4);
+
+        let prepared =
+            crate::encode::PreparedDictionary::from_raw_data(raw, crate::Quality::best()).unwrap();
+        let mut encoder = crate::BrotliEncoder::new();
+        encoder.attach_prepared_dictionary(&prepared).unwrap();
+        let compressed = {
+            let mut output = vec![0; 1024];
+            let res = encoder
+                .compress(&input, &mut output, crate::encode::BrotliOperation::Finish)
+                .unwrap();
+            output.truncate(res.bytes_written);
+            output
+        };
+
+        let mut dictionary = SharedDictionary::new();
+        dictionary
+            .add(SharedDictionaryType::Raw, raw)
+            .unwrap()
+            .add(
+                SharedDictionaryType::Serialized,
+                b"not a serialized dictionary",
+            )
+            .unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        assert_eq!(
+            decoder.attach_shared_dictionary(&dictionary).unwrap_err(),
+            DictionaryError::Unsupported
+        );
+
+        // the raw dictionary was attached before the serialized one failed, but
+        // must not be kept
+        let mut output = vec![0; 1024];
+        assert!(decoder.decompress(&compressed, &mut output).is_err());
+
+        let mut dictionary = SharedDictionary::new();
+        dictionary.add(SharedDictionaryType::Raw, raw).unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        decoder.attach_shared_dictionary(&dictionary).unwrap();
+        let res = decoder.decompress(&compressed, &mut output).unwrap();
+
+        assert_eq!(res.info, DecoderInfo::Finished);
+        assert_eq!(&output[..res.bytes_written], input);
+    }
+
+    #[test]
+    fn shared_dictionary_rejects_invalid_data() {
+        let mut dictionary = SharedDictionary::new();
+
+        assert_eq!(
+            dictionary.add(SharedDictionaryType::Raw, &[]).unwrap_err(),
+            DictionaryError::Empty
+        );
+
+        dictionary
+            .add(SharedDictionaryType::Serialized, b"serialized")
+            .unwrap();
+
+        assert_eq!(
+            dictionary
+                .add(SharedDictionaryType::Serialized, b"serialized")
+                .unwrap_err(),
+            DictionaryError::TooManySerializedDictionaries
+        );
+    }
+
+    #[test]
+    fn multi_stream_keeps_dictionaries() {
+        let dictionary = b"hello world, hello brotli";
+        let input = b"hello world, hello brotli, hello world".repeat(4);
+
+        let prepared =
+            crate::encode::PreparedDictionary::from_raw_data(dictionary, crate::Quality::best())
+                .unwrap();
+        let encoder = crate::BrotliEncoderOptions::new()
+            .with_dictionary(&prepared)
+            .build()
+            .unwrap();
+        let mut writer = crate::CompressorWriter::with_encoder(encoder, Vec::new());
+        writer.write_all(&input).unwrap();
+        let compressed = writer.into_inner().unwrap();
+        let concatenated = [compressed.as_slice(), &compressed].concat();
+
+        let decoder = BrotliDecoderOptions::new()
+            .with_raw_dictionary(dictionary)
+            .build()
+            .unwrap();
+        let mut reader =
+            DecompressorReader::multi_stream_with_decoder(decoder, concatenated.as_slice());
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+
+        assert_eq!(output, input.repeat(2));
+    }
+
+    #[test]
+    fn raw_dictionary_options_decode() {
+        let dictionary = b"hello world, hello brotli";
+        let input = b"hello world, hello brotli, hello world".repeat(4);
+
+        let prepared =
+            crate::encode::PreparedDictionary::from_raw_data(dictionary, crate::Quality::best())
+                .unwrap();
+        let mut encoder = crate::BrotliEncoder::new();
+        encoder.attach_prepared_dictionary(&prepared).unwrap();
+        let compressed = {
+            let mut output = vec![0; 1024];
+            let res = encoder
+                .compress(&input, &mut output, crate::encode::BrotliOperation::Finish)
+                .unwrap();
+            output.truncate(res.bytes_written);
+            output
+        };
+
+        let mut decoder = BrotliDecoderOptions::new()
+            .with_raw_dictionary(dictionary)
+            .build()
+            .unwrap();
+        let mut output = vec![0; 1024];
+        let res = decoder.decompress(&compressed, &mut output).unwrap();
+
+        assert_eq!(res.info, DecoderInfo::Finished);
+        assert_eq!(&output[..res.bytes_written], input);
+    }
+
+    #[test]
+    fn read_decoder_output() {
+        let input = b"hello world, hello brotli, hello world".repeat(1024);
+        let compressed = crate::compress_to_vec(
+            &input,
+            crate::Quality::default(),
+            crate::WindowSize::default(),
+            crate::CompressionMode::Generic,
+        )
+        .unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        let mut decompressed = Vec::new();
+        let mut buf = [0; 100];
+
+        assert_eq!(
+            decoder.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        for chunk in compressed.chunks(16) {
+            let mut chunk = chunk;
+
+            loop {
+                let (bytes_read, _) = decoder.give_input(chunk).unwrap();
+                chunk = &chunk[bytes_read..];
+
+                loop {
+                    match decoder.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => decompressed.extend_from_slice(&buf[..n]),
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => panic!("{e}"),
+                    }
+                }
+
+                if chunk.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        assert!(decoder.is_finished());
+        assert_eq!(decoder.read(&mut buf).unwrap(), 0);
+        assert_eq!(decompressed, input);
+    }
+
+    #[test]
+    fn drain_to_writer_after_each_input() {
+        let input = b"hello world, hello brotli, hello world".repeat(1024);
+        let compressed = crate::compress_to_vec(
+            &input,
+            crate::Quality::default(),
+            crate::WindowSize::default(),
+            crate::CompressionMode::Generic,
+        )
+        .unwrap();
+
+        let mut decoder = BrotliDecoder::new();
+        let mut decompressed = Vec::new();
+        let mut remaining = compressed.as_slice();
+
+        while !decoder.is_finished() {
+            let (bytes_read, _) = decoder.give_input(remaining).unwrap();
+            remaining = &remaining[bytes_read..];
+
+            decoder.drain_to_writer(&mut decompressed).unwrap();
+            assert!(!decoder.has_output());
+        }
+
+        assert_eq!(decoder.drain_to_writer(&mut decompressed).unwrap(), 0);
+        assert_eq!(decoder.total_out(), decompressed.len());
+        assert_eq!(decompressed, input);
+    }
+
+    #[test]
+    fn attach_raw_dictionary_after_decoding_started() {
+        let mut decoder = BrotliDecoder::new();
+        decoder.decompress(&[0x0b], &mut []).unwrap();
+
+        assert_eq!(
+            decoder.attach_raw_dictionary(b"hello"),
+            Err(DictionaryError::DecodingStarted)
+        );
+        assert_eq!(
+            decoder.attach_serialized_dictionary(b"hello"),
+            Err(DictionaryError::DecodingStarted)
+        );
+    }
+
+    #[test]
+    fn attach_dictionary_after_decoding_started() {
+        let mut decoder = BrotliDecoder::new();
+        decoder.decompress(&[0x0b], &mut []).unwrap();
+
+        assert_eq!(
+            decoder.attach_dictionary(SharedDictionaryType::Raw, b"hello"),
+            Err(DictionaryError::DecodingStarted)
+        );
+    }
+
+    #[test]
+    fn options_attach_shared_dictionary() {
+        let raw = b"hello world, hello brotli";
+        let input = b"hello world, hello brotli, hello world".repeat(4);
+
+        let prepared =
+            crate::encode::PreparedDictionary::from_raw_data(raw, crate::Quality::best()).unwrap();
+        let mut encoder = crate::BrotliEncoder::new();
+        encoder.attach_prepared_dictionary(&prepared).unwrap();
+        let compressed = {
+            let mut output = vec![0; 1024];
+            let res = encoder
+                .compress(&input, &mut output, crate::encode::BrotliOperation::Finish)
+                .unwrap();
+            output.truncate(res.bytes_written);
+            output
+        };
+
+        // the stream refers to the dictionary, so it can't be decoded without it
+        let mut output = vec![0; 1024];
+        assert!(BrotliDecoder::new()
+            .decompress(&compressed, &mut output)
+            .is_err());
+
+        let mut dictionary = SharedDictionary::new();
+        dictionary.add(SharedDictionaryType::Raw, raw).unwrap();
+
+        let mut decoder = BrotliDecoderOptions::new()
+            .dictionary(&dictionary)
+            .build()
+            .unwrap();
+        let res = decoder.decompress(&compressed, &mut output).unwrap();
+
+        assert_eq!(res.info, DecoderInfo::Finished);
+        assert_eq!(&output[..res.bytes_written], input);
+    }
+
+    #[test]
+    fn too_many_serialized_dictionaries() {
+        // magic bytes, an 11 byte LZ77 dictionary and no custom static dictionary
+        let serialized = [&[0x91, 0x00, 11][..], b"hello world", &[0x00]].concat();
+        let mut decoder = BrotliDecoder::new();
+
+        assert_eq!(
+            decoder.attach_serialized_dictionary(&serialized),
+            Err(DictionaryError::Unsupported)
+        );
+
+        // serialized dictionaries can't be attached in this build, so pretend
+        // one was attached to reach the limit.
+        decoder.serialized_dictionaries = 1;
+
+        assert_eq!(
+            decoder.attach_serialized_dictionary(&serialized),
+            Err(DictionaryError::TooManySerializedDictionaries)
+        );
+        assert!(decoder.attach_raw_dictionary(b"hello brotli").is_ok());
+    }
+}
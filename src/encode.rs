@@ -6,15 +6,33 @@
 //! [`Read`]: https://doc.rust-lang.org/stable/std/io/trait.Read.html
 //! [`Write`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html
 
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::alloc::GlobalAlloc;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use core::mem;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use core::pin::Pin;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use core::task::{ready, Context, Poll};
+use core::{fmt, ptr, slice};
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::io::{BufRead, Read, Write};
-use std::{fmt, io, mem, ptr, slice};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, IoSlice, Read, Write};
 
 use brotlic_sys::*;
 
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use crate::async_io::{AsyncBufRead, AsyncWrite, PendingOp, PendingOutput};
+#[cfg(feature = "std")]
+use crate::{write_until_error, IntoInnerError, OutputBuffer};
 use crate::{
-    BlockSize, CompressionMode, IntoInnerError, LargeWindowSize, Quality, SetParameterError,
-    WindowSize,
+    BlockSize, BrotliAllocator, CompressionMode, DictionaryError, DirectDistanceCodes,
+    LargeWindowSize, PostfixBits, Quality, SetParameterError, SharedDictionaryType, WindowSize,
 };
 
 /// A reference to a brotli encoder.
@@ -23,12 +41,36 @@ use crate::{
 /// wrapper intended to be used for people who are familiar with the C API. For
 /// higher level abstractions, see [`CompressorReader`] and
 /// [`CompressorWriter`].
-pub struct BrotliEncoder {
+///
+/// The window mode `M` tracks whether the encoder may produce streams that
+/// require a large window decoder, see [`WindowMode`].
+///
+/// `BrotliEncoder` implements [`Write`] by giving the written data to the
+/// encoder as input, and flushing by [flushing the encoding stream][flush].
+/// The encoder may stop accepting input while it holds output that has not
+/// been taken, so a write may consume fewer bytes than given or none at all.
+/// Take the output with e.g. [`take_output_to_vec`] between writes to make
+/// room for more input.
+///
+/// As nothing takes the output while writing, the encoder only accepts a
+/// limited amount of input at once, about one to two times its window size.
+/// Writing more than that using [`Write::write_all`], or a function built on
+/// it such as `serde_json::to_writer`, fails with
+/// [`io::ErrorKind::WriteZero`]. Use a [`CompressorWriter`] to compress data
+/// of arbitrary size, which writes the output to an underlying writer.
+///
+/// [flush]: BrotliEncoder::flush
+/// [`take_output_to_vec`]: BrotliEncoder::take_output_to_vec
+pub struct BrotliEncoder<M: WindowMode = StandardWindow> {
     state: *mut BrotliEncoderState,
+    _alloc: Option<BrotliAllocator>,
+    dictionaries: Vec<PreparedDictionary>,
+    total_out: usize,
+    window_mode: PhantomData<M>,
 }
 
-unsafe impl Send for BrotliEncoder {}
-unsafe impl Sync for BrotliEncoder {}
+unsafe impl<M: WindowMode> Send for BrotliEncoder<M> {}
+unsafe impl<M: WindowMode> Sync for BrotliEncoder<M> {}
 
 impl BrotliEncoder {
     /// Constructs a new brotli encoder instance.
@@ -38,15 +80,144 @@ impl BrotliEncoder {
     /// Panics if the encoder fails to be allocated or initialized
     #[doc(alias = "BrotliEncoderCreateInstance")]
     pub fn new() -> Self {
-        let instance = unsafe { BrotliEncoderCreateInstance(None, None, ptr::null_mut()) };
+        Self::create(None)
+    }
+
+    /// Constructs a new brotli encoder instance using the custom allocator
+    /// `alloc`.
+    ///
+    /// The allocator is kept alive for as long as the encoder exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::alloc::System;
+    ///
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let encoder = BrotliEncoder::new_in(System);
+    /// ```
+    #[doc(alias = "BrotliEncoderCreateInstance")]
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(alloc: A) -> Self {
+        Self::create(Some(BrotliAllocator::new(alloc)))
+    }
+
+    /// Returns the version of the C brotli encoder library.
+    ///
+    /// The version is encoded as `(major << 24) | (minor << 12) | patch`. Use
+    /// [`encoder_library_version`] to get the unpacked version instead.
+    ///
+    /// [`encoder_library_version`]: crate::encoder_library_version
+    #[doc(alias = "BrotliEncoderVersion")]
+    pub fn version() -> u32 {
+        unsafe { BrotliEncoderVersion() }
+    }
+}
+
+impl<M: WindowMode> BrotliEncoder<M> {
+    fn create(alloc: Option<BrotliAllocator>) -> Self {
+        BrotliEncoder {
+            state: Self::create_instance(alloc.as_ref()),
+            _alloc: alloc,
+            dictionaries: Vec::new(),
+            total_out: 0,
+            window_mode: PhantomData,
+        }
+    }
+
+    fn create_instance(alloc: Option<&BrotliAllocator>) -> *mut BrotliEncoderState {
+        let (alloc_func, free_func, opaque) = BrotliAllocator::funcs(alloc);
+        let instance = unsafe { BrotliEncoderCreateInstance(alloc_func, free_func, opaque) };
 
         if !instance.is_null() {
-            BrotliEncoder { state: instance }
+            instance
         } else {
             panic!("BrotliEncoderCreateInstance returned NULL: failed to allocate or initialize");
         }
     }
 
+    /// Resets the encoder to its initial state, so that it can be used to
+    /// compress a new stream.
+    ///
+    /// The internal encoder state is destroyed and recreated using the same
+    /// allocator, all parameters are reset to their defaults and attached
+    /// dictionaries are detached. An encoder in [`LargeWindow`] mode keeps
+    /// large window support enabled, so that its output still matches its
+    /// type. Use [`reset_with_options`] to apply parameters afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::BrotliOperation;
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    ///
+    /// encoder.give_input(b"hello world", BrotliOperation::Finish)?;
+    /// encoder.reset();
+    ///
+    /// assert!(!encoder.is_finished());
+    /// assert!(!encoder.has_output());
+    /// # Ok::<(), brotlic::encode::EncodeError>(())
+    /// ```
+    ///
+    /// [`reset_with_options`]: BrotliEncoder::reset_with_options
+    #[doc(alias = "BrotliEncoderDestroyInstance")]
+    #[doc(alias = "BrotliEncoderCreateInstance")]
+    pub fn reset(&mut self) {
+        let instance = Self::create_instance(self._alloc.as_ref());
+
+        unsafe {
+            BrotliEncoderDestroyInstance(self.state);
+        }
+
+        self.state = instance;
+        self.dictionaries.clear();
+        self.total_out = 0;
+
+        if M::LARGE {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_LARGE_WINDOW;
+
+            self.set_param(key, 1)
+                .expect("a new encoder accepts large window support");
+        }
+    }
+
+    /// Resets the encoder like [`reset`] and applies `options` to it.
+    ///
+    /// The encoder afterwards behaves like one built using
+    /// [`BrotliEncoderOptions::build`]. The allocator is kept, but like
+    /// [`reset`], this destroys the internal encoder state and creates a new
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
+    ///
+    /// [`reset`]: BrotliEncoder::reset
+    #[doc(alias = "BrotliEncoderSetParameter")]
+    pub fn reset_with_options(
+        &mut self,
+        options: &BrotliEncoderOptions<M>,
+    ) -> Result<(), SetParameterError> {
+        self.reset();
+
+        options.configure(self)
+    }
+
     /// Checks if the encoder instance reached its final state.
     #[doc(alias = "BrotliEncoderIsFinished")]
     pub fn is_finished(&self) -> bool {
@@ -89,6 +260,7 @@ impl BrotliEncoder {
         let mut input_len = input.len();
         let mut output_ptr = output.as_mut_ptr();
         let mut output_len = output.len();
+        let mut total_out = self.total_out;
 
         let result = unsafe {
             BrotliEncoderCompressStream(
@@ -98,10 +270,12 @@ impl BrotliEncoder {
                 &mut input_ptr,
                 &mut output_len,
                 &mut output_ptr,
-                ptr::null_mut(),
+                &mut total_out,
             )
         };
 
+        self.total_out = total_out;
+
         if result != 0 {
             let bytes_read = input.len() - input_len;
             let bytes_written = output.len() - output_len;
@@ -121,15 +295,76 @@ impl BrotliEncoder {
         Ok(self.compress(input, &mut [], op)?.bytes_read)
     }
 
+    /// Compresses all of `input` using the operation `op`, appending all
+    /// output to `output`.
+    ///
+    /// Unlike [`Self::compress`], this keeps giving input to the encoder and
+    /// taking its output until `input` is consumed and the operation is
+    /// complete. After a [`BrotliOperation::Flush`] the output suffices to
+    /// decode all input given so far, and after a [`BrotliOperation::Finish`]
+    /// the encoder [is finished](Self::is_finished).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if the encoder rejects the operation, e.g.
+    /// because the stream is already finished. Output produced before the
+    /// error occurred has already been appended to `output`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::BrotliOperation;
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    /// let mut compressed = Vec::new();
+    ///
+    /// encoder.compress_all_into(b"hello ", &mut compressed, BrotliOperation::Process)?;
+    /// encoder.compress_all_into(b"world", &mut compressed, BrotliOperation::Finish)?;
+    ///
+    /// assert!(encoder.is_finished());
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_all_into(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        op: BrotliOperation,
+    ) -> Result<(), EncodeError> {
+        let mut remaining = input;
+
+        loop {
+            let bytes_read = self.give_input(remaining, op)?;
+            remaining = &remaining[bytes_read..];
+
+            let has_output = self.has_output();
+
+            while let Some(buf) = unsafe { self.take_output() } {
+                output.extend_from_slice(buf);
+            }
+
+            // the operation is complete once the encoder makes no more progress
+            if bytes_read == 0 && !has_output && remaining.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
     /// Attempts the flush the encoding stream.
     ///
     /// Actual flush is performed when all output has been successfully read.
-    /// Use [`Self::has_output`] to verify that flushing completedNo other
+    /// Use [`Self::has_output`] to verify that flushing completed. No other
     /// modifying operation should be queried before flushing has been
     /// finalized. When flush is complete, output data will be sufficient for a
     /// decoder to reproduce all given input. Calling this function might
     /// resulting in a worse compression ratio, because the encoder is forced to
     /// emit all output immediately.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if the encoder rejects the flush operation,
+    /// e.g. because it is in an inconsistent state.
     pub fn flush(&mut self) -> Result<(), EncodeError> {
         self.give_op(BrotliOperation::Flush)
     }
@@ -147,6 +382,89 @@ impl BrotliEncoder {
         self.give_op(BrotliOperation::Finish)
     }
 
+    /// Emits `metadata` as a metadata block, appending all encoder output to
+    /// `output`.
+    ///
+    /// The encoder is flushed first, so that the metadata block is placed
+    /// after all previously given input. Metadata is stored in the compression
+    /// stream as-is and skipped by decoders, which allows to embed custom
+    /// framing information. After this method returns, the encoder accepts
+    /// input as usual.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `metadata` is larger than 16 MiB or if
+    /// the encoder rejects the operation, e.g. because the stream is already
+    /// finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    /// let mut output = Vec::new();
+    ///
+    /// encoder.emit_metadata(b"content-type: text/plain", &mut output)?;
+    ///
+    /// assert!(output.len() > 24);
+    /// # Ok::<(), brotlic::encode::EncodeError>(())
+    /// ```
+    pub fn emit_metadata(
+        &mut self,
+        metadata: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), EncodeError> {
+        if metadata.len() > MAX_METADATA_SIZE {
+            return Err(EncodeError);
+        }
+
+        let mut remaining = metadata;
+
+        loop {
+            let bytes_read = self.give_input(remaining, BrotliOperation::EmitMetadata)?;
+            remaining = &remaining[bytes_read..];
+
+            let mut bytes_written = 0;
+            while let Some(buf) = unsafe { self.take_output() } {
+                output.extend_from_slice(buf);
+                bytes_written += buf.len();
+            }
+
+            // the operation is complete once the encoder makes no more progress
+            if bytes_read == 0 && bytes_written == 0 && remaining.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns the total number of compressed bytes produced by the encoder.
+    ///
+    /// This counts all output since the encoder was created or [reset],
+    /// whether it was written by [`Self::compress`] or taken using
+    /// [`Self::take_output`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::BrotliOperation;
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    /// let mut output = [0; 64];
+    ///
+    /// let result = encoder.compress(b"hello world", &mut output, BrotliOperation::Finish)?;
+    ///
+    /// assert!(encoder.is_finished());
+    /// assert_eq!(encoder.total_out(), result.bytes_written);
+    /// # Ok::<(), brotlic::encode::EncodeError>(())
+    /// ```
+    ///
+    /// [reset]: Self::reset
+    pub fn total_out(&self) -> usize {
+        self.total_out
+    }
+
     /// Checks if the encoder has more output.
     #[doc(alias = "BrotliEncoderHasMoreOutput")]
     pub fn has_output(&self) -> bool {
@@ -168,20 +486,142 @@ impl BrotliEncoder {
     /// invalidated.
     #[doc(alias = "BrotliEncoderTakeOutput")]
     pub unsafe fn take_output(&mut self) -> Option<&[u8]> {
+        self.take_output_at_most(0)
+    }
+
+    /// Takes all output currently buffered in the encoder into a newly
+    /// allocated [`Vec<u8>`].
+    ///
+    /// This is a safe alternative to [`Self::take_output`] at the cost of an
+    /// additional allocation. An empty vector is returned if the encoder has
+    /// no output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::BrotliOperation;
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    /// let mut input = &b"hello world"[..];
+    /// let mut compressed = Vec::new();
+    ///
+    /// while !encoder.is_finished() {
+    ///     let bytes_read = encoder.give_input(input, BrotliOperation::Finish)?;
+    ///     input = &input[bytes_read..];
+    ///     compressed.extend(encoder.take_output_to_vec());
+    /// }
+    ///
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "BrotliEncoderTakeOutput")]
+    pub fn take_output_to_vec(&mut self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        while let Some(buf) = unsafe { self.take_output() } {
+            output.extend_from_slice(buf);
+        }
+
+        output
+    }
+
+    /// Writes all output currently buffered in the encoder to `writer`.
+    ///
+    /// This is a safe alternative to [`Self::take_output`] that avoids the
+    /// intermediate allocation of [`Self::take_output_to_vec`]. Returns the
+    /// number of compressed bytes written, which is zero if the encoder has no
+    /// output.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if writing to `writer` fails. Output is
+    /// taken from the encoder in bounded chunks, so only the chunk that failed
+    /// to be written is lost while the remainder stays with the encoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliEncoder;
+    ///
+    /// let mut encoder = BrotliEncoder::new();
+    /// let mut compressed = Vec::new();
+    ///
+    /// encoder.give_input(b"hello world", brotlic::encode::BrotliOperation::Finish)?;
+    /// let written = encoder.drain_to_writer(&mut compressed)?;
+    ///
+    /// assert!(encoder.is_finished());
+    /// assert_eq!(written, compressed.len());
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn drain_to_writer<W: Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<usize> {
+        let mut total = 0;
+
+        while let Some(output) = unsafe { self.take_output_at_most(64 * 1024) } {
+            writer.write_all(output)?;
+            total += output.len();
+        }
+
+        Ok(total)
+    }
+
+    /// Like [`Self::take_output`], but returns at most `max_len` bytes, leaving
+    /// the rest in the encoder. A `max_len` of zero returns all available
+    /// output.
+    unsafe fn take_output_at_most(&mut self, max_len: usize) -> Option<&[u8]> {
         if self.has_output() {
-            let mut len: usize = 0;
+            let mut len = max_len;
             let output = BrotliEncoderTakeOutput(self.state, &mut len as _);
 
+            self.total_out += len;
+
             Some(slice::from_raw_parts(output, len))
         } else {
             None
         }
     }
 
-    /// Returns the version of the C brotli encoder library.
-    #[doc(alias = "BrotliEncoderVersion")]
-    pub fn version() -> u32 {
-        unsafe { BrotliEncoderVersion() }
+    /// Attaches a prepared dictionary to the encoder.
+    ///
+    /// The dictionary is shared with `dictionary` and kept alive by the encoder
+    /// for as long as it exists. Dictionaries must be attached before encoding
+    /// starts. A stream compressed using a dictionary can only be decompressed
+    /// by a decoder that has the same dictionary attached.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if the dictionary count limit is reached or
+    /// encoding has already started.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::PreparedDictionary;
+    /// use brotlic::{BrotliEncoder, Quality};
+    ///
+    /// let dictionary = PreparedDictionary::from_raw_data(b"hello world", Quality::best())?;
+    /// let mut encoder = BrotliEncoder::new();
+    ///
+    /// encoder.attach_prepared_dictionary(&dictionary)?;
+    /// # Ok::<(), brotlic::DictionaryError>(())
+    /// ```
+    #[doc(alias = "BrotliEncoderAttachPreparedDictionary")]
+    pub fn attach_prepared_dictionary(
+        &mut self,
+        dictionary: &PreparedDictionary,
+    ) -> Result<(), DictionaryError> {
+        let r =
+            unsafe { BrotliEncoderAttachPreparedDictionary(self.state, dictionary.raw.dictionary) };
+
+        if r == 0 {
+            return Err(DictionaryError::Generic);
+        }
+
+        self.dictionaries.push(dictionary.clone());
+
+        Ok(())
     }
 
     fn set_param(
@@ -204,7 +644,7 @@ impl BrotliEncoder {
     }
 }
 
-impl fmt::Debug for BrotliEncoder {
+impl<M: WindowMode> fmt::Debug for BrotliEncoder<M> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BrotliEncoder")
             .field("state", &self.state)
@@ -218,7 +658,18 @@ impl Default for BrotliEncoder {
     }
 }
 
-impl Drop for BrotliEncoder {
+#[cfg(feature = "std")]
+impl<M: WindowMode> Write for BrotliEncoder<M> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.give_input(buf, BrotliOperation::Process)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(BrotliEncoder::flush(self)?)
+    }
+}
+
+impl<M: WindowMode> Drop for BrotliEncoder<M> {
     #[doc(alias = "BrotliEncoderDestroyInstance")]
     fn drop(&mut self) {
         unsafe {
@@ -227,6 +678,48 @@ impl Drop for BrotliEncoder {
     }
 }
 
+/// Marker trait for the window mode of a [`BrotliEncoder`].
+///
+/// Streams compressed with a large window size can only be decompressed by a
+/// decoder that explicitly enables large window support. The window mode is
+/// tracked in the type of [`BrotliEncoderOptions`], [`BrotliEncoder`] and the
+/// compressors built on top of them, so that this requirement is visible at
+/// compile time. It is implemented by [`StandardWindow`] and [`LargeWindow`].
+pub trait WindowMode: private::Sealed + fmt::Debug {}
+
+/// Window mode of encoders producing streams decodable by any brotli decoder.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StandardWindow {}
+
+/// Window mode of encoders that may produce streams requiring a large window
+/// decoder.
+///
+/// Such streams can only be decoded by a [`BrotliDecoder`] that has
+/// [`large_window_size`] enabled.
+///
+/// [`BrotliDecoder`]: crate::decode::BrotliDecoder
+/// [`large_window_size`]: crate::decode::BrotliDecoderOptions::large_window_size
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LargeWindow {}
+
+impl WindowMode for StandardWindow {}
+impl WindowMode for LargeWindow {}
+
+mod private {
+    pub trait Sealed {
+        /// Whether encoders in this mode may use a large window size.
+        const LARGE: bool;
+    }
+
+    impl Sealed for super::StandardWindow {
+        const LARGE: bool = false;
+    }
+
+    impl Sealed for super::LargeWindow {
+        const LARGE: bool = true;
+    }
+}
+
 /// The operation for the encoder to process.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum BrotliOperation {
@@ -244,45 +737,302 @@ pub enum BrotliOperation {
     /// operations till the encoder has no more output available. Additionally,
     /// the input stream should not be swapped, reduced or extended.
     Finish = BrotliEncoderOperation_BROTLI_OPERATION_FINISH as isize,
+
+    /// Instructs the encoder to emit the input as a metadata block. Metadata
+    /// is stored in the compression stream as-is and skipped by decoders.
+    /// Care must be taken once a metadata operation is initiated, to keep
+    /// submitting metadata operations with the remaining input till all of it
+    /// has been consumed and the encoder has no more output available. The
+    /// input must not exceed 16 MiB. See [`BrotliEncoder::emit_metadata`].
+    EmitMetadata = BrotliEncoderOperation_BROTLI_OPERATION_EMIT_METADATA as isize,
 }
 
-/// Compression options to be used for a [`BrotliEncoder`].
+/// The maximum size of a metadata block emitted by a [`BrotliEncoder`].
+const MAX_METADATA_SIZE: usize = 1 << 24;
+
+/// The default size of the buffer used by [`CompressorWriter::copy_from`].
+#[cfg(feature = "std")]
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A dictionary prepared for compression by a [`BrotliEncoder`].
 ///
-/// # Examples
+/// Preparing a dictionary is relatively expensive, therefore a prepared
+/// dictionary is intended to be created once and then used by many encoders.
+/// The dictionary data is copied and kept alive for as long as the prepared
+/// dictionary exists.
 ///
-/// Building an encoder using text mode and use a custom quality:
-/// ```
-/// use brotlic::{BrotliEncoderOptions, CompressionMode, Quality};
+/// A prepared dictionary is reference counted, so cloning it is cheap and
+/// yields a handle to the same dictionary. Two prepared dictionaries compare
+/// equal if they are handles to the same dictionary. Use
+/// [`BrotliEncoder::attach_prepared_dictionary`] or
+/// [`BrotliEncoderOptions::dictionary`] to compress with it.
 ///
-/// let encoder = BrotliEncoderOptions::new()
-///     .mode(CompressionMode::Text)
-///     .quality(Quality::new(5)?)
-///     .build()?;
+/// # Raw and serialized dictionaries
 ///
-/// # Ok::<(), brotlic::SetParameterError>(())
-/// ```
-#[derive(Debug, Clone)]
-pub struct BrotliEncoderOptions {
-    mode: Option<CompressionMode>,
-    quality: Option<Quality>,
-    window_size: Option<LargeWindowSize>,
-    block_bits: Option<BlockSize>,
-    disable_context_modeling: Option<bool>,
-    size_hint: Option<u32>,
-    postfix_bits: Option<u32>,
-    direct_distance_codes: Option<u32>,
-    stream_offset: Option<u32>,
+/// Brotli knows two kinds of dictionaries:
+///
+/// * Raw dictionaries, see [`from_raw_data`]. These are plain LZ77 prefixes:
+///   the encoder treats them as if they preceded the input, so any data can be
+///   used as a raw dictionary. Use these when the input is known to share
+///   content with some sample data, such as previously served versions of the
+///   same resource.
+/// * Serialized dictionaries, see [`from_serialized`]. These are pre-built
+///   brotli dictionaries containing custom word lists and transforms that
+///   replace the built-in static dictionary, optionally combined with a LZ77
+///   prefix. They would be used when a dictionary was generated by dedicated
+///   tooling for a specific kind of content.
+///
+/// Serialized dictionaries are an experimental feature of brotli, which is only
+/// available if the C library is built with `BROTLI_EXPERIMENTAL`. As
+/// `brotlic-sys` does not enable it, only raw dictionaries can be used and
+/// [`from_serialized`] always returns an error.
+///
+/// [`from_raw_data`]: PreparedDictionary::from_raw_data
+/// [`from_serialized`]: PreparedDictionary::from_serialized
+#[derive(Clone)]
+pub struct PreparedDictionary {
+    raw: Arc<RawPreparedDictionary>,
 }
 
-impl BrotliEncoderOptions {
-    /// Creates a new blank set encoder options.
+struct RawPreparedDictionary {
+    dictionary: *mut BrotliEncoderPreparedDictionary,
+    _data: Box<[u8]>,
+}
+
+unsafe impl Send for RawPreparedDictionary {}
+unsafe impl Sync for RawPreparedDictionary {}
+
+impl PreparedDictionary {
+    /// Prepares a raw LZ77 prefix dictionary.
     ///
-    /// initially no modifications are applied to the encoder and everything is
-    /// set to its default values.
-    pub fn new() -> Self {
-        BrotliEncoderOptions {
-            mode: None,
-            quality: None,
+    /// The dictionary `data` is used by the encoder as if it preceded the
+    /// input. The dictionary is prepared for compression qualities up to and
+    /// including `quality`.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `data` is empty or if the dictionary
+    /// fails to be allocated or initialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::PreparedDictionary;
+    /// use brotlic::Quality;
+    ///
+    /// let dictionary = PreparedDictionary::from_raw_data(b"hello world", Quality::best())?;
+    /// # Ok::<(), brotlic::DictionaryError>(())
+    /// ```
+    #[doc(alias = "BrotliEncoderPrepareDictionary")]
+    pub fn from_raw_data(data: &[u8], quality: Quality) -> Result<Self, DictionaryError> {
+        PreparedDictionary::prepare(SharedDictionaryType::Raw, data, quality)
+    }
+
+    /// Prepares a serialized dictionary.
+    ///
+    /// The dictionary `data` must be in the serialized brotli shared dictionary
+    /// format, containing custom word lists and transforms.
+    ///
+    /// Serialized dictionaries are not supported by the brotli library built
+    /// by `brotlic-sys`, see [raw and serialized dictionaries]. This function
+    /// therefore always returns an error.
+    ///
+    /// # Errors
+    ///
+    /// [`DictionaryError::Empty`] is returned if `data` is empty, otherwise
+    /// [`DictionaryError::Unsupported`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::PreparedDictionary;
+    /// use brotlic::{DictionaryError, Quality};
+    ///
+    /// let res = PreparedDictionary::from_serialized(&[0x91, 0x00, 0x00], Quality::best());
+    ///
+    /// assert_eq!(res.unwrap_err(), DictionaryError::Unsupported);
+    /// ```
+    ///
+    /// [raw and serialized dictionaries]: PreparedDictionary#raw-and-serialized-dictionaries
+    #[doc(alias = "BrotliEncoderPrepareDictionary")]
+    pub fn from_serialized(data: &[u8], _quality: Quality) -> Result<Self, DictionaryError> {
+        if data.is_empty() {
+            return Err(DictionaryError::Empty);
+        }
+
+        Err(DictionaryError::Unsupported)
+    }
+
+    /// Returns the size of the prepared dictionary in bytes.
+    ///
+    /// This includes the dictionary data as well as the hash tables and other
+    /// structures built while preparing it, which makes it useful for memory
+    /// accounting, e.g. in a dictionary cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::encode::PreparedDictionary;
+    /// use brotlic::Quality;
+    ///
+    /// let data = b"hello world".repeat(16);
+    /// let dictionary = PreparedDictionary::from_raw_data(&data, Quality::best())?;
+    ///
+    /// assert!(dictionary.len() >= data.len());
+    /// # Ok::<(), brotlic::DictionaryError>(())
+    /// ```
+    #[doc(alias = "BrotliEncoderGetPreparedDictionarySize")]
+    pub fn len(&self) -> usize {
+        unsafe { BrotliEncoderGetPreparedDictionarySize(self.raw.dictionary) }
+    }
+
+    /// Returns `true` if the prepared dictionary has a size of zero bytes.
+    ///
+    /// Since empty dictionaries are rejected when preparing, this is `false`
+    /// for any successfully prepared dictionary.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn prepare(
+        dict_type: SharedDictionaryType,
+        data: &[u8],
+        quality: Quality,
+    ) -> Result<Self, DictionaryError> {
+        if data.is_empty() {
+            return Err(DictionaryError::Empty);
+        }
+
+        let data = Box::<[u8]>::from(data);
+        let dictionary = unsafe {
+            BrotliEncoderPrepareDictionary(
+                dict_type as BrotliSharedDictionaryType,
+                data.len(),
+                data.as_ptr(),
+                quality.0 as _,
+                None,
+                None,
+                ptr::null_mut(),
+            )
+        };
+
+        if !dictionary.is_null() {
+            Ok(PreparedDictionary {
+                raw: Arc::new(RawPreparedDictionary {
+                    dictionary,
+                    _data: data,
+                }),
+            })
+        } else {
+            Err(DictionaryError::AllocationFailed)
+        }
+    }
+}
+
+impl fmt::Debug for PreparedDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedDictionary")
+            .field("dictionary", &self.raw.dictionary)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for PreparedDictionary {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.raw, &other.raw)
+    }
+}
+
+impl Eq for PreparedDictionary {}
+
+impl Hash for PreparedDictionary {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.raw).hash(state);
+    }
+}
+
+impl Drop for RawPreparedDictionary {
+    #[doc(alias = "BrotliEncoderDestroyPreparedDictionary")]
+    fn drop(&mut self) {
+        unsafe {
+            BrotliEncoderDestroyPreparedDictionary(self.dictionary);
+        }
+    }
+}
+
+/// Compression options to be used for a [`BrotliEncoder`].
+///
+/// # Examples
+///
+/// Building an encoder using text mode and use a custom quality:
+/// ```
+/// use brotlic::{BrotliEncoderOptions, CompressionMode};
+///
+/// let encoder = BrotliEncoderOptions::new()
+///     .with_mode(CompressionMode::Text)
+///     .with_quality(5)?
+///     .build()?;
+///
+/// # Ok::<(), brotlic::SetParameterError>(())
+/// ```
+///
+/// Every setter has a consuming `with_*` variant that takes and returns the
+/// options by value, which is convenient for building options in a single
+/// expression. The setters taking `&mut self` are useful when options are
+/// modified conditionally:
+/// ```
+/// use brotlic::BrotliEncoderOptions;
+///
+/// let text_input = true;
+/// let mut options = BrotliEncoderOptions::new();
+///
+/// if text_input {
+///     options.context_modeling(true);
+/// }
+///
+/// let encoder = options.build()?;
+///
+/// # Ok::<(), brotlic::SetParameterError>(())
+/// ```
+///
+/// Setting a [`large_window_size`] turns the options into
+/// `BrotliEncoderOptions<LargeWindow>`, which builds a
+/// `BrotliEncoder<LargeWindow>`:
+/// ```
+/// use brotlic::encode::{BrotliEncoder, LargeWindow};
+/// use brotlic::BrotliEncoderOptions;
+///
+/// let encoder: BrotliEncoder<LargeWindow> =
+///     BrotliEncoderOptions::new().large_window_size(28)?.build()?;
+///
+/// # Ok::<(), brotlic::SetParameterError>(())
+/// ```
+///
+/// [`large_window_size`]: BrotliEncoderOptions::large_window_size
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BrotliEncoderOptions<M: WindowMode = StandardWindow> {
+    mode: Option<CompressionMode>,
+    quality: Option<Quality>,
+    window_size: Option<LargeWindowSize>,
+    block_bits: Option<BlockSize>,
+    disable_context_modeling: Option<bool>,
+    size_hint: Option<u32>,
+    postfix_bits: Option<u32>,
+    direct_distance_codes: Option<u32>,
+    stream_offset: Option<u32>,
+    dictionaries: Vec<PreparedDictionary>,
+    window_mode: PhantomData<M>,
+}
+
+impl BrotliEncoderOptions {
+    /// Creates a new blank set encoder options.
+    ///
+    /// initially no modifications are applied to the encoder and everything is
+    /// set to its default values.
+    pub const fn new() -> Self {
+        BrotliEncoderOptions {
+            mode: None,
+            quality: None,
             window_size: None,
             block_bits: None,
             disable_context_modeling: None,
@@ -290,10 +1040,205 @@ impl BrotliEncoderOptions {
             postfix_bits: None,
             direct_distance_codes: None,
             stream_offset: None,
+            dictionaries: Vec::new(),
+            window_mode: PhantomData,
         }
     }
 
+    /// Creates options tuned for compression speed.
+    ///
+    /// Uses a quality level of 1 and the smallest window size, which compresses
+    /// quickly with little memory at the expense of compression ratio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let writer = Vec::new();
+    /// let compressor = CompressorWriter::with_encoder(BrotliEncoderOptions::fast().build()?, writer);
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn fast() -> Self {
+        let mut options = Self::new();
+        options.quality = Some(Quality(1));
+        options.window_size = Some(LargeWindowSize(WindowSize::worst().bits()));
+        options
+    }
+
+    /// Creates options that balance compression speed and ratio.
+    ///
+    /// Uses a quality level of 6 and a window size of 20 bits (1 MiB).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let writer = Vec::new();
+    /// let compressor =
+    ///     CompressorWriter::with_encoder(BrotliEncoderOptions::balanced().build()?, writer);
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn balanced() -> Self {
+        let mut options = Self::new();
+        options.quality = Some(Quality(6));
+        options.window_size = Some(LargeWindowSize(20));
+        options
+    }
+
+    /// Creates options tuned for compression ratio.
+    ///
+    /// Uses [`Quality::best`] and [`WindowSize::best`], which produces the
+    /// smallest output but is considerably slower than the other presets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let writer = Vec::new();
+    /// let compressor = CompressorWriter::with_encoder(BrotliEncoderOptions::best().build()?, writer);
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn best() -> Self {
+        let mut options = Self::new();
+        options.quality = Some(Quality::best());
+        options.window_size = Some(LargeWindowSize(WindowSize::best().bits()));
+        options
+    }
+
+    /// Creates options tuned for UTF-8 text.
+    ///
+    /// Uses a quality level of 6 and [`CompressionMode::Text`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let writer = Vec::new();
+    /// let compressor = CompressorWriter::with_encoder(BrotliEncoderOptions::text().build()?, writer);
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn text() -> Self {
+        let mut options = Self::new();
+        options.mode = Some(CompressionMode::Text);
+        options.quality = Some(Quality(6));
+        options
+    }
+
+    /// Creates options for compressing HTTP responses on the fly.
+    ///
+    /// Uses a quality level of 4, [`CompressionMode::Generic`] and a window
+    /// size of 20 bits (1 MiB). Responses are typically compressed once per
+    /// request while the client is waiting, so a low quality level keeps the
+    /// latency down while still compressing considerably better than gzip.
+    /// The generic mode makes no assumptions about the content type, and the
+    /// moderate window size bounds the memory needed by both the server and
+    /// the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let encoder = BrotliEncoderOptions::for_http_response().build()?;
+    /// let compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn for_http_response() -> Self {
+        let mut options = Self::new();
+        options.mode = Some(CompressionMode::Generic);
+        options.quality = Some(Quality(4));
+        options.window_size = Some(LargeWindowSize(20));
+        options
+    }
+
+    /// Creates options for compressing static assets ahead of time.
+    ///
+    /// Uses [`Quality::best`], [`WindowSize::best`] and
+    /// [`CompressionMode::Text`]. Static assets are compressed once and served
+    /// many times, so the considerable compression time is worth the smallest
+    /// possible output. Most static assets on the web are HTML, JavaScript or
+    /// CSS, which benefit from the text mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let encoder = BrotliEncoderOptions::for_static_asset().build()?;
+    /// let compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn for_static_asset() -> Self {
+        let mut options = Self::new();
+        options.mode = Some(CompressionMode::Text);
+        options.quality = Some(Quality::best());
+        options.window_size = Some(LargeWindowSize(WindowSize::best().bits()));
+        options
+    }
+
+    /// Creates options for UTF-8 text, such as HTML, JSON or CSS.
+    ///
+    /// Only sets [`CompressionMode::Text`] and [`Quality::default`]. Unlike
+    /// [`text`](Self::text), the quality and window size are not tuned. The
+    /// options can be customized further using the setters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let encoder = BrotliEncoderOptions::text_mode()
+    ///     .with_window_size(20)?
+    ///     .build()?;
+    /// let compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn text_mode() -> Self {
+        let mut options = Self::new();
+        options.mode = Some(CompressionMode::Text);
+        options.quality = Some(Quality::default());
+        options
+    }
+
+    /// Creates options for fonts, such as those contained in WOFF 2.0 files.
+    ///
+    /// Only sets [`CompressionMode::Font`]. All other settings keep their
+    /// default values. The options can be customized further using the
+    /// setters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let encoder = BrotliEncoderOptions::font_mode().build()?;
+    /// let compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    ///
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn font_mode() -> Self {
+        let mut options = Self::new();
+        options.mode = Some(CompressionMode::Font);
+        options
+    }
+}
+
+impl<M: WindowMode> BrotliEncoderOptions<M> {
     /// Allows to tune a brotli compressor for a specific type of input.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_MODE` encoder parameter.
+    #[doc(alias = "BROTLI_PARAM_MODE")]
     pub fn mode(&mut self, mode: CompressionMode) -> &mut Self {
         self.mode = Some(mode);
         self
@@ -303,69 +1248,305 @@ impl BrotliEncoderOptions {
     /// compression ratios at the expense of slower compression times. For more
     /// information see [`Quality`]
     ///
+    /// Corresponds to the `BROTLI_PARAM_QUALITY` encoder parameter.
+    ///
+    /// Accepts either a [`Quality`] or a raw quality level, which is validated
+    /// inline.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `quality` is not a valid [`Quality`].
+    ///
     /// [`Quality`]: crate::Quality
-    pub fn quality(&mut self, quality: Quality) -> &mut Self {
-        self.quality = Some(quality);
-        self
+    #[doc(alias = "BROTLI_PARAM_QUALITY")]
+    pub fn quality<T>(&mut self, quality: T) -> Result<&mut Self, SetParameterError>
+    where
+        T: TryInto<Quality>,
+        SetParameterError: From<T::Error>,
+    {
+        self.quality = Some(quality.try_into()?);
+        Ok(self)
+    }
+
+    /// Sets the quality from a raw quality level, such as one read from a
+    /// configuration file or user input.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_QUALITY` encoder parameter.
+    ///
+    /// This is equivalent to `quality(Quality::new(raw)?)`.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `raw` is out of the range of valid
+    /// qualities.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliEncoderOptions;
+    ///
+    /// let level = "9".parse().unwrap();
+    /// let encoder = BrotliEncoderOptions::new()
+    ///     .quality_level_raw(level)?
+    ///     .build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BROTLI_PARAM_QUALITY")]
+    pub fn quality_level_raw(&mut self, raw: u8) -> Result<&mut Self, SetParameterError> {
+        self.quality = Some(Quality::new(raw)?);
+        Ok(self)
     }
 
     /// Recommended sliding LZ77 window size according to RFC7932 (Brotli
     /// proper). For more information see [`WindowSize`].
     ///
+    /// Corresponds to the `BROTLI_PARAM_LGWIN` encoder parameter.
+    ///
+    /// Besides a [`WindowSize`], anything that converts into one is accepted,
+    /// such as a raw number of bits or a [`LargeWindowSize`] within the range
+    /// of standard window sizes.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `window_size` does not fit into a
+    /// [`WindowSize`].
+    ///
     /// [`WindowSize`]: crate::WindowSize
-    pub fn window_size(&mut self, window_size: WindowSize) -> &mut Self {
+    /// [`LargeWindowSize`]: crate::LargeWindowSize
+    #[doc(alias = "BROTLI_PARAM_LGWIN")]
+    pub fn window_size<T>(&mut self, window_size: T) -> Result<&mut Self, SetParameterError>
+    where
+        T: TryInto<WindowSize>,
+        SetParameterError: From<T::Error>,
+    {
+        let window_size = window_size.try_into()?;
+
         self.window_size = Some(window_size.into());
-        self
+        Ok(self)
     }
 
     /// The non-standard large window size to use. For more information see
-    /// [`LargeWindowSize`].
+    /// [`LargeWindowSize`]. A standard [`WindowSize`] or a raw number of bits
+    /// is accepted as well.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_LGWIN` encoder parameter, with
+    /// `BROTLI_PARAM_LARGE_WINDOW` enabled.
+    ///
+    /// Returns a copy of these options in [`LargeWindow`] mode, so that
+    /// encoders built from it are marked as possibly requiring a large window
+    /// decoder.
     ///
     /// Warning: The decompressor needs explicit support in order to use this
     /// feature. This is not supported by the convenience [`decompress`]
     /// function. A matching [`BrotliDecoder`] must set [`large_window_size`] to
     /// true to decode non standard window sizes properly.
     ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `large_window_size` is not a valid
+    /// [`LargeWindowSize`].
+    ///
     /// [`LargeWindowSize`]: crate::LargeWindowSize
+    /// [`WindowSize`]: crate::WindowSize
     /// [`decompress`]: crate::decompress
     /// [`BrotliDecoder`]: crate::decode::BrotliDecoder
     /// [`large_window_size`]: crate::decode::BrotliDecoderOptions::large_window_size
-    pub fn large_window_size(&mut self, large_window_size: LargeWindowSize) -> &mut Self {
-        self.window_size = Some(large_window_size);
-        self
+    #[doc(alias = "BROTLI_PARAM_LARGE_WINDOW")]
+    #[doc(alias = "BROTLI_PARAM_LGWIN")]
+    pub fn large_window_size<T>(
+        &self,
+        large_window_size: T,
+    ) -> Result<BrotliEncoderOptions<LargeWindow>, SetParameterError>
+    where
+        T: TryInto<LargeWindowSize>,
+        SetParameterError: From<T::Error>,
+    {
+        Ok(BrotliEncoderOptions {
+            mode: self.mode,
+            quality: self.quality,
+            window_size: Some(large_window_size.try_into()?),
+            block_bits: self.block_bits,
+            disable_context_modeling: self.disable_context_modeling,
+            size_hint: self.size_hint,
+            postfix_bits: self.postfix_bits,
+            direct_distance_codes: self.direct_distance_codes,
+            stream_offset: self.stream_offset,
+            dictionaries: self.dictionaries.clone(),
+            window_mode: PhantomData,
+        })
     }
 
     /// The recommended input block size to use.
     ///
+    /// Corresponds to the `BROTLI_PARAM_LGBLOCK` encoder parameter.
+    ///
     /// The encoder may reduce this value, e.g. when the input is much smaller
     /// than the input block size.
-    pub fn block_size(&mut self, block_size: BlockSize) -> &mut Self {
-        self.block_bits = Some(block_size);
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `block_size` is not a valid
+    /// [`BlockSize`].
+    ///
+    /// [`BlockSize`]: crate::BlockSize
+    #[doc(alias = "BROTLI_PARAM_LGBLOCK")]
+    pub fn block_size<T>(&mut self, block_size: T) -> Result<&mut Self, SetParameterError>
+    where
+        T: TryInto<BlockSize>,
+        SetParameterError: From<T::Error>,
+    {
+        self.block_bits = Some(block_size.try_into()?);
+        Ok(self)
+    }
+
+    /// Enable or disable the "literal context modeling" format feature.
+    ///
+    /// Corresponds to the inverse of the
+    /// `BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING` encoder parameter.
+    ///
+    /// Literal context modeling is enabled by default. Disabling it decreases
+    /// compression ratio in favor of decompression speed.
+    #[doc(alias = "BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING")]
+    pub fn context_modeling(&mut self, enabled: bool) -> &mut Self {
+        self.disable_context_modeling = Some(!enabled);
         self
     }
 
     /// Disable "literal context modeling" format feature.
     ///
+    /// Corresponds to the `BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING`
+    /// encoder parameter.
+    ///
     /// Disabling literal context modeling decreases compression ratio in favor
     /// of decompression speed.
+    #[deprecated(note = "use `context_modeling(false)` instead")]
+    #[doc(alias = "BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING")]
     pub fn disable_context_modeling(&mut self, disable_context_modeling: bool) -> &mut Self {
-        self.disable_context_modeling = Some(disable_context_modeling);
-        self
+        self.context_modeling(!disable_context_modeling)
     }
 
     /// Estimated total input size.
     ///
+    /// Corresponds to the `BROTLI_PARAM_SIZE_HINT` encoder parameter.
+    ///
     /// This is 0 by default, which corresponds to the size being unknown.
+    #[deprecated(note = "use `size_hint_usize` instead, which accepts any input size")]
+    #[doc(alias = "BROTLI_PARAM_SIZE_HINT")]
     pub fn size_hint(&mut self, size_hint: u32) -> &mut Self {
         self.size_hint = Some(size_hint);
         self
     }
 
+    /// Estimated total input size.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_SIZE_HINT` encoder parameter.
+    ///
+    /// This is 0 by default, which corresponds to the size being unknown. The
+    /// encoder only accepts a 32-bit size hint, so sizes of 4 GiB or more are
+    /// clamped to [`u32::MAX`]. As the hint is only an estimate, this merely
+    /// tells the encoder that the input is very large.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliEncoderOptions;
+    ///
+    /// // e.g. taken from a Content-Length header
+    /// let content_length: u64 = 10_000_000_000;
+    ///
+    /// let mut options = BrotliEncoderOptions::new();
+    /// options.size_hint_usize(usize::try_from(content_length).unwrap_or(usize::MAX));
+    ///
+    /// assert_eq!(options.get_size_hint(), Some(u32::MAX));
+    /// ```
+    #[doc(alias = "BROTLI_PARAM_SIZE_HINT")]
+    pub fn size_hint_usize(&mut self, size_hint: usize) -> &mut Self {
+        self.size_hint = Some(u32::try_from(size_hint).unwrap_or(u32::MAX));
+        self
+    }
+
+    /// The number of postfix bits to use
+    ///
+    /// Corresponds to the `BROTLI_PARAM_NPOSTFIX` encoder parameter.
+    ///
+    /// The encoder may change this value on the fly. The number of bits is
+    /// validated when constructing the [`PostfixBits`]. To set the number of
+    /// direct distance codes as well, use [`distance_codes`] instead.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if direct distance codes were already set
+    /// that are invalid for `postfix_bits`, see [`DirectDistanceCodes::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, DirectDistanceCodes, PostfixBits, SetParameterError};
+    ///
+    /// let encoder = BrotliEncoderOptions::new()
+    ///     .postfix(PostfixBits::new(2)?)?
+    ///     .build()?;
+    ///
+    /// let codes = DirectDistanceCodes::new(120, PostfixBits::new(3)?)?;
+    /// let res = BrotliEncoderOptions::new()
+    ///     .distance_codes(codes)
+    ///     .postfix(PostfixBits::new(2)?);
+    ///
+    /// assert_eq!(res.unwrap_err(), SetParameterError::InvalidDirectDistanceCodes);
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [`PostfixBits`]: crate::PostfixBits
+    /// [`DirectDistanceCodes::new`]: crate::DirectDistanceCodes::new
+    /// [`distance_codes`]: Self::distance_codes
+    #[doc(alias = "BROTLI_PARAM_NPOSTFIX")]
+    pub fn postfix(&mut self, postfix_bits: PostfixBits) -> Result<&mut Self, SetParameterError> {
+        if let Some(direct_distance_codes) = self.direct_distance_codes {
+            DirectDistanceCodes::new(direct_distance_codes, postfix_bits)?;
+        }
+
+        self.postfix_bits = Some(postfix_bits.bits());
+        Ok(self)
+    }
+
+    /// The recommended number of direct distance codes and postfix bits.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_NDIRECT` and `BROTLI_PARAM_NPOSTFIX`
+    /// encoder parameters.
+    ///
+    /// The encoder may change these values on the fly. Whether a number of
+    /// direct distance codes is valid depends on the number of postfix bits,
+    /// so both are set together and validated when constructing the
+    /// [`DirectDistanceCodes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, DirectDistanceCodes, PostfixBits};
+    ///
+    /// let codes = DirectDistanceCodes::new(120, PostfixBits::new(3)?)?;
+    /// let encoder = BrotliEncoderOptions::new().distance_codes(codes).build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [`DirectDistanceCodes`]: crate::DirectDistanceCodes
+    #[doc(alias = "BROTLI_PARAM_NDIRECT")]
+    #[doc(alias = "BROTLI_PARAM_NPOSTFIX")]
+    pub fn distance_codes(&mut self, distance_codes: DirectDistanceCodes) -> &mut Self {
+        self.postfix_bits = Some(distance_codes.postfix().bits());
+        self.direct_distance_codes = Some(distance_codes.value());
+        self
+    }
+
     /// The number of postfix bits to use
     ///
+    /// Corresponds to the `BROTLI_PARAM_NPOSTFIX` encoder parameter.
+    ///
     /// The encoder may change this value on the fly.
     ///
     /// Valid ranges are from `0` to `3` (`BROTLI_MAX_NPOSTFIX`) inclusive.
+    #[deprecated(note = "use `postfix` instead, which validates its argument")]
+    #[doc(alias = "BROTLI_PARAM_NPOSTFIX")]
     pub fn postfix_bits(&mut self, postfix_bits: u32) -> &mut Self {
         self.postfix_bits = Some(postfix_bits);
         self
@@ -373,10 +1554,14 @@ impl BrotliEncoderOptions {
 
     /// Recommended number of direct distance codes.
     ///
+    /// Corresponds to the `BROTLI_PARAM_NDIRECT` encoder parameter.
+    ///
     /// The encoder may change this value on the fly.
     ///
     /// Valid range is from 0 to (15 << postfix) inclusive in steps of (1 <<
     /// postfix), where postfix is the number of postfix bits.
+    #[deprecated(note = "use `distance_codes` instead, which validates its argument")]
+    #[doc(alias = "BROTLI_PARAM_NDIRECT")]
     pub fn direct_distance_codes(&mut self, direct_distance_codes: u32) -> &mut Self {
         self.direct_distance_codes = Some(direct_distance_codes);
         self
@@ -384,144 +1569,680 @@ impl BrotliEncoderOptions {
 
     /// Number of bytes already processed by a different instance.
     ///
+    /// Corresponds to the `BROTLI_PARAM_STREAM_OFFSET` encoder parameter.
+    ///
     /// It is worth noting that when using this parameter, all other encoders
     /// must share the same parameters, so that all encoded parts obey the same
     /// restrictions as implied by the header of the compression stream.
     ///
     /// If the offset is non-zero, the stream header is omitted. Values greater
     /// than 2**30 are not allowed.
+    #[deprecated(note = "use `stream_offset_from_bytes` instead, which validates its argument")]
+    #[doc(alias = "BROTLI_PARAM_STREAM_OFFSET")]
     pub fn stream_offset(&mut self, stream_offset: u32) -> &mut Self {
         self.stream_offset = Some(stream_offset);
         self
     }
 
-    /// Creates a brotli encoder with the specified settings using allocator
-    /// `alloc`.
+    /// Number of bytes already processed by a different instance.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_STREAM_OFFSET` encoder parameter.
+    ///
+    /// It is worth noting that when using this parameter, all other encoders
+    /// must share the same parameters, so that all encoded parts obey the same
+    /// restrictions as implied by the header of the compression stream.
+    ///
+    /// If the offset is non-zero, the stream header is omitted. Since all
+    /// offsets greater than or equal to the maximal window size have the same
+    /// effect, offsets of up to 2**30 bytes are accepted.
     ///
     /// # Errors
     ///
-    /// If any of the preconditions of the parameters are violated, an error is
-    /// returned.
-    #[doc(alias = "BrotliEncoderSetParameter")]
-    pub fn build(&self) -> Result<BrotliEncoder, SetParameterError> {
-        let mut encoder = BrotliEncoder::new();
-
-        self.configure(&mut encoder)?;
-
-        Ok(encoder)
-    }
-
-    fn configure(&self, encoder: &mut BrotliEncoder) -> Result<(), SetParameterError> {
-        if let Some(mode) = self.mode {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_MODE;
-            let value = mode as u32;
-
-            encoder.set_param(key, value)?;
+    /// An [`Err`] will be returned if `byte_offset` is greater than 2**30.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, SetParameterError};
+    ///
+    /// let mut options = BrotliEncoderOptions::new();
+    /// options.stream_offset_from_bytes(4096)?;
+    ///
+    /// assert_eq!(options.get_stream_offset(), Some(4096));
+    /// assert_eq!(
+    ///     options.stream_offset_from_bytes((1 << 30) + 1).unwrap_err(),
+    ///     SetParameterError::InvalidStreamOffset
+    /// );
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BROTLI_PARAM_STREAM_OFFSET")]
+    pub fn stream_offset_from_bytes(
+        &mut self,
+        byte_offset: usize,
+    ) -> Result<&mut Self, SetParameterError> {
+        if byte_offset > 1 << 30 {
+            return Err(SetParameterError::InvalidStreamOffset);
         }
 
-        if let Some(quality) = self.quality {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_QUALITY;
-            let value = quality.0 as u32;
+        self.stream_offset = Some(byte_offset as u32);
+        Ok(self)
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Sets the [`stream_offset`] to the size of the sliding window in bytes.
+    ///
+    /// Corresponds to the `BROTLI_PARAM_STREAM_OFFSET` encoder parameter.
+    ///
+    /// The offset is computed as `(1 << window.bits()) - 16`, clamped to
+    /// 2**30. Since all offsets greater than or equal to the maximal window
+    /// size have the same effect, this is useful when compressing multiple
+    /// parts that each start at a window boundary.
+    ///
+    /// [`stream_offset`]: Self::stream_offset_from_bytes
+    #[doc(alias = "BROTLI_PARAM_STREAM_OFFSET")]
+    pub fn stream_offset_from_window(&mut self, window: impl Into<LargeWindowSize>) -> &mut Self {
+        self.stream_offset = Some(window.into().bytes().min(1 << 30) as u32);
+        self
+    }
 
-        if let Some(window_size) = self.window_size {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_LGWIN;
-            let value = window_size.0 as u32;
+    /// Attaches a prepared dictionary to encoders built from these options.
+    ///
+    /// May be called multiple times to attach multiple dictionaries. The
+    /// dictionary is shared, not copied. See
+    /// [`BrotliEncoder::attach_prepared_dictionary`] for more information.
+    ///
+    /// Dictionaries are not included when serializing the options.
+    #[doc(alias = "BrotliEncoderAttachPreparedDictionary")]
+    pub fn dictionary(&mut self, dictionary: &PreparedDictionary) -> &mut Self {
+        self.dictionaries.push(dictionary.clone());
+        self
+    }
 
-            encoder.set_param(key, value)?;
+    /// Consuming variant of [`mode`](Self::mode).
+    #[doc(alias = "BROTLI_PARAM_MODE")]
+    pub fn with_mode(mut self, mode: CompressionMode) -> Self {
+        self.mode(mode);
+        self
+    }
 
-            let large_window = WindowSize::try_from(window_size).is_err();
+    /// Consuming variant of [`quality`](Self::quality).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `quality` is not a valid [`Quality`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, Quality};
+    ///
+    /// let encoder = BrotliEncoderOptions::new()
+    ///     .with_quality(Quality::best())?
+    ///     .build()?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [`Quality`]: crate::Quality
+    #[doc(alias = "BROTLI_PARAM_QUALITY")]
+    pub fn with_quality<T>(mut self, quality: T) -> Result<Self, SetParameterError>
+    where
+        T: TryInto<Quality>,
+        SetParameterError: From<T::Error>,
+    {
+        self.quality(quality)?;
+        Ok(self)
+    }
 
-            let key = BrotliEncoderParameter_BROTLI_PARAM_LARGE_WINDOW;
-            let value = large_window as u32;
+    /// Consuming variant of [`quality_level_raw`](Self::quality_level_raw).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `raw` is out of the range of valid
+    /// qualities.
+    #[doc(alias = "BROTLI_PARAM_QUALITY")]
+    pub fn with_quality_level_raw(mut self, raw: u8) -> Result<Self, SetParameterError> {
+        self.quality_level_raw(raw)?;
+        Ok(self)
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Consuming variant of [`window_size`](Self::window_size).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `window_size` does not fit into a
+    /// [`WindowSize`].
+    ///
+    /// [`WindowSize`]: crate::WindowSize
+    #[doc(alias = "BROTLI_PARAM_LGWIN")]
+    pub fn with_window_size<T>(mut self, window_size: T) -> Result<Self, SetParameterError>
+    where
+        T: TryInto<WindowSize>,
+        SetParameterError: From<T::Error>,
+    {
+        self.window_size(window_size)?;
+        Ok(self)
+    }
 
-        if let Some(block_bits) = self.block_bits {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_LGBLOCK;
-            let value = block_bits.0 as u32;
+    /// Consuming variant of [`block_size`](Self::block_size).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `block_size` is not a valid
+    /// [`BlockSize`].
+    ///
+    /// [`BlockSize`]: crate::BlockSize
+    #[doc(alias = "BROTLI_PARAM_LGBLOCK")]
+    pub fn with_block_size<T>(mut self, block_size: T) -> Result<Self, SetParameterError>
+    where
+        T: TryInto<BlockSize>,
+        SetParameterError: From<T::Error>,
+    {
+        self.block_size(block_size)?;
+        Ok(self)
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Consuming variant of [`context_modeling`](Self::context_modeling).
+    #[doc(alias = "BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING")]
+    pub fn with_context_modeling(mut self, enabled: bool) -> Self {
+        self.context_modeling(enabled);
+        self
+    }
 
-        if let Some(disable_context_modeling) = self.disable_context_modeling {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING;
-            let value = disable_context_modeling as u32;
+    /// Consuming variant of [`size_hint_usize`](Self::size_hint_usize).
+    #[doc(alias = "BROTLI_PARAM_SIZE_HINT")]
+    pub fn with_size_hint_usize(mut self, size_hint: usize) -> Self {
+        self.size_hint_usize(size_hint);
+        self
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Consuming variant of [`postfix`](Self::postfix).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if direct distance codes were already set
+    /// that are invalid for `postfix_bits`.
+    #[doc(alias = "BROTLI_PARAM_NPOSTFIX")]
+    pub fn with_postfix(mut self, postfix_bits: PostfixBits) -> Result<Self, SetParameterError> {
+        self.postfix(postfix_bits)?;
+        Ok(self)
+    }
 
-        if let Some(size_hint) = self.size_hint {
-            let key = BrotliEncoderParameter_BROTLI_PARAM_SIZE_HINT;
-            let value = size_hint;
+    /// Consuming variant of [`distance_codes`](Self::distance_codes).
+    #[doc(alias = "BROTLI_PARAM_NDIRECT")]
+    #[doc(alias = "BROTLI_PARAM_NPOSTFIX")]
+    pub fn with_distance_codes(mut self, distance_codes: DirectDistanceCodes) -> Self {
+        self.distance_codes(distance_codes);
+        self
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Consuming variant of
+    /// [`stream_offset_from_bytes`](Self::stream_offset_from_bytes).
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `byte_offset` is greater than 2**30.
+    #[doc(alias = "BROTLI_PARAM_STREAM_OFFSET")]
+    pub fn with_stream_offset_from_bytes(
+        mut self,
+        byte_offset: usize,
+    ) -> Result<Self, SetParameterError> {
+        self.stream_offset_from_bytes(byte_offset)?;
+        Ok(self)
+    }
 
-        if let Some(postfix_bits) = self.postfix_bits {
-            if postfix_bits > 3 {
-                return Err(SetParameterError::InvalidPostfix);
-            }
+    /// Consuming variant of
+    /// [`stream_offset_from_window`](Self::stream_offset_from_window).
+    #[doc(alias = "BROTLI_PARAM_STREAM_OFFSET")]
+    pub fn with_stream_offset_from_window(mut self, window: impl Into<LargeWindowSize>) -> Self {
+        self.stream_offset_from_window(window);
+        self
+    }
 
-            let key = BrotliEncoderParameter_BROTLI_PARAM_NPOSTFIX;
-            let value = postfix_bits;
+    /// Consuming variant of [`dictionary`](Self::dictionary).
+    #[doc(alias = "BrotliEncoderAttachPreparedDictionary")]
+    pub fn with_dictionary(mut self, dictionary: &PreparedDictionary) -> Self {
+        self.dictionary(dictionary);
+        self
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Fills in every parameter that is not set with the default of the C
+    /// brotli library.
+    ///
+    /// This is useful to inspect or serialize the effective configuration.
+    /// Building an encoder from the returned options behaves the same as
+    /// building it from the original options. The block size is left unset if
+    /// it is not set, because by default the encoder chooses it based on the
+    /// quality.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressionMode, Quality};
+    ///
+    /// let options = BrotliEncoderOptions::new().with_defaults();
+    ///
+    /// assert_eq!(options.get_mode(), Some(CompressionMode::Generic));
+    /// assert_eq!(options.get_quality(), Some(Quality::best()));
+    /// assert_eq!(options.get_block_size(), None);
+    /// ```
+    pub fn with_defaults(mut self) -> Self {
+        self.mode.get_or_insert(CompressionMode::Generic);
+        self.quality.get_or_insert(Quality::default());
+        self.window_size.get_or_insert(WindowSize::default().into());
+        self.disable_context_modeling.get_or_insert(false);
+        self.size_hint.get_or_insert(0);
+        self.postfix_bits.get_or_insert(0);
+        self.direct_distance_codes.get_or_insert(0);
+        self.stream_offset.get_or_insert(0);
+        self
+    }
 
-        if let Some(direct_distance_codes) = self.direct_distance_codes {
-            let postfix = self.postfix_bits.unwrap_or(0);
+    /// Returns the compression mode, if set.
+    ///
+    /// See [`mode`](Self::mode).
+    pub fn get_mode(&self) -> Option<CompressionMode> {
+        self.mode
+    }
 
-            if (direct_distance_codes > (15 << postfix))
-                || (direct_distance_codes & ((1 << postfix) - 1)) != 0
-            {
-                return Err(SetParameterError::InvalidDirectDistanceCodes);
-            }
+    /// Returns the quality, if set.
+    ///
+    /// See [`quality`](Self::quality).
+    pub fn get_quality(&self) -> Option<Quality> {
+        self.quality
+    }
 
-            let key = BrotliEncoderParameter_BROTLI_PARAM_NDIRECT;
-            let value = direct_distance_codes;
+    /// Returns the window size, if set.
+    ///
+    /// The window size is returned as a [`LargeWindowSize`] regardless of the
+    /// window mode, see [`window_size`](Self::window_size) and
+    /// [`large_window_size`](BrotliEncoderOptions::large_window_size).
+    pub fn get_window_size(&self) -> Option<LargeWindowSize> {
+        self.window_size
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Returns the block size, if set.
+    ///
+    /// See [`block_size`](Self::block_size).
+    pub fn get_block_size(&self) -> Option<BlockSize> {
+        self.block_bits
+    }
 
-        if let Some(stream_offset) = self.stream_offset {
-            if stream_offset > (1 << 30) {
-                return Err(SetParameterError::InvalidStreamOffset);
-            }
+    /// Returns whether literal context modeling is enabled, if set.
+    ///
+    /// See [`context_modeling`](Self::context_modeling).
+    pub fn get_context_modeling(&self) -> Option<bool> {
+        self.disable_context_modeling.map(|disabled| !disabled)
+    }
 
-            let key = BrotliEncoderParameter_BROTLI_PARAM_STREAM_OFFSET;
-            let value = stream_offset;
+    /// Returns the estimated total input size, if set.
+    ///
+    /// See [`size_hint_usize`](Self::size_hint_usize).
+    pub fn get_size_hint(&self) -> Option<u32> {
+        self.size_hint
+    }
 
-            encoder.set_param(key, value)?;
-        }
+    /// Returns the number of postfix bits, if set.
+    ///
+    /// See [`postfix`](Self::postfix).
+    pub fn get_postfix_bits(&self) -> Option<u32> {
+        self.postfix_bits
+    }
 
-        Ok(())
+    /// Returns the recommended number of direct distance codes, if set.
+    ///
+    /// See [`distance_codes`](Self::distance_codes).
+    pub fn get_direct_distance_codes(&self) -> Option<u32> {
+        self.direct_distance_codes
     }
-}
 
-impl Default for BrotliEncoderOptions {
-    fn default() -> Self {
-        BrotliEncoderOptions::new()
+    /// Returns the stream offset, if set.
+    ///
+    /// See [`stream_offset_from_bytes`](Self::stream_offset_from_bytes).
+    pub fn get_stream_offset(&self) -> Option<u32> {
+        self.stream_offset
     }
-}
 
-/// A struct used by [`BrotliEncoder::compress`].
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct EncodeResult {
-    /// the number of bytes read from `input`.
-    pub bytes_read: usize,
-    /// the number of bytes written to `output`.
-    pub bytes_written: usize,
-}
+    /// Creates a brotli encoder with the specified settings.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    #[doc(alias = "BrotliEncoderSetParameter")]
+    pub fn build(&self) -> Result<BrotliEncoder<M>, SetParameterError> {
+        let mut encoder = BrotliEncoder::create(None);
 
-/// An error returned by [`BrotliEncoder::compress`].
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct EncodeError;
+        self.configure(&mut encoder)?;
 
-impl Error for EncodeError {}
+        Ok(encoder)
+    }
+
+    /// Creates a brotli encoder with the specified settings using allocator
+    /// `alloc`.
+    ///
+    /// The allocator is kept alive for as long as the encoder exists.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::alloc::System;
+    ///
+    /// use brotlic::BrotliEncoderOptions;
+    ///
+    /// let encoder = BrotliEncoderOptions::new()
+    ///     .with_quality(4)?
+    ///     .build_in(System)?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[doc(alias = "BrotliEncoderCreateInstance")]
+    pub fn build_in<A: GlobalAlloc + Send + Sync + 'static>(
+        &self,
+        alloc: A,
+    ) -> Result<BrotliEncoder<M>, SetParameterError> {
+        let mut encoder = BrotliEncoder::create(Some(BrotliAllocator::new(alloc)));
+
+        self.configure(&mut encoder)?;
+
+        Ok(encoder)
+    }
+
+    /// Creates a [`CompressorWriter`] around `writer` using an encoder with the
+    /// specified settings.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BrotliEncoderOptions;
+    ///
+    /// let writer = BrotliEncoderOptions::new()
+    ///     .with_quality(4)?
+    ///     .build_to_writer(Vec::new())?;
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn build_to_writer<W: Write>(
+        &self,
+        writer: W,
+    ) -> Result<CompressorWriter<W, M>, SetParameterError> {
+        Ok(CompressorWriter::with_encoder(self.build()?, writer))
+    }
+
+    /// Creates a [`CompressorReader`] around `reader` using an encoder with the
+    /// specified settings.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    #[cfg(feature = "std")]
+    pub fn build_to_reader<R: BufRead>(
+        &self,
+        reader: R,
+    ) -> Result<CompressorReader<R, M>, SetParameterError> {
+        Ok(CompressorReader::with_encoder(self.build()?, reader))
+    }
+
+    /// Checks the settings without creating an encoder.
+    ///
+    /// Performs the same parameter validation as [`build`](Self::build), which
+    /// is useful to check options that were e.g. deserialized from a
+    /// configuration file. Dictionaries are only checked when they are
+    /// attached to an encoder, so `build` may still fail if the options
+    /// contain dictionaries.
+    ///
+    /// # Errors
+    ///
+    /// If any of the preconditions of the parameters are violated, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, SetParameterError};
+    ///
+    /// let mut options = BrotliEncoderOptions::new();
+    /// # #[allow(deprecated)]
+    /// options.stream_offset(1 << 31);
+    ///
+    /// assert_eq!(
+    ///     options.validate(),
+    ///     Err(SetParameterError::InvalidStreamOffset)
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), SetParameterError> {
+        self.validate_params()
+    }
+
+    fn validate_params(&self) -> Result<(), SetParameterError> {
+        if let Some(postfix_bits) = self.postfix_bits {
+            if postfix_bits > 3 {
+                return Err(SetParameterError::InvalidPostfix);
+            }
+        }
+
+        if let Some(direct_distance_codes) = self.direct_distance_codes {
+            let postfix = self.postfix_bits.unwrap_or(0);
+
+            if (direct_distance_codes > (15 << postfix))
+                || (direct_distance_codes & ((1 << postfix) - 1)) != 0
+            {
+                return Err(SetParameterError::InvalidDirectDistanceCodes);
+            }
+        }
+
+        if let Some(stream_offset) = self.stream_offset {
+            if stream_offset > (1 << 30) {
+                return Err(SetParameterError::InvalidStreamOffset);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn configure(&self, encoder: &mut BrotliEncoder<M>) -> Result<(), SetParameterError> {
+        self.validate_params()?;
+
+        if let Some(mode) = self.mode {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_MODE;
+            let value = mode as u32;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(quality) = self.quality {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_QUALITY;
+            let value = quality.0 as u32;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(window_size) = self.window_size {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_LGWIN;
+            let value = window_size.0 as u32;
+
+            encoder.set_param(key, value)?;
+
+            let large_window = WindowSize::try_from(window_size).is_err();
+
+            let key = BrotliEncoderParameter_BROTLI_PARAM_LARGE_WINDOW;
+            let value = large_window as u32;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(block_bits) = self.block_bits {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_LGBLOCK;
+            let value = block_bits.0 as u32;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(disable_context_modeling) = self.disable_context_modeling {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_DISABLE_LITERAL_CONTEXT_MODELING;
+            let value = disable_context_modeling as u32;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(size_hint) = self.size_hint {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_SIZE_HINT;
+            let value = size_hint;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(postfix_bits) = self.postfix_bits {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_NPOSTFIX;
+            let value = postfix_bits;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(direct_distance_codes) = self.direct_distance_codes {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_NDIRECT;
+            let value = direct_distance_codes;
+
+            encoder.set_param(key, value)?;
+        }
+
+        if let Some(stream_offset) = self.stream_offset {
+            let key = BrotliEncoderParameter_BROTLI_PARAM_STREAM_OFFSET;
+            let value = stream_offset;
+
+            encoder.set_param(key, value)?;
+        }
+
+        for dictionary in &self.dictionaries {
+            encoder
+                .attach_prepared_dictionary(dictionary)
+                .map_err(|_| SetParameterError::InvalidDictionary)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for BrotliEncoderOptions {
+    fn default() -> Self {
+        BrotliEncoderOptions::new()
+    }
+}
+
+/// The serialized form of [`BrotliEncoderOptions`].
+///
+/// A standard window size is stored as `window_size`, whereas options in
+/// [`LargeWindow`] mode store it as `large_window_size`.
+#[cfg(feature = "serde")]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BrotliEncoderOptionsRepr {
+    mode: Option<CompressionMode>,
+    quality: Option<Quality>,
+    window_size: Option<WindowSize>,
+    large_window_size: Option<LargeWindowSize>,
+    block_size: Option<BlockSize>,
+    disable_context_modeling: Option<bool>,
+    size_hint: Option<u32>,
+    postfix_bits: Option<u32>,
+    direct_distance_codes: Option<u32>,
+    stream_offset: Option<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl<M: WindowMode> serde::Serialize for BrotliEncoderOptions<M> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (window_size, large_window_size) = if M::LARGE {
+            (None, self.window_size)
+        } else {
+            let window_size = self.window_size.and_then(|w| WindowSize::try_from(w).ok());
+
+            (window_size, None)
+        };
+
+        let repr = BrotliEncoderOptionsRepr {
+            mode: self.mode,
+            quality: self.quality,
+            window_size,
+            large_window_size,
+            block_size: self.block_bits,
+            disable_context_modeling: self.disable_context_modeling,
+            size_hint: self.size_hint,
+            postfix_bits: self.postfix_bits,
+            direct_distance_codes: self.direct_distance_codes,
+            stream_offset: self.stream_offset,
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, M: WindowMode> serde::Deserialize<'de> for BrotliEncoderOptions<M> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let repr = BrotliEncoderOptionsRepr::deserialize(deserializer)?;
+
+        let window_size = match (repr.window_size, repr.large_window_size) {
+            (Some(_), Some(_)) => {
+                return Err(D::Error::custom(
+                    "window_size and large_window_size are mutually exclusive",
+                ));
+            }
+            (Some(window_size), None) => Some(window_size.into()),
+            (None, Some(large_window_size)) if M::LARGE => Some(large_window_size),
+            (None, Some(_)) => {
+                return Err(D::Error::custom(
+                    "large_window_size requires BrotliEncoderOptions<LargeWindow>",
+                ));
+            }
+            (None, None) => None,
+        };
+
+        let options = BrotliEncoderOptions {
+            mode: repr.mode,
+            quality: repr.quality,
+            window_size,
+            block_bits: repr.block_size,
+            disable_context_modeling: repr.disable_context_modeling,
+            size_hint: repr.size_hint,
+            postfix_bits: repr.postfix_bits,
+            direct_distance_codes: repr.direct_distance_codes,
+            stream_offset: repr.stream_offset,
+            dictionaries: Vec::new(),
+            window_mode: PhantomData,
+        };
+
+        options.validate().map_err(D::Error::custom)?;
+
+        Ok(options)
+    }
+}
+
+/// A struct used by [`BrotliEncoder::compress`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EncodeResult {
+    /// the number of bytes read from `input`.
+    pub bytes_read: usize,
+    /// the number of bytes written to `output`.
+    pub bytes_written: usize,
+}
+
+/// An error returned by [`BrotliEncoder::compress`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EncodeError;
+
+#[cfg(feature = "std")]
+impl Error for EncodeError {}
 
 impl fmt::Display for EncodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -529,6 +2250,7 @@ impl fmt::Display for EncodeError {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<EncodeError> for io::Error {
     fn from(err: EncodeError) -> Self {
         io::Error::new(io::ErrorKind::Other, err)
@@ -543,6 +2265,10 @@ impl From<EncodeError> for io::Error {
 /// an infinite amount of data. When [`read`] returns zero on a non-zero buffer,
 /// the compression is considered finished.
 ///
+/// `CompressorReader<R>` also implements [`BufRead`], so it can be passed
+/// directly to functions that require one, such as
+/// [`DecompressorReader::new`], without wrapping it in a [`BufReader`].
+///
 /// # Examples
 ///
 /// Suppose the file `test.txt` contains uncompressed text. Let's try to
@@ -565,48 +2291,67 @@ impl From<EncodeError> for io::Error {
 /// ```
 ///
 /// [`read`]: CompressorReader::read
+/// [`DecompressorReader::new`]: crate::decode::DecompressorReader::new
+/// [`BufReader`]: std::io::BufReader
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct CompressorReader<R: BufRead> {
+pub struct CompressorReader<R, M: WindowMode = StandardWindow> {
     inner: R,
-    encoder: BrotliEncoder,
+    encoder: BrotliEncoder<M>,
     op: BrotliOperation,
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
 }
 
-impl<R: BufRead> CompressorReader<R> {
+#[cfg(feature = "std")]
+impl<R> CompressorReader<R> {
     /// Creates a new `CompressorReader<R>` with a newly created encoder.
     ///
     /// # Panics
     ///
     /// Panics if the encoder fails to be allocated or initialized
     pub fn new(inner: R) -> Self {
-        CompressorReader {
-            inner,
-            encoder: BrotliEncoder::new(),
-            op: BrotliOperation::Process,
-        }
+        CompressorReader::with_encoder(BrotliEncoder::new(), inner)
+    }
+
+    /// Creates a new `CompressorReader<R>` with a newly created encoder using
+    /// the custom allocator `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(inner: R, alloc: A) -> Self {
+        CompressorReader::with_encoder(BrotliEncoder::new_in(alloc), inner)
     }
+}
 
+#[cfg(feature = "std")]
+impl<R, M: WindowMode> CompressorReader<R, M> {
     /// Creates a new `CompressorReader<R>` with a specified encoder.
     ///
     /// # Examples
     ///
     /// ```
-    /// use brotlic::{BrotliEncoderOptions, CompressorReader, Quality, WindowSize};
+    /// use brotlic::{BrotliEncoderOptions, CompressorReader};
     ///
     /// let encoder = BrotliEncoderOptions::new()
-    ///     .quality(Quality::new(6)?)
-    ///     .window_size(WindowSize::new(18)?)
+    ///     .with_quality(6)?
+    ///     .with_window_size(18)?
     ///     .build()?;
     ///
     /// let underlying_source = [1, 2, 3, 4, 5];
     /// let writer = CompressorReader::with_encoder(encoder, underlying_source.as_slice());
     /// # Ok::<(), brotlic::SetParameterError>(())
     /// ```
-    pub fn with_encoder(encoder: BrotliEncoder, inner: R) -> Self {
+    pub fn with_encoder(encoder: BrotliEncoder<M>, inner: R) -> Self {
         CompressorReader {
             inner,
             encoder,
             op: BrotliOperation::Process,
+            buf: Vec::new(),
+            pos: 0,
+            filled: 0,
         }
     }
 
@@ -622,14 +2367,34 @@ impl<R: BufRead> CompressorReader<R> {
         &mut self.inner
     }
 
+    /// Gets a reference to the underlying encoder.
+    ///
+    /// This can be used to inspect the state of the encoder, e.g. whether it
+    /// is finished, while the reader is in use.
+    pub fn get_encoder(&self) -> &BrotliEncoder<M> {
+        &self.encoder
+    }
+
+    /// Gets a mutable reference to the underlying encoder.
+    ///
+    /// It is inadvisable to directly use the underlying encoder. Giving it
+    /// input or taking its output breaks the reader, as the compressed stream
+    /// would then be incomplete or corrupted.
+    pub fn get_encoder_mut(&mut self) -> &mut BrotliEncoder<M> {
+        &mut self.encoder
+    }
+
     /// Unwraps this `CompressorReader<R>`, returning the underlying reader.
     ///
     /// # Errors
     ///
     /// An [`Err`] will be returned if the compression stream has not been
-    /// finished.
-    pub fn into_inner(self) -> Result<R, IntoInnerError<CompressorReader<R>>> {
-        if self.encoder.is_finished() {
+    /// finished or not all compressed output buffered by [`fill_buf`] has
+    /// been consumed.
+    ///
+    /// [`fill_buf`]: BufRead::fill_buf
+    pub fn into_inner(self) -> Result<R, IntoInnerError<CompressorReader<R, M>>> {
+        if self.encoder.is_finished() && self.pos == self.filled {
             Ok(self.inner)
         } else {
             Err(IntoInnerError::new(
@@ -643,39 +2408,174 @@ impl<R: BufRead> CompressorReader<R> {
     /// and encoder.
     ///
     /// `into_parts` makes no attempt to validate that the compression stream
-    /// finished and cannot fail.
-    pub fn into_parts(self) -> (R, BrotliEncoder) {
+    /// finished and cannot fail. Compressed output buffered by [`fill_buf`]
+    /// that has not been consumed is discarded.
+    ///
+    /// [`fill_buf`]: BufRead::fill_buf
+    pub fn into_parts(self) -> (R, BrotliEncoder<M>) {
         (self.inner, self.encoder)
     }
+
+    /// Compresses `input` into `buf`, returning the number of bytes consumed
+    /// and, once reading should return, the number of bytes written.
+    fn read_step(
+        encoder: &mut BrotliEncoder<M>,
+        op: &mut BrotliOperation,
+        input: &[u8],
+        buf: &mut [u8],
+    ) -> io::Result<(usize, Option<usize>)> {
+        let eof = input.is_empty();
+        let EncodeResult {
+            bytes_read,
+            bytes_written,
+        } = encoder.compress(input, buf, *op)?;
+
+        let written = match *op {
+            _ if bytes_written > 0 => Some(bytes_written),
+            _ if buf.is_empty() => Some(0),
+            _ if !eof => None,
+            BrotliOperation::Process => {
+                *op = BrotliOperation::Finish;
+                None
+            }
+            BrotliOperation::Finish => Some(0),
+            _ => unreachable!(),
+        };
+
+        Ok((bytes_read, written))
+    }
+
+    /// Copies compressed output buffered by [`BufRead::fill_buf`] into `buf`,
+    /// returning `None` if the buffer is empty.
+    fn read_buffered(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.pos == self.filled {
+            return None;
+        }
+
+        let buffered = &self.buf[self.pos..self.filled];
+        let n = buffered.len().min(buf.len());
+
+        buf[..n].copy_from_slice(&buffered[..n]);
+        self.pos += n;
+
+        Some(n)
+    }
 }
 
-impl<R: BufRead> Read for CompressorReader<R> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+#[cfg(feature = "std")]
+impl<R: BufRead, M: WindowMode> CompressorReader<R, M> {
+    fn read_encoded(
+        inner: &mut R,
+        encoder: &mut BrotliEncoder<M>,
+        op: &mut BrotliOperation,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
         loop {
-            let input = self.inner.fill_buf()?;
-            let eof = input.is_empty();
-            let EncodeResult {
-                bytes_read,
-                bytes_written,
-            } = self.encoder.compress(input, buf, self.op)?;
-            self.inner.consume(bytes_read);
+            let input = inner.fill_buf()?;
+            let (bytes_read, written) = Self::read_step(encoder, op, input, buf)?;
+            inner.consume(bytes_read);
 
-            match self.op {
-                _ if bytes_written > 0 => return Ok(bytes_written),
-                _ if buf.is_empty() => return Ok(0),
-                _ if !eof => continue,
-                BrotliOperation::Process => {
-                    self.op = BrotliOperation::Finish;
-                    continue;
-                }
-                BrotliOperation::Finish => return Ok(0),
-                _ => unreachable!(),
+            if let Some(bytes_written) = written {
+                return Ok(bytes_written);
             }
         }
     }
 }
 
-/// Wraps a writer and compresses its output.
+#[cfg(feature = "std")]
+impl<R: BufRead, M: WindowMode> Read for CompressorReader<R, M> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(n) = self.read_buffered(buf) {
+            return Ok(n);
+        }
+
+        Self::read_encoded(&mut self.inner, &mut self.encoder, &mut self.op, buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead, M: WindowMode> BufRead for CompressorReader<R, M> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            if self.buf.is_empty() {
+                self.buf = vec![0; 8 * 1024];
+            }
+
+            self.filled = Self::read_encoded(
+                &mut self.inner,
+                &mut self.encoder,
+                &mut self.op,
+                &mut self.buf,
+            )?;
+            self.pos = 0;
+        }
+
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<R, M: WindowMode> CompressorReader<R, M> {
+    fn poll_read_with<F>(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>
+    where
+        R: AsyncBufRead<F>,
+    {
+        if let Some(n) = self.read_buffered(buf) {
+            return Poll::Ready(Ok(n));
+        }
+
+        loop {
+            let input = ready!(self.inner.poll_fill_buf(cx))?;
+            let (bytes_read, written) =
+                Self::read_step(&mut self.encoder, &mut self.op, input, buf)?;
+            self.inner.consume(bytes_read);
+
+            if let Some(bytes_written) = written {
+                return Poll::Ready(Ok(bytes_written));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, M> tokio::io::AsyncRead for CompressorReader<R, M>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    M: WindowMode + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let bytes_written = ready!(this.poll_read_with(cx, buf.initialize_unfilled()))?;
+
+        buf.advance(bytes_written);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R, M> futures_io::AsyncRead for CompressorReader<R, M>
+where
+    R: futures_io::AsyncBufRead + Unpin,
+    M: WindowMode + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_with(cx, buf)
+    }
+}
+
+/// Wraps a writer and compresses its output.
 ///
 /// `CompressorWriter<W>` wraps a writer and adds brotli compression to the
 /// output. It is critical to finish the compression stream, otherwise
@@ -688,6 +2588,11 @@ impl<R: BufRead> Read for CompressorReader<R> {
 /// compression quality, as output will be forced to be flushed as is and not
 /// compressed till the block is finished.
 ///
+/// A call to `write` gives all of the data to the encoder, writing compressed
+/// output to the underlying writer whenever the encoder needs room for more
+/// input. Therefore `write` consumes all of the given data unless an error
+/// occurs.
+///
 /// # Examples
 ///
 /// Let's compress some text file named `text.txt` and write the output to
@@ -710,16 +2615,39 @@ impl<R: BufRead> Read for CompressorReader<R> {
 ///
 /// To decompress it again, use [`DecompressorWriter`].
 ///
+/// With the `tokio` or `futures-io` feature enabled, `CompressorWriter<W>`
+/// implements the respective `AsyncWrite` trait for writers implementing it.
+/// Use `new_async` to create one around a writer that does not implement
+/// [`Write`]. Asynchronous writers are not finished when dropped, the
+/// compression stream is finished by shutting the writer down instead.
+///
+/// `CompressorWriter<W>` also implements [`fmt::Write`], so that text can be
+/// formatted directly into the compression stream using [`write!`]. As
+/// [`fmt::Error`] carries no information, the I/O error that caused a write to
+/// fail is lost. Only import one of [`Write`] and [`fmt::Write`], as calling
+/// `write!` is ambiguous otherwise.
+///
 /// [`into_inner`]: CompressorWriter::into_inner
 /// [`flush`]: CompressorWriter::flush
 /// [`DecompressorWriter`]: crate::decode::DecompressorWriter
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct CompressorWriter<W: Write> {
+pub struct CompressorWriter<W, M: WindowMode = StandardWindow> {
     inner: W,
-    encoder: BrotliEncoder,
+    encoder: BrotliEncoder<M>,
     panicked: bool,
+    finish_on_drop: Option<fn(&mut Self) -> io::Result<()>>,
+    buffer: Option<Box<OutputBuffer>>,
+    unwritten: Vec<u8>,
+    error: Option<io::Error>,
+    stats: EncoderStats,
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pending: PendingOutput,
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pending_op: PendingOp,
 }
 
+#[cfg(feature = "std")]
 impl<W: Write> CompressorWriter<W> {
     /// Creates a new `CompressorWriter<W>` with a newly created encoder.
     ///
@@ -727,34 +2655,129 @@ impl<W: Write> CompressorWriter<W> {
     ///
     /// Panics if the encoder fails to be allocated or initialized
     pub fn new(inner: W) -> Self {
-        CompressorWriter {
-            inner,
-            encoder: BrotliEncoder::new(),
-            panicked: false,
-        }
+        CompressorWriter::with_encoder(BrotliEncoder::new(), inner)
     }
 
-    /// Creates a new `CompressorWriter<W>` with a specified encoder.
+    /// Creates a new `CompressorWriter<W>` with a newly created encoder using
+    /// the custom allocator `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(inner: W, alloc: A) -> Self {
+        CompressorWriter::with_encoder(BrotliEncoder::new_in(alloc), inner)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<W> CompressorWriter<W> {
+    /// Creates a new asynchronous `CompressorWriter<W>` with a newly created
+    /// encoder.
+    ///
+    /// Unlike [`new`], the writer does not need to implement [`Write`] and the
+    /// compression stream is not finished when dropped. Shut the writer down
+    /// to finish the compression stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated or initialized
     ///
     /// # Examples
     ///
     /// ```
-    /// use brotlic::{BrotliEncoderOptions, CompressorWriter, Quality, WindowSize};
+    /// use brotlic::CompressorWriter;
+    /// use tokio::io::AsyncWriteExt;
     ///
-    /// let encoder = BrotliEncoderOptions::new()
-    ///     .quality(Quality::new(4)?)
-    ///     .window_size(WindowSize::new(16)?)
-    ///     .build()?;
+    /// # #[cfg(feature = "tokio")]
+    /// # tokio_test::block_on(async {
+    /// let mut writer = CompressorWriter::new_async(Vec::new());
     ///
-    /// let underlying_storage = Vec::new();
-    /// let writer = CompressorWriter::with_encoder(encoder, underlying_storage);
-    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// writer.write_all(b"hello world").await?;
+    /// writer.shutdown().await?;
+    ///
+    /// let compressed = writer.get_ref();
+    /// assert_eq!(brotlic::decompress_to_vec(compressed)?, b"hello world");
+    /// # Ok::<(), std::io::Error>(())
+    /// # }).unwrap();
+    /// ```
+    ///
+    /// [`new`]: CompressorWriter::new
+    pub fn new_async(inner: W) -> Self {
+        CompressorWriter::with_encoder_async(BrotliEncoder::new(), inner)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<W, M: WindowMode> CompressorWriter<W, M> {
+    /// Creates a new asynchronous `CompressorWriter<W>` with a specified
+    /// encoder.
+    ///
+    /// See [`new_async`] for how asynchronous writers differ.
+    ///
+    /// [`new_async`]: CompressorWriter::new_async
+    pub fn with_encoder_async(encoder: BrotliEncoder<M>, inner: W) -> Self {
+        CompressorWriter::create(encoder, inner, None)
+    }
+}
+
+#[cfg(feature = "std")]
+impl CompressorWriter<Vec<u8>> {
+    /// Creates a new `CompressorWriter<Vec<u8>>` writing to an empty `Vec<u8>`
+    /// using the specified `quality` and `window_size`.
+    ///
+    /// This is a shorthand for building an encoder with
+    /// [`BrotliEncoderOptions`] and passing it to [`with_encoder`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder fails to be allocated, initialized or configured
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{CompressorWriter, Quality, WindowSize};
+    ///
+    /// let mut writer = CompressorWriter::new_vec(Quality::best(), WindowSize::best());
+    /// writer.write_all(b"hello world")?;
+    ///
+    /// let compressed = writer.into_inner()?;
+    /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn with_encoder(encoder: BrotliEncoder, inner: W) -> Self {
+    ///
+    /// [`with_encoder`]: CompressorWriter::with_encoder
+    pub fn new_vec(quality: Quality, window_size: WindowSize) -> Self {
+        let encoder = BrotliEncoderOptions::new()
+            .quality(quality)
+            .and_then(|options| options.window_size(window_size))
+            .and_then(|options| options.build())
+            .expect("failed to configure encoder");
+
+        CompressorWriter::with_encoder(encoder, Vec::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W, M: WindowMode> CompressorWriter<W, M> {
+    fn create(
+        encoder: BrotliEncoder<M>,
+        inner: W,
+        finish_on_drop: Option<fn(&mut Self) -> io::Result<()>>,
+    ) -> Self {
         CompressorWriter {
             inner,
             encoder,
             panicked: false,
+            finish_on_drop,
+            buffer: None,
+            unwritten: Vec::new(),
+            error: None,
+            stats: EncoderStats::default(),
+            #[cfg(any(feature = "tokio", feature = "futures-io"))]
+            pending: PendingOutput::default(),
+            #[cfg(any(feature = "tokio", feature = "futures-io"))]
+            pending_op: PendingOp::None,
         }
     }
 
@@ -770,23 +2793,106 @@ impl<W: Write> CompressorWriter<W> {
         &mut self.inner
     }
 
-    /// Unwraps this `CompressorWriter<W>`, returning the underlying writer.
+    /// Gets a reference to the underlying encoder.
+    ///
+    /// This can be used to inspect the state of the encoder, e.g. whether it
+    /// is finished, while the writer is in use.
+    pub fn get_encoder(&self) -> &BrotliEncoder<M> {
+        &self.encoder
+    }
+
+    /// Gets a mutable reference to the underlying encoder.
     ///
-    /// The compression stream is finished before returning the writer.
+    /// It is inadvisable to directly use the underlying encoder. Giving it
+    /// input or taking its output breaks the writer, as the compressed stream
+    /// would then be incomplete or corrupted.
+    pub fn get_encoder_mut(&mut self) -> &mut BrotliEncoder<M> {
+        &mut self.encoder
+    }
+
+    /// Returns the total number of uncompressed bytes written to this
+    /// compressor.
     ///
-    /// # Errors
+    /// Only input accepted by the encoder is counted, metadata written with
+    /// [`write_metadata`] is not.
     ///
-    /// An [`Err`] will be returned if an error occurs while finishing the
-    /// compression stream.
-    pub fn into_inner(mut self) -> Result<W, IntoInnerError<CompressorWriter<W>>> {
-        match self.finish() {
-            Err(e) => Err(IntoInnerError::new(self, e)),
-            Ok(()) => Ok(self.into_parts().0),
+    /// [`write_metadata`]: CompressorWriter::write_metadata
+    pub fn total_in(&self) -> u64 {
+        self.stats.bytes_in
+    }
+
+    /// Returns the total number of compressed bytes produced by the encoder.
+    ///
+    /// Compressed bytes count as produced once they have been taken from the
+    /// encoder, even if writing them to the underlying writer failed. See
+    /// [`BrotliEncoder::total_out`].
+    pub fn total_out(&self) -> u64 {
+        self.encoder.total_out() as u64
+    }
+
+    /// Returns the ratio of compressed to uncompressed bytes so far, or
+    /// [`None`] if no input has been written yet.
+    ///
+    /// A ratio below `1.0` means the output is smaller than the input. Output
+    /// that the encoder has not produced yet, e.g. because the stream has not
+    /// been flushed, is not taken into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut compressor = CompressorWriter::new(Vec::new());
+    /// assert_eq!(compressor.compression_ratio(), None);
+    ///
+    /// compressor.write_all(&[0; 4096])?;
+    /// compressor.flush()?;
+    ///
+    /// assert!(compressor.compression_ratio().unwrap() < 0.1);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn compression_ratio(&self) -> Option<f64> {
+        match self.stats.bytes_in {
+            0 => None,
+            total_in => Some(self.total_out() as f64 / total_in as f64),
         }
     }
 
-    /// Disassembles this `CompressorWriter<W>`, returning the underlying writer
-    /// and encoder.
+    /// Returns statistics about the compression performed by this writer.
+    ///
+    /// The statistics accumulate over the lifetime of the writer and are not
+    /// reset when it is flushed. The final statistics are returned by
+    /// [`into_parts`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut compressor = CompressorWriter::new(Vec::new());
+    /// compressor.write_all(&[0; 4096])?;
+    /// compressor.flush()?;
+    /// compressor.try_finish()?;
+    ///
+    /// let stats = *compressor.stats();
+    /// assert_eq!(stats.bytes_in, 4096);
+    /// assert_eq!(stats.bytes_out, compressor.get_ref().len() as u64);
+    /// assert_eq!(stats.flush_count, 1);
+    /// assert_eq!(stats.finish_count, 1);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`into_parts`]: Self::into_parts
+    pub fn stats(&self) -> &EncoderStats {
+        &self.stats
+    }
+
+    /// Disassembles this `CompressorWriter<W>`, returning the underlying
+    /// writer, the encoder and the final statistics.
     ///
     /// If the underlying writer panicked, it is not known what portion of the
     /// data was written. In this case, we return `WriterPanicked` to get the
@@ -795,101 +2901,1310 @@ impl<W: Write> CompressorWriter<W> {
     /// writer once the compression stream is finished, use [`into_inner`].
     ///
     /// `into_parts` makes no attempt to finish the compression stream and
-    /// cannot fail.
+    /// cannot fail. If writing compressed data to the underlying writer failed
+    /// earlier, [`BrotliEncoder::has_output`] reports whether the encoder
+    /// still holds output that was not written. Output buffered by a writer
+    /// created with [`with_encoder_and_capacity`] is discarded, [`flush`] the
+    /// writer beforehand to keep it.
     ///
     /// [`into_inner`]: Self::into_inner
-    pub fn into_parts(self) -> (W, Result<BrotliEncoder, WriterPanicked>) {
+    /// [`with_encoder_and_capacity`]: CompressorWriter::with_encoder_and_capacity
+    /// [`flush`]: Write::flush
+    pub fn into_parts(self) -> (W, Result<BrotliEncoder<M>, WriterPanicked<M>>, EncoderStats) {
         let inner = unsafe { ptr::read(&self.inner) };
         let encoder = unsafe { ptr::read(&self.encoder) };
+        drop(unsafe { ptr::read(&self.buffer) });
+        drop(unsafe { ptr::read(&self.unwritten) });
+        drop(unsafe { ptr::read(&self.error) });
+        #[cfg(any(feature = "tokio", feature = "futures-io"))]
+        drop(unsafe { ptr::read(&self.pending) });
         let panicked = self.panicked;
+        let stats = self.stats;
         mem::forget(self);
 
-        let encoder = if !panicked {
-            Ok(encoder)
-        } else {
-            Err(WriterPanicked { encoder })
+        let encoder = if !panicked {
+            Ok(encoder)
+        } else {
+            Err(WriterPanicked { encoder })
+        };
+
+        (inner, encoder, stats)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl<W, M: WindowMode> CompressorWriter<W, M> {
+    fn poll_flush_encoder_output<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        loop {
+            ready!(self.pending.poll_write_to(&mut self.inner, cx))?;
+
+            match unsafe { self.encoder.take_output_at_most(64 * 1024) } {
+                Some(output) => {
+                    self.stats.bytes_out += output.len() as u64;
+                    self.pending.set(output);
+                }
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    fn poll_write_with<F>(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>
+    where
+        W: AsyncWrite<F>,
+    {
+        loop {
+            ready!(self.poll_flush_encoder_output(cx))?;
+
+            // a flush that was not polled to completion is abandoned
+            self.pending_op = PendingOp::None;
+
+            let bytes_read = self.encoder.give_input(buf, BrotliOperation::Process)?;
+            self.stats.bytes_in += bytes_read as u64;
+
+            // output is written on the next call, so that no input is consumed
+            // by a call that returns pending. If the encoder did not accept any
+            // input, its output is written first to make room for more.
+            if bytes_read > 0 || buf.is_empty() || !self.encoder.has_output() {
+                return Poll::Ready(Ok(bytes_read));
+            }
+        }
+    }
+
+    fn poll_flush_with<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        if self.pending_op != PendingOp::Flush {
+            ready!(self.poll_flush_encoder_output(cx))?;
+            self.encoder.flush()?;
+            self.stats.flush_count += 1;
+            self.pending_op = PendingOp::Flush;
+        }
+
+        ready!(self.poll_flush_encoder_output(cx))?;
+        let r = ready!(self.inner.poll_flush(cx));
+        self.pending_op = PendingOp::None;
+
+        Poll::Ready(r)
+    }
+
+    fn poll_close_with<F>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite<F>,
+    {
+        if self.pending_op != PendingOp::Finish {
+            ready!(self.poll_flush_encoder_output(cx))?;
+
+            if !self.encoder.is_finished() {
+                self.encoder.finish()?;
+                self.pending_op = PendingOp::Finish;
+            }
+        }
+
+        if self.pending_op == PendingOp::Finish {
+            ready!(self.poll_flush_encoder_output(cx))?;
+            self.stats.finish_count += 1;
+            self.pending_op = PendingOp::None;
+        }
+
+        self.inner.poll_close(cx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write, M: WindowMode> CompressorWriter<W, M> {
+    /// Creates a new `CompressorWriter<W>` with a specified encoder.
+    ///
+    /// An encoder in [`LargeWindow`] mode results in a
+    /// `CompressorWriter<W, LargeWindow>`, whose output requires a large window
+    /// decoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{BrotliEncoderOptions, CompressorWriter};
+    ///
+    /// let encoder = BrotliEncoderOptions::new()
+    ///     .with_quality(4)?
+    ///     .with_window_size(16)?
+    ///     .build()?;
+    ///
+    /// let underlying_storage = Vec::new();
+    /// let writer = CompressorWriter::with_encoder(encoder, underlying_storage);
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub fn with_encoder(encoder: BrotliEncoder<M>, inner: W) -> Self {
+        CompressorWriter::create(encoder, inner, Some(Self::finish))
+    }
+
+    /// Creates a new `CompressorWriter<W>` with a specified encoder that
+    /// buffers up to `capacity` bytes of compressed output.
+    ///
+    /// Compressed output is only written to the underlying writer once the
+    /// buffer is full, when the writer is flushed or when the compression
+    /// stream is finished. This reduces the number of writes to the underlying
+    /// writer, which is useful if each write is expensive, such as a write to
+    /// a file or socket. A `capacity` of zero disables buffering, which is
+    /// what [`with_encoder`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::{BrotliEncoder, CompressorWriter};
+    ///
+    /// let mut writer =
+    ///     CompressorWriter::with_encoder_and_capacity(BrotliEncoder::new(), Vec::new(), 64 * 1024);
+    ///
+    /// writer.write_all(b"hello world")?;
+    ///
+    /// let compressed = writer.into_inner()?;
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, b"hello world");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`with_encoder`]: CompressorWriter::with_encoder
+    pub fn with_encoder_and_capacity(encoder: BrotliEncoder<M>, inner: W, capacity: usize) -> Self {
+        let mut writer = CompressorWriter::with_encoder(encoder, inner);
+        writer.buffer = OutputBuffer::new(capacity);
+        writer
+    }
+
+    /// Compresses the entire contents of `reader`, returning the number of
+    /// bytes read.
+    ///
+    /// This is similar to [`io::copy`], but uses a larger 64 KiB buffer to
+    /// feed the encoder bigger chunks of input at a time. The compression
+    /// stream is not finished, so more data may be written afterwards. To use
+    /// a different buffer size, see [`Self::copy_from_with_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if reading from `reader`, compressing or
+    /// writing to the underlying writer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let input = vec![0; 100_000];
+    /// let mut writer = CompressorWriter::new(Vec::new());
+    ///
+    /// let bytes_read = writer.copy_from(&mut input.as_slice())?;
+    ///
+    /// assert_eq!(bytes_read, 100_000);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn copy_from<R: Read + ?Sized>(&mut self, reader: &mut R) -> io::Result<u64> {
+        self.copy_from_with_capacity(COPY_BUFFER_SIZE, reader)
+    }
+
+    /// Compresses the entire contents of `reader`, returning the number of
+    /// bytes read.
+    ///
+    /// This is the same as [`Self::copy_from`].
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if reading from `reader`, compressing or
+    /// writing to the underlying writer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let input = b"hello world".repeat(1000);
+    /// let mut writer = CompressorWriter::new(Vec::new());
+    ///
+    /// let bytes_read = writer.compress_from_reader(&mut input.as_slice())?;
+    /// let compressed = writer.into_inner()?;
+    ///
+    /// assert_eq!(bytes_read, 11_000);
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, input);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_from_reader<R: Read + ?Sized>(&mut self, reader: &mut R) -> io::Result<u64> {
+        self.copy_from(reader)
+    }
+
+    /// Compresses the entire contents of `reader` using a buffer of
+    /// `capacity` bytes, returning the number of bytes read.
+    ///
+    /// This is equivalent to [`Self::copy_from`], which uses a buffer of
+    /// 64 KiB. A `capacity` of zero also uses a buffer of 64 KiB.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if reading from `reader`, compressing or
+    /// writing to the underlying writer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut input = Cursor::new(b"hello world".repeat(1000));
+    /// let mut writer = CompressorWriter::new(Vec::new());
+    ///
+    /// let bytes_read = writer.copy_from_with_capacity(4096, &mut input)?;
+    /// let compressed = writer.into_inner()?;
+    ///
+    /// assert_eq!(bytes_read, 11_000);
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, input.into_inner());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn copy_from_with_capacity<R: Read + ?Sized>(
+        &mut self,
+        capacity: usize,
+        reader: &mut R,
+    ) -> io::Result<u64> {
+        let capacity = if capacity == 0 {
+            COPY_BUFFER_SIZE
+        } else {
+            capacity
+        };
+        let mut buf = vec![0; capacity];
+        let mut bytes_read = 0;
+
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(bytes_read),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            self.write_all(&buf[..n])?;
+            bytes_read += n as u64;
+        }
+    }
+
+    /// Writes `metadata` as a metadata block to the underlying writer.
+    ///
+    /// All data written so far is flushed to the underlying writer before the
+    /// metadata block. Metadata is stored in the compression stream as-is and
+    /// skipped by decoders. See [`BrotliEncoder::emit_metadata`] for more
+    /// information.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `metadata` is larger than 16 MiB, if the
+    /// encoder rejects the operation or if writing to the underlying writer
+    /// fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut writer = CompressorWriter::new(Vec::new());
+    ///
+    /// writer.write_all(b"first frame")?;
+    /// writer.write_metadata(b"frame boundary")?;
+    /// writer.write_all(b"second frame")?;
+    ///
+    /// let compressed = writer.into_inner()?;
+    /// let decompressed = brotlic::decompress_to_vec(&compressed)?;
+    ///
+    /// assert_eq!(decompressed, b"first framesecond frame");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_metadata(&mut self, metadata: &[u8]) -> io::Result<()> {
+        if metadata.len() > MAX_METADATA_SIZE {
+            return Err(EncodeError.into());
+        }
+
+        let mut remaining = metadata;
+
+        loop {
+            let bytes_read = self
+                .encoder
+                .give_input(remaining, BrotliOperation::EmitMetadata)?;
+            remaining = &remaining[bytes_read..];
+
+            let has_output = self.encoder.has_output();
+            self.flush_encoder_output()?;
+
+            // the operation is complete once the encoder makes no more progress
+            if bytes_read == 0 && !has_output && remaining.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Finishes the compression stream without consuming this writer.
+    ///
+    /// All remaining output is written to the underlying writer. Calling this
+    /// method again after it succeeded is a no-op, as is finishing the stream
+    /// through [`into_inner`] afterwards. No further data should be written
+    /// once the stream is finished.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if an error occurs while finishing the
+    /// compression stream. The call can be retried, or the writer can be
+    /// recovered using [`into_parts`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut compressor = CompressorWriter::new(Vec::new());
+    /// compressor.write_all(b"hello world")?;
+    /// compressor.try_finish()?;
+    ///
+    /// let compressed = compressor.into_inner()?;
+    /// assert_eq!(brotlic::decompress_to_vec(&compressed)?, b"hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`into_inner`]: Self::into_inner
+    /// [`into_parts`]: Self::into_parts
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        self.finish()
+    }
+
+    /// Unwraps this `CompressorWriter<W>`, returning the underlying writer.
+    ///
+    /// The compression stream is finished before returning the writer. If the
+    /// writer is a borrowed writer such as `&mut Vec<u8>`, the borrow itself is
+    /// returned, after which the original `Vec<u8>` is accessible again.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if an error occurs while finishing the
+    /// compression stream. The returned [`IntoInnerError`] converts into an
+    /// [`io::Error`], so it can simply be propagated with `?` in functions
+    /// returning [`io::Result`]. Use [`IntoInnerError::into_inner`] to recover
+    /// the compressor instead.
+    ///
+    /// # Examples
+    ///
+    /// Appending compressed data to an existing `Vec<u8>`:
+    ///
+    /// ```
+    /// use std::io::{self, Write};
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut output = b"header".to_vec();
+    /// let mut compressor = CompressorWriter::new(&mut output);
+    ///
+    /// compressor.write_all(b"hello world")?;
+    ///
+    /// let borrowed: &mut Vec<u8> = compressor.into_inner()?;
+    /// assert!(borrowed.len() > 6);
+    /// assert!(output.starts_with(b"header"));
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    ///
+    /// [`IntoInnerError`]: crate::IntoInnerError
+    /// [`IntoInnerError::into_inner`]: crate::IntoInnerError::into_inner
+    // the writer is returned on error so that it can be recovered, like
+    // `std::io::BufWriter::into_inner` does
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(mut self) -> Result<W, IntoInnerError<CompressorWriter<W, M>>> {
+        match self.finish() {
+            Err(e) => Err(IntoInnerError::new(self, e)),
+            Ok(()) => Ok(self.into_parts().0),
+        }
+    }
+
+    /// Unwraps this `CompressorWriter<W>`, returning the underlying writer
+    /// without finishing the compression stream.
+    ///
+    /// This is useful to abandon a compression stream whose output is going
+    /// to be discarded anyway, e.g. because of an error elsewhere. The data
+    /// written to the underlying writer so far is generally not decodable.
+    /// Output still held by the encoder or buffered by this writer is
+    /// discarded. This is equivalent to `into_parts().0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use brotlic::CompressorWriter;
+    ///
+    /// let mut compressor = CompressorWriter::new(Vec::new());
+    /// compressor.write_all(b"hello world")?;
+    ///
+    /// let abandoned = compressor.into_inner_without_finish();
+    /// assert!(brotlic::decompress_to_vec(&abandoned).is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn into_inner_without_finish(self) -> W {
+        self.into_parts().0
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let finished = self.encoder.is_finished();
+
+        if !finished {
+            self.encoder.finish()?;
+        }
+        self.flush_encoder_output()?;
+
+        if !finished && self.encoder.is_finished() {
+            self.stats.finish_count += 1;
+        }
+
+        self.flush_buffer()
+    }
+
+    /// Gives all of `buf` to the encoder, writing its output in between.
+    ///
+    /// The encoder may stop accepting input while it holds output, so input is
+    /// given until `buf` is consumed or the encoder makes no more progress.
+    /// Returns the number of bytes consumed.
+    ///
+    /// If writing fails after some of `buf` was consumed, the number of bytes
+    /// consumed is returned and the error is reported by the next call, as
+    /// the caller would otherwise give the same bytes again.
+    fn give_all(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        let mut bytes_read = 0;
+
+        while bytes_read < buf.len() {
+            let n = self
+                .encoder
+                .give_input(&buf[bytes_read..], BrotliOperation::Process)?;
+            bytes_read += n;
+            self.stats.bytes_in += n as u64;
+
+            let has_output = self.encoder.has_output();
+
+            if let Err(err) = self.flush_encoder_output() {
+                if bytes_read == 0 {
+                    return Err(err);
+                }
+
+                self.error = Some(err);
+                break;
+            }
+
+            if n == 0 && !has_output {
+                break;
+            }
+        }
+
+        Ok(bytes_read)
+    }
+
+    fn flush_encoder_output(&mut self) -> io::Result<()> {
+        let total_out = self.encoder.total_out();
+        let r = self.write_encoder_output();
+        self.stats.bytes_out += (self.encoder.total_out() - total_out) as u64;
+        r
+    }
+
+    fn write_encoder_output(&mut self) -> io::Result<()> {
+        while let Some(buffer) = self.buffer.as_deref_mut() {
+            let spare_capacity = buffer.spare_capacity();
+
+            if spare_capacity == 0 {
+                self.flush_buffer()?;
+                continue;
+            }
+
+            match unsafe { self.encoder.take_output_at_most(spare_capacity) } {
+                Some(output) => buffer.extend(output),
+                None => return Ok(()),
+            }
+        }
+
+        self.panicked = true;
+        let r = self.write_unbuffered_output();
+        self.panicked = false;
+        r
+    }
+
+    fn write_unbuffered_output(&mut self) -> io::Result<()> {
+        // output taken from the encoder cannot be given back, so whatever was
+        // not written due to an error is kept and written first next time.
+        let (written, r) = write_until_error(&mut self.inner, &self.unwritten);
+        self.unwritten.drain(..written);
+        r?;
+
+        while let Some(output) = unsafe { self.encoder.take_output_at_most(64 * 1024) } {
+            let (written, r) = write_until_error(&mut self.inner, output);
+
+            if let Err(err) = r {
+                self.unwritten.extend_from_slice(&output[written..]);
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        match &mut self.buffer {
+            Some(buffer) => {
+                self.panicked = true;
+                let r = buffer.write_to(&mut self.inner);
+                self.panicked = false;
+                r
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write, M: WindowMode> Write for CompressorWriter<W, M> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.give_all(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let mut bytes_read = 0;
+
+        // feed every slice to the encoder in turn instead of only the first
+        // one, as the default implementation does.
+        for buf in bufs {
+            let n = match self.give_all(buf) {
+                Ok(n) => n,
+                Err(err) if bytes_read > 0 => {
+                    self.error = Some(err);
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            bytes_read += n;
+
+            if n < buf.len() {
+                break;
+            }
+        }
+
+        Ok(bytes_read)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        self.encoder.flush()?;
+        self.stats.flush_count += 1;
+        self.flush_encoder_output()?;
+        self.flush_buffer()?;
+
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write, M: WindowMode> fmt::Write for CompressorWriter<W, M> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W, M> tokio::io::AsyncWrite for CompressorWriter<W, M>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    M: WindowMode + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_with(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_with(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_with(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W, M> futures_io::AsyncWrite for CompressorWriter<W, M>
+where
+    W: futures_io::AsyncWrite + Unpin,
+    M: WindowMode + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_with(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_with(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_with(cx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W, M: WindowMode> Drop for CompressorWriter<W, M> {
+    fn drop(&mut self) {
+        if let Some(finish) = self.finish_on_drop {
+            if !self.panicked {
+                let _r = finish(self);
+            }
+        }
+    }
+}
+
+/// Statistics about the compression performed by a [`CompressorWriter`].
+///
+/// Obtained using [`CompressorWriter::stats`].
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct EncoderStats {
+    /// Number of uncompressed bytes accepted by the encoder.
+    pub bytes_in: u64,
+    /// Number of compressed bytes taken from the encoder.
+    pub bytes_out: u64,
+    /// Number of times the encoder was flushed.
+    pub flush_count: u32,
+    /// Number of compression streams that were finished.
+    pub finish_count: u32,
+}
+
+/// Error returned from [`CompressorWriter::into_inner`], when the underlying
+/// writer has previously panicked. Contains the encoder that was used for
+/// compression.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct WriterPanicked<M: WindowMode = StandardWindow> {
+    encoder: BrotliEncoder<M>,
+}
+
+#[cfg(feature = "std")]
+impl<M: WindowMode> WriterPanicked<M> {
+    /// Returns the encoder that was used for compression. It is unknown what
+    /// data was fed to the encoder, so simply using it to finish it is not a
+    /// good idea.
+    pub fn into_inner(self) -> BrotliEncoder<M> {
+        self.encoder
+    }
+}
+
+#[cfg(feature = "std")]
+impl<M: WindowMode> Error for WriterPanicked<M> {}
+
+#[cfg(feature = "std")]
+impl<M: WindowMode> fmt::Display for WriterPanicked<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "CompressorWriter inner writer panicked, what data remains unwritten is not known",
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_quality() {
+        let invalid = Quality::new(12);
+
+        assert_eq!(invalid.unwrap_err(), SetParameterError::InvalidQuality);
+    }
+
+    #[test]
+    fn options_as_hash_map_key() {
+        use std::collections::HashMap;
+
+        let mut fast = BrotliEncoderOptions::new();
+        fast.quality(1).unwrap();
+        let mut best = BrotliEncoderOptions::new();
+        best.quality(11).unwrap();
+
+        let mut cache = HashMap::new();
+        cache.insert(fast.clone(), "fast");
+        cache.insert(best.clone(), "best");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache[&fast], "fast");
+        assert_eq!(cache[&best], "best");
+        assert_ne!(fast, BrotliEncoderOptions::new());
+    }
+
+    #[test]
+    fn equal_parameters_hash_equally() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash<T: Hash>(value: T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        for level in 0..=11 {
+            let quality = Quality::new(level).unwrap();
+
+            assert_eq!(hash(quality), hash(Quality::new(level).unwrap()));
+        }
+
+        for bits in 10..=24 {
+            let window_size = WindowSize::new(bits).unwrap();
+
+            assert_eq!(hash(window_size), hash(WindowSize::new(bits).unwrap()));
+        }
+
+        for bits in 16..=24 {
+            let block_size = BlockSize::new(bits).unwrap();
+
+            assert_eq!(hash(block_size), hash(BlockSize::new(bits).unwrap()));
+        }
+
+        assert_eq!(hash(CompressionMode::Text), hash(CompressionMode::Text));
+    }
+
+    #[test]
+    fn display_parameters() {
+        assert_eq!(Quality::new(6).unwrap().to_string(), "quality level 6");
+        assert_eq!(
+            WindowSize::new(22).unwrap().to_string(),
+            "window 22 bits (4194288 bytes)"
+        );
+        assert_eq!(
+            LargeWindowSize::new(30).unwrap().to_string(),
+            "window 30 bits (1073741808 bytes)"
+        );
+        assert_eq!(
+            BlockSize::new(20).unwrap().to_string(),
+            "block 20 bits (1048576 bytes)"
+        );
+        assert_eq!(CompressionMode::Generic.to_string(), "generic");
+        assert_eq!(CompressionMode::Text.to_string(), "text");
+        assert_eq!(CompressionMode::Font.to_string(), "font");
+    }
+
+    #[test]
+    fn presets_match_setters() {
+        let fast = BrotliEncoderOptions::new()
+            .with_quality(1)
+            .unwrap()
+            .with_window_size(WindowSize::worst())
+            .unwrap();
+        let balanced = BrotliEncoderOptions::new()
+            .with_quality(6)
+            .unwrap()
+            .with_window_size(20)
+            .unwrap();
+        let best = BrotliEncoderOptions::new()
+            .with_quality(Quality::best())
+            .unwrap()
+            .with_window_size(WindowSize::best())
+            .unwrap();
+        let text = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Text)
+            .with_quality(6)
+            .unwrap();
+
+        assert_eq!(BrotliEncoderOptions::fast(), fast);
+        assert_eq!(BrotliEncoderOptions::balanced(), balanced);
+        assert_eq!(BrotliEncoderOptions::best(), best);
+        assert_eq!(BrotliEncoderOptions::text(), text);
+
+        let text_mode = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Text)
+            .with_quality(Quality::default())
+            .unwrap();
+        let font_mode = BrotliEncoderOptions::new().with_mode(CompressionMode::Font);
+
+        assert_eq!(BrotliEncoderOptions::text_mode(), text_mode);
+        assert_eq!(BrotliEncoderOptions::font_mode(), font_mode);
+
+        let http_response = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Generic)
+            .with_quality(4)
+            .unwrap()
+            .with_window_size(20)
+            .unwrap();
+        let static_asset = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Text)
+            .with_quality(Quality::best())
+            .unwrap()
+            .with_window_size(WindowSize::best())
+            .unwrap();
+
+        assert_eq!(BrotliEncoderOptions::for_http_response(), http_response);
+        assert_eq!(BrotliEncoderOptions::for_static_asset(), static_asset);
+    }
+
+    #[test]
+    fn getters_return_set_values() {
+        let options = BrotliEncoderOptions::new();
+
+        assert_eq!(options.get_mode(), None);
+        assert_eq!(options.get_quality(), None);
+        assert_eq!(options.get_window_size(), None);
+        assert_eq!(options.get_context_modeling(), None);
+
+        let options = options
+            .with_mode(CompressionMode::Font)
+            .with_quality(4)
+            .unwrap()
+            .with_window_size(18)
+            .unwrap()
+            .with_block_size(17)
+            .unwrap()
+            .with_context_modeling(false)
+            .with_size_hint_usize(1000)
+            .with_distance_codes(DirectDistanceCodes::new(4, PostfixBits::new(2).unwrap()).unwrap())
+            .with_stream_offset_from_bytes(12)
+            .unwrap();
+
+        assert_eq!(options.get_mode(), Some(CompressionMode::Font));
+        assert_eq!(options.get_quality(), Some(Quality::new(4).unwrap()));
+        assert_eq!(
+            options.get_window_size(),
+            Some(LargeWindowSize::new(18).unwrap())
+        );
+        assert_eq!(options.get_block_size(), Some(BlockSize::new(17).unwrap()));
+        assert_eq!(options.get_context_modeling(), Some(false));
+        assert_eq!(options.get_size_hint(), Some(1000));
+        assert_eq!(options.get_postfix_bits(), Some(2));
+        assert_eq!(options.get_direct_distance_codes(), Some(4));
+        assert_eq!(options.get_stream_offset(), Some(12));
+
+        assert_eq!(options.clone().with_defaults(), options);
+    }
+
+    #[test]
+    fn with_defaults_matches_unset_options() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let mut fresh = BrotliEncoderOptions::new().build().unwrap();
+        let mut defaults = BrotliEncoderOptions::new().with_defaults().build().unwrap();
+
+        assert_eq!(
+            compress_all(&mut fresh, &input),
+            compress_all(&mut defaults, &input)
+        );
+    }
+
+    #[test]
+    fn write_json_to_encoder() {
+        let value = serde_json::json!({
+            "name": "brotli",
+            "qualities": (0..=11).collect::<Vec<_>>(),
+        });
+
+        let mut encoder = BrotliEncoder::new();
+        serde_json::to_writer(&mut encoder, &value).unwrap();
+        encoder.finish().unwrap();
+
+        let compressed = encoder.take_output_to_vec();
+        assert!(encoder.is_finished());
+
+        let decompressed = crate::decompress_to_vec(&compressed).unwrap();
+        let decoded: serde_json::Value = serde_json::from_slice(&decompressed).unwrap();
+
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn drain_to_writer_matches_compressor_writer() {
+        let input = b"hello hello hello hello hello".repeat(1000);
+
+        let mut encoder = BrotliEncoder::new();
+        let mut compressed = Vec::new();
+        let mut remaining = input.as_slice();
+
+        while !encoder.is_finished() {
+            let bytes_read = encoder
+                .give_input(remaining, BrotliOperation::Finish)
+                .unwrap();
+            remaining = &remaining[bytes_read..];
+
+            let written = encoder.drain_to_writer(&mut compressed).unwrap();
+            assert!(!encoder.has_output());
+            assert!(written <= compressed.len());
+        }
+
+        assert_eq!(encoder.drain_to_writer(&mut compressed).unwrap(), 0);
+        assert_eq!(encoder.total_out(), compressed.len());
+
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(&input).unwrap();
+        assert_eq!(compressor.into_inner().unwrap(), compressed);
+    }
+
+    #[test]
+    fn write_large_json_to_encoder() {
+        let value = serde_json::json!({
+            "values": (0..100_000).collect::<Vec<u32>>(),
+        });
+        let options = BrotliEncoderOptions::new().with_window_size(16).unwrap();
+
+        // the output is never taken, so the encoder runs out of room
+        let mut encoder = options.build().unwrap();
+        let err = serde_json::to_writer(&mut encoder, &value).unwrap_err();
+        assert_eq!(err.io_error_kind(), Some(io::ErrorKind::WriteZero));
+
+        let mut writer = CompressorWriter::with_encoder(options.build().unwrap(), Vec::new());
+        serde_json::to_writer(&mut writer, &value).unwrap();
+        let compressed = writer.into_inner().unwrap();
+
+        let decompressed = crate::decompress_to_vec(&compressed).unwrap();
+        let decoded: serde_json::Value = serde_json::from_slice(&decompressed).unwrap();
+
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn write_with_output_taken_between_writes() {
+        let input = (0..1u32 << 20)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let mut encoder = BrotliEncoderOptions::new()
+            .with_quality(5)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut compressed = Vec::new();
+
+        for chunk in input.chunks(100 * 1024) {
+            let mut chunk = chunk;
+
+            while !chunk.is_empty() {
+                let written = encoder.write(chunk).unwrap();
+                chunk = &chunk[written..];
+                compressed.extend(encoder.take_output_to_vec());
+            }
+        }
+
+        encoder.finish().unwrap();
+        compressed.extend(encoder.take_output_to_vec());
+
+        assert_eq!(crate::decompress_to_vec(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn size_hint_usize_clamps() {
+        let mut options = BrotliEncoderOptions::new();
+
+        options.size_hint_usize(4096);
+        assert_eq!(options.get_size_hint(), Some(4096));
+        assert_eq!(&options, BrotliEncoderOptions::new().size_hint(4096));
+
+        options.size_hint_usize(u32::MAX as usize);
+        assert_eq!(options.get_size_hint(), Some(u32::MAX));
+
+        options.size_hint_usize(usize::MAX);
+        assert_eq!(options.get_size_hint(), Some(u32::MAX));
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn consuming_setters_match_mut_setters() {
+        let mut expected = BrotliEncoderOptions::new();
+        expected
+            .mode(CompressionMode::Text)
+            .quality(Quality::best())
+            .unwrap()
+            .window_size(WindowSize::best())
+            .unwrap()
+            .block_size(BlockSize::best())
+            .unwrap()
+            .context_modeling(false)
+            .size_hint_usize(1024)
+            .distance_codes(DirectDistanceCodes::new(2, PostfixBits::new(1).unwrap()).unwrap())
+            .stream_offset_from_bytes(16)
+            .unwrap();
+
+        let options = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Text)
+            .with_quality(Quality::best())
+            .unwrap()
+            .with_window_size(WindowSize::best())
+            .unwrap()
+            .with_block_size(BlockSize::best())
+            .unwrap()
+            .with_context_modeling(false)
+            .with_size_hint_usize(1024)
+            .with_distance_codes(DirectDistanceCodes::new(2, PostfixBits::new(1).unwrap()).unwrap())
+            .with_stream_offset_from_bytes(16)
+            .unwrap();
+
+        assert_eq!(options, expected);
+        assert!(options.build().is_ok());
+    }
+
+    fn compress_all<M: WindowMode>(encoder: &mut BrotliEncoder<M>, mut input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !encoder.is_finished() {
+            let res = encoder
+                .compress(input, &mut buf, BrotliOperation::Finish)
+                .unwrap();
+
+            input = &input[res.bytes_read..];
+            output.extend_from_slice(&buf[..res.bytes_written]);
+        }
+
+        output
+    }
+
+    #[test]
+    fn reset_matches_fresh_encoder() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let expected = compress_all(&mut BrotliEncoder::new(), &input);
+
+        let mut encoder = BrotliEncoder::new();
+        encoder
+            .give_input(&input[..100], BrotliOperation::Process)
+            .unwrap();
+        encoder.reset();
+
+        assert!(!encoder.has_output());
+        assert_eq!(compress_all(&mut encoder, &input), expected);
+
+        encoder.reset();
+
+        assert_eq!(compress_all(&mut encoder, &input), expected);
+    }
+
+    #[test]
+    fn reset_keeps_large_window_support() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let mut encoder = BrotliEncoderOptions::new()
+            .large_window_size(LargeWindowSize::best())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        encoder.reset();
+        let compressed = compress_all(&mut encoder, &input);
+
+        assert!(crate::decompress_to_vec(&compressed).is_err());
+
+        let mut decoder = crate::decode::BrotliDecoderOptions::new()
+            .with_large_window_size(true)
+            .build()
+            .unwrap();
+        let mut output = Vec::new();
+        decoder
+            .decompress_all_into(&compressed, &mut output)
+            .unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn total_out_counts_taken_output() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let mut encoder = BrotliEncoder::new();
+        let mut output = [0; 16];
+
+        let res = encoder
+            .compress(&input, &mut output, BrotliOperation::Finish)
+            .unwrap();
+        let mut expected = res.bytes_written;
+
+        assert_eq!(encoder.total_out(), expected);
+
+        while let Some(taken) = unsafe { encoder.take_output() } {
+            expected += taken.len();
+        }
+
+        assert!(encoder.is_finished());
+        assert_eq!(encoder.total_out(), expected);
+
+        encoder.reset();
+
+        assert_eq!(encoder.total_out(), 0);
+    }
+
+    #[test]
+    fn take_output_to_vec_matches_compressor_writer() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let expected = {
+            let mut writer = CompressorWriter::new(Vec::new());
+            writer.write_all(&input).unwrap();
+            writer.into_inner().unwrap()
         };
 
-        (inner, encoder)
-    }
+        let mut encoder = BrotliEncoder::new();
+        let mut remaining = input.as_slice();
+        let mut output = Vec::new();
 
-    fn finish(&mut self) -> io::Result<()> {
-        self.encoder.finish()?;
-        self.flush_encoder_output()
-    }
+        assert!(encoder.take_output_to_vec().is_empty());
 
-    fn flush_encoder_output(&mut self) -> io::Result<()> {
-        while let Some(output) = unsafe { self.encoder.take_output() } {
-            self.panicked = true;
-            let r = self.inner.write_all(output);
-            self.panicked = false;
-            r?;
+        while !encoder.is_finished() {
+            let bytes_read = encoder
+                .give_input(remaining, BrotliOperation::Finish)
+                .unwrap();
+            remaining = &remaining[bytes_read..];
+            output.extend(encoder.take_output_to_vec());
         }
 
-        Ok(())
+        assert_eq!(output, expected);
     }
-}
 
-impl<W: Write> Write for CompressorWriter<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let bytes_read = self.encoder.give_input(buf, BrotliOperation::Process)?;
-        self.flush_encoder_output()?;
+    #[test]
+    fn reset_with_options_matches_build() {
+        let input = b"hello world, hello brotli, hello world".repeat(64);
+        let options = BrotliEncoderOptions::new()
+            .with_mode(CompressionMode::Text)
+            .with_quality(Quality::new(5).unwrap())
+            .unwrap()
+            .with_window_size(WindowSize::new(16).unwrap())
+            .unwrap();
+        let expected = compress_all(&mut options.build().unwrap(), &input);
 
-        Ok(bytes_read)
+        let mut encoder = BrotliEncoder::new_in(std::alloc::System);
+        compress_all(&mut encoder, &input);
+        encoder.reset_with_options(&options).unwrap();
+
+        assert_eq!(compress_all(&mut encoder, &input), expected);
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.encoder.flush()?;
-        self.flush_encoder_output()?;
+    #[test]
+    fn quality_level_raw() {
+        let mut options = BrotliEncoderOptions::new();
 
-        self.inner.flush()
+        assert!(options.quality_level_raw(9).is_ok());
+        assert_eq!(options.quality, Some(Quality::new(9).unwrap()));
+        assert_eq!(
+            options.quality_level_raw(12).unwrap_err(),
+            SetParameterError::InvalidQuality
+        );
     }
-}
 
-impl<W: Write> Drop for CompressorWriter<W> {
-    fn drop(&mut self) {
-        if !self.panicked {
-            let _r = self.finish();
+    #[test]
+    fn window_size_bytes_round_trip() {
+        assert_eq!(WindowSize::best().bytes(), (1 << 24) - 16);
+        assert_eq!(WindowSize::worst().bytes(), (1 << 10) - 16);
+        assert_eq!(LargeWindowSize::best().bytes(), (1 << 30) - 16);
+        assert_eq!(BlockSize::best().memory_usage(), 3 << 24);
+
+        for bits in 10..=24 {
+            let window_size = WindowSize::new(bits).unwrap();
+
+            assert_eq!(WindowSize::from_bytes(window_size.bytes()), Ok(window_size));
+            assert_eq!(
+                WindowSize::from_bytes(window_size.bytes() - 1),
+                Ok(window_size)
+            );
+            assert_eq!(
+                LargeWindowSize::from(window_size).bytes(),
+                window_size.bytes()
+            );
         }
+
+        assert_eq!(WindowSize::from_bytes(0), Ok(WindowSize::worst()));
+        assert_eq!(
+            WindowSize::from_bytes(WindowSize::best().bytes() + 1),
+            Err(SetParameterError::InvalidWindowSize)
+        );
     }
-}
 
-/// Error returned from [`CompressorWriter::into_inner`], when the underlying
-/// writer has previously panicked. Contains the encoder that was used for
-/// compression.
-#[derive(Debug)]
-pub struct WriterPanicked {
-    encoder: BrotliEncoder,
-}
+    #[test]
+    fn clamped_constructors() {
+        for level in 0..=u8::MAX {
+            let clamped = Quality::from_level_clamped(level);
 
-impl WriterPanicked {
-    /// Returns the encoder that was used for compression. It is unknown what
-    /// data was fed to the encoder, so simply using it to finish it is not a
-    /// good idea.
-    pub fn into_inner(self) -> BrotliEncoder {
-        self.encoder
+            assert_eq!(clamped.level(), level.min(11));
+            assert_eq!(
+                Quality::new(level).ok(),
+                Some(clamped).filter(|_| level <= 11)
+            );
+        }
+
+        for bits in 0..=u8::MAX {
+            assert_eq!(
+                WindowSize::from_bits_clamped(bits).bits(),
+                bits.clamp(10, 24)
+            );
+            assert_eq!(
+                LargeWindowSize::from_bits_clamped(bits).bits(),
+                bits.clamp(10, 30)
+            );
+            assert_eq!(
+                BlockSize::from_bits_clamped(bits).bits(),
+                bits.clamp(16, 24)
+            );
+        }
     }
-}
 
-impl Error for WriterPanicked {}
+    #[test]
+    fn u8_conversions_round_trip() {
+        for level in 0..=11 {
+            assert_eq!(u8::from(Quality::try_from(level).unwrap()), level);
+        }
 
-impl fmt::Display for WriterPanicked {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(
-            "CompressorWriter inner writer panicked, what data remains unwritten is not known",
-        )
-    }
-}
+        for bits in 10..=24 {
+            assert_eq!(u8::from(WindowSize::try_from(bits).unwrap()), bits);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        for bits in 10..=30 {
+            assert_eq!(u8::from(LargeWindowSize::try_from(bits).unwrap()), bits);
+        }
 
-    #[test]
-    fn invalid_quality() {
-        let invalid = Quality::new(12);
+        for bits in 16..=24 {
+            assert_eq!(u8::from(BlockSize::try_from(bits).unwrap()), bits);
+        }
 
-        assert_eq!(invalid.unwrap_err(), SetParameterError::InvalidQuality);
+        let level: u8 = Quality::best().into();
+        assert_eq!(level, 11);
+
+        assert_eq!(
+            u32::from(CompressionMode::Generic),
+            BrotliEncoderMode_BROTLI_MODE_GENERIC as u32
+        );
+        assert_eq!(
+            u32::from(CompressionMode::Text),
+            BrotliEncoderMode_BROTLI_MODE_TEXT as u32
+        );
+        assert_eq!(
+            u32::from(CompressionMode::Font),
+            BrotliEncoderMode_BROTLI_MODE_FONT as u32
+        );
+
+        assert_eq!(
+            Quality::try_from(12).unwrap_err(),
+            SetParameterError::InvalidQuality
+        );
+        assert_eq!(
+            WindowSize::try_from(9).unwrap_err(),
+            SetParameterError::InvalidWindowSize
+        );
+        assert_eq!(
+            LargeWindowSize::try_from(31).unwrap_err(),
+            SetParameterError::InvalidWindowSize
+        );
+        assert_eq!(
+            BlockSize::try_from(15).unwrap_err(),
+            SetParameterError::InvalidBlockSize
+        );
     }
 
     #[test]
@@ -914,13 +4229,117 @@ mod tests {
     }
 
     #[test]
-    fn valid_stream_offset() {
-        let res = BrotliEncoderOptions::new().stream_offset(1 << 30).build();
+    fn window_size_from_large_window_size() {
+        let res = BrotliEncoderOptions::new()
+            .window_size(LargeWindowSize::new(20).unwrap())
+            .unwrap()
+            .build();
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn window_size_from_invalid_large_window_size() {
+        let mut options = BrotliEncoderOptions::new();
+        let res = options.window_size(LargeWindowSize::best());
+
+        assert_eq!(res.unwrap_err(), SetParameterError::InvalidWindowSize);
+    }
+
+    #[test]
+    fn large_window_size_from_window_size() {
+        let res = BrotliEncoderOptions::new()
+            .large_window_size(WindowSize::best())
+            .unwrap()
+            .build();
 
         assert!(res.is_ok());
     }
 
     #[test]
+    fn setters_from_raw_integers() {
+        let res = BrotliEncoderOptions::new()
+            .quality(9)
+            .unwrap()
+            .window_size(22)
+            .unwrap()
+            .block_size(20)
+            .unwrap()
+            .build();
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn setters_from_invalid_raw_integers() {
+        let mut options = BrotliEncoderOptions::new();
+
+        assert_eq!(
+            options.quality(12).unwrap_err(),
+            SetParameterError::InvalidQuality
+        );
+        assert_eq!(
+            options.window_size(25).unwrap_err(),
+            SetParameterError::InvalidWindowSize
+        );
+        assert_eq!(
+            options.large_window_size(31).unwrap_err(),
+            SetParameterError::InvalidWindowSize
+        );
+        assert_eq!(
+            options.block_size(25).unwrap_err(),
+            SetParameterError::InvalidBlockSize
+        );
+    }
+
+    #[test]
+    fn context_modeling_polarity() {
+        let mut options = BrotliEncoderOptions::new();
+
+        options.context_modeling(false);
+        assert_eq!(options.disable_context_modeling, Some(true));
+
+        options.context_modeling(true);
+        assert_eq!(options.disable_context_modeling, Some(false));
+    }
+
+    #[test]
+    fn valid_stream_offset() {
+        let mut options = BrotliEncoderOptions::new();
+        options.stream_offset_from_bytes(1 << 30).unwrap();
+
+        assert_eq!(options.stream_offset, Some(1 << 30));
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn stream_offset_from_bytes_rejects_too_large() {
+        let mut options = BrotliEncoderOptions::new();
+
+        assert_eq!(
+            options.stream_offset_from_bytes((1 << 30) + 1).unwrap_err(),
+            SetParameterError::InvalidStreamOffset
+        );
+        assert_eq!(options.stream_offset, None);
+
+        let res = BrotliEncoderOptions::new().with_stream_offset_from_bytes(usize::MAX);
+        assert_eq!(res.unwrap_err(), SetParameterError::InvalidStreamOffset);
+    }
+
+    #[test]
+    fn stream_offset_from_window() {
+        let mut options = BrotliEncoderOptions::new();
+
+        options.stream_offset_from_window(WindowSize::new(16).unwrap());
+        assert_eq!(options.stream_offset, Some((1 << 16) - 16));
+
+        options.stream_offset_from_window(LargeWindowSize::best());
+        assert_eq!(options.stream_offset, Some((1 << 30) - 16));
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn invalid_stream_offset() {
         let res = BrotliEncoderOptions::new()
             .stream_offset((1 << 30) + 2)
@@ -930,6 +4349,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn valid_postfix_bits() {
         let res = BrotliEncoderOptions::new().postfix_bits(3).build();
 
@@ -937,6 +4357,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn invalid_postfix_bits() {
         let res = BrotliEncoderOptions::new().postfix_bits(7).build();
 
@@ -944,6 +4365,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn valid_direct_distance_codes() {
         let res = BrotliEncoderOptions::new()
             .postfix_bits(3)
@@ -954,6 +4376,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn invalid_direct_distance_codes() {
         let res = BrotliEncoderOptions::new()
             .postfix_bits(2)
@@ -965,4 +4388,315 @@ mod tests {
             SetParameterError::InvalidDirectDistanceCodes
         );
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn validate_matches_build() {
+        let invalid = [
+            (
+                BrotliEncoderOptions::new().postfix_bits(4).clone(),
+                SetParameterError::InvalidPostfix,
+            ),
+            (
+                BrotliEncoderOptions::new()
+                    .direct_distance_codes(121)
+                    .clone(),
+                SetParameterError::InvalidDirectDistanceCodes,
+            ),
+            (
+                BrotliEncoderOptions::new()
+                    .postfix_bits(1)
+                    .direct_distance_codes(3)
+                    .clone(),
+                SetParameterError::InvalidDirectDistanceCodes,
+            ),
+            (
+                BrotliEncoderOptions::new()
+                    .stream_offset((1 << 30) + 1)
+                    .clone(),
+                SetParameterError::InvalidStreamOffset,
+            ),
+        ];
+
+        for (options, err) in invalid {
+            assert_eq!(options.validate(), Err(err));
+            assert_eq!(options.build().unwrap_err(), err);
+        }
+
+        let valid = BrotliEncoderOptions::best()
+            .with_distance_codes(
+                DirectDistanceCodes::new(120, PostfixBits::new(3).unwrap()).unwrap(),
+            )
+            .with_stream_offset_from_bytes(1 << 30)
+            .unwrap();
+
+        assert_eq!(valid.validate(), Ok(()));
+        assert!(valid.build().is_ok());
+    }
+
+    #[test]
+    fn postfix_rejects_mismatching_distance_codes() {
+        let mut options = BrotliEncoderOptions::new();
+        options
+            .distance_codes(DirectDistanceCodes::new(120, PostfixBits::new(3).unwrap()).unwrap());
+
+        assert_eq!(
+            options.postfix(PostfixBits::new(2).unwrap()).unwrap_err(),
+            SetParameterError::InvalidDirectDistanceCodes
+        );
+        assert_eq!(options.get_postfix_bits(), Some(3));
+
+        options
+            .distance_codes(DirectDistanceCodes::new(8, PostfixBits::new(1).unwrap()).unwrap())
+            .postfix(PostfixBits::new(2).unwrap())
+            .unwrap();
+
+        assert_eq!(options.get_postfix_bits(), Some(2));
+        assert_eq!(options.get_direct_distance_codes(), Some(8));
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn distance_codes_are_validated_on_construction() {
+        for bits in 0..=3 {
+            let postfix = PostfixBits::new(bits).unwrap();
+
+            for value in 0..=(15 << bits) + 1 {
+                let res = DirectDistanceCodes::new(value, postfix);
+
+                if value % (1 << bits) == 0 && value <= 15 << bits {
+                    let codes = res.unwrap();
+                    let options = BrotliEncoderOptions::new().with_distance_codes(codes);
+
+                    assert!(options.build().is_ok());
+                } else {
+                    assert_eq!(
+                        res.unwrap_err(),
+                        SetParameterError::InvalidDirectDistanceCodes
+                    );
+                }
+            }
+        }
+
+        assert_eq!(PostfixBits::new(4), Err(SetParameterError::InvalidPostfix));
+    }
+
+    #[test]
+    fn input_after_finish_is_rejected() {
+        let mut encoder = BrotliEncoder::new();
+        encoder.finish().unwrap();
+
+        assert!(encoder.flush().is_ok());
+        assert_eq!(
+            encoder.give_input(b"input", BrotliOperation::Flush),
+            Err(EncodeError)
+        );
+    }
+
+    #[test]
+    fn flush_propagates_encoder_error() {
+        let mut encoder = BrotliEncoder::new();
+
+        // start a metadata block without giving the encoder room for output,
+        // so the block stays unfinished.
+        encoder
+            .compress(b"metadata", &mut [], BrotliOperation::EmitMetadata)
+            .unwrap();
+
+        assert_eq!(encoder.flush(), Err(EncodeError));
+    }
+
+    #[test]
+    fn compressor_writer_keeps_unwritten_finish_output() {
+        #[derive(Debug)]
+        struct LimitedWriter {
+            written: Vec<u8>,
+            limit: usize,
+        }
+
+        impl Write for LimitedWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written.len() >= self.limit {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+
+                let n = buf.len().min(self.limit - self.written.len());
+                self.written.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut state = 0x9e3779b9u32;
+        let input: Vec<u8> = (0..200 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        let writer = LimitedWriter {
+            written: Vec::new(),
+            limit: usize::MAX,
+        };
+        let mut compressor = CompressorWriter::new(writer);
+        compressor.write_all(&input).unwrap();
+
+        // fail partway through writing the output of finishing the stream
+        let limit = compressor.get_ref().written.len() + 1000;
+        compressor.get_mut().limit = limit;
+
+        let err = compressor.into_inner().unwrap_err();
+        assert_eq!(err.error().kind(), io::ErrorKind::WriteZero);
+
+        let (writer, encoder, _) = err.into_inner().into_parts();
+        let encoder = encoder.unwrap();
+
+        assert_eq!(writer.written.len(), limit);
+        assert!(encoder.has_output());
+        assert!(!encoder.is_finished());
+    }
+
+    #[test]
+    fn emit_metadata_is_skipped_by_decoder() {
+        let mut encoder = BrotliEncoder::new();
+        let mut output = Vec::new();
+
+        encoder
+            .give_input(b"hello ", BrotliOperation::Process)
+            .unwrap();
+        encoder.emit_metadata(b"metadata", &mut output).unwrap();
+        encoder.emit_metadata(&[], &mut output).unwrap();
+        encoder
+            .give_input(b"world", BrotliOperation::Process)
+            .unwrap();
+        encoder.finish().unwrap();
+
+        while let Some(buf) = unsafe { encoder.take_output() } {
+            output.extend_from_slice(buf);
+        }
+
+        assert!(output.windows(8).any(|w| w == b"metadata"));
+        assert_eq!(crate::decompress_to_vec(&output).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn emit_metadata_rejects_large_metadata() {
+        let mut encoder = BrotliEncoder::new();
+        let metadata = vec![0; MAX_METADATA_SIZE + 1];
+
+        assert_eq!(
+            encoder.emit_metadata(&metadata, &mut Vec::new()),
+            Err(EncodeError)
+        );
+    }
+
+    #[test]
+    fn compressor_writer_write_propagates_encoder_error() {
+        let mut encoder = BrotliEncoder::new();
+        encoder
+            .give_input(b"input", BrotliOperation::Flush)
+            .unwrap();
+
+        // the pending flush was never completed, so processing more input fails
+        let mut writer = CompressorWriter::with_encoder(encoder, Vec::new());
+        let err = writer.write(b"more input").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(err.into_inner().unwrap().is::<EncodeError>());
+    }
+
+    #[test]
+    fn compressor_writer_flush_propagates_encoder_error() {
+        let mut encoder = BrotliEncoder::new();
+        encoder
+            .compress(b"metadata", &mut [], BrotliOperation::EmitMetadata)
+            .unwrap();
+
+        // the metadata block was never completed, so the encoder rejects the flush
+        let mut writer = CompressorWriter::with_encoder(encoder, Vec::new());
+        let err = writer.flush().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(err.into_inner().unwrap().is::<EncodeError>());
+        assert_eq!(writer.stats().flush_count, 0);
+    }
+
+    #[test]
+    fn prepare_raw_dictionary() {
+        let res = PreparedDictionary::from_raw_data(b"hello world", Quality::best());
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn prepared_dictionary_len() {
+        let small_data = b"hello world".repeat(16);
+        let large_data = b"hello world".repeat(1024);
+
+        let small = PreparedDictionary::from_raw_data(&small_data, Quality::best()).unwrap();
+        let large = PreparedDictionary::from_raw_data(&large_data, Quality::best()).unwrap();
+
+        assert!(!small.is_empty());
+        assert!(small.len() >= small_data.len());
+        assert!(large.len() >= large_data.len());
+        assert!(large.len() > small.len());
+        assert_eq!(small.clone().len(), small.len());
+    }
+
+    #[test]
+    fn prepare_empty_raw_dictionary() {
+        let res = PreparedDictionary::from_raw_data(&[], Quality::best());
+
+        assert_eq!(res.unwrap_err(), DictionaryError::Empty);
+    }
+
+    #[test]
+    fn prepare_empty_serialized_dictionary() {
+        let res = PreparedDictionary::from_serialized(&[], Quality::best());
+
+        assert_eq!(res.unwrap_err(), DictionaryError::Empty);
+    }
+
+    #[test]
+    fn prepare_serialized_dictionary_is_unsupported() {
+        // magic bytes, an 11 byte LZ77 dictionary and no custom static dictionary
+        let serialized = [&[0x91, 0x00, 11][..], b"hello world", &[0x00]].concat();
+        let res = PreparedDictionary::from_serialized(&serialized, Quality::best());
+
+        assert_eq!(res.unwrap_err(), DictionaryError::Unsupported);
+    }
+
+    #[test]
+    fn compress_with_prepared_dictionary() {
+        let dictionary_data = b"the quick brown fox jumps over the lazy dog".repeat(4);
+        let input = b"the quick brown fox jumps over the lazy cat";
+
+        let dictionary =
+            PreparedDictionary::from_raw_data(&dictionary_data, Quality::best()).unwrap();
+        assert_eq!(dictionary, dictionary.clone());
+
+        let encoder = BrotliEncoderOptions::new()
+            .dictionary(&dictionary)
+            .build()
+            .unwrap();
+        let mut writer = CompressorWriter::with_encoder(encoder, Vec::new());
+        writer.write_all(input).unwrap();
+        let compressed = writer.into_inner().unwrap();
+
+        let mut decoder = crate::BrotliDecoder::new();
+        decoder
+            .attach_dictionary(SharedDictionaryType::Raw, &dictionary_data)
+            .unwrap();
+
+        let mut output = [0; 64];
+        let res = decoder.decompress(&compressed, &mut output).unwrap();
+
+        assert_eq!(&output[..res.bytes_written], input);
+    }
 }
@@ -35,6 +35,37 @@
 //! [`BufRead`]: https://doc.rust-lang.org/std/io/trait.BufRead.html
 //! [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
 //!
+//! With the `tokio` or `futures-io` feature enabled, the readers implement
+//! `AsyncRead` when wrapping an `AsyncBufRead` and the writers implement
+//! `AsyncWrite` when wrapping an `AsyncWrite`, using the traits of the
+//! respective crate. Both features can be enabled at the same time. Use
+//! `CompressorWriter::new_async` to create an asynchronous compressor, the
+//! compression stream is finished when the writer is shut down (closed).
+//!
+//! The `tokio` feature also provides `compress_async` and
+//! `decompress_async`, which run one-shot compression on the blocking thread
+//! pool so the runtime is not blocked.
+//!
+//! With the `serde` feature enabled, the parameter types and
+//! [`BrotliEncoderOptions`] implement `Serialize` and `Deserialize`.
+//! [`Quality`] and the size types are represented by their `u8` value and
+//! [`CompressionMode`] by its lowercase name. Deserialization rejects values
+//! that the respective `new` constructor would reject.
+//!
+//! [`BrotliEncoderOptions`]: encode::BrotliEncoderOptions
+//!
+//! The `bytes` feature provides `compress_bytes` and `decompress_bytes`,
+//! which take and return `bytes::Bytes`, and `BytesMutWriter`, which appends
+//! written data to a `bytes::BytesMut`.
+//!
+//! The `std` feature is enabled by default. Disabling it makes the crate
+//! `no_std`, depending only on `core` and `alloc`, for embedded targets and
+//! WebAssembly. The one-shot functions such as [`compress`] and
+//! [`decompress_to_vec`], the parameter types, the error types and the
+//! low-level [`BrotliEncoder`] and [`BrotliDecoder`] remain available, while
+//! the readers, writers and everything else built on `std::io` require
+//! `std`. The `tokio`, `futures-io` and `bytes` features enable `std`.
+//!
 //! To compress a file with brotli:
 //!
 //! ```no_run
@@ -105,9 +136,9 @@
 //! use brotlic::{BlockSize, BrotliEncoderOptions, CompressorWriter, Quality, WindowSize};
 //!
 //! let encoder = BrotliEncoderOptions::new()
-//!     .quality(Quality::best())
-//!     .window_size(WindowSize::best())
-//!     .block_size(BlockSize::best())
+//!     .with_quality(Quality::best())?
+//!     .with_window_size(WindowSize::best())?
+//!     .with_block_size(BlockSize::best())?
 //!     .build()?;
 //!
 //! let compressed_writer = CompressorWriter::with_encoder(encoder, Vec::new());
@@ -119,19 +150,52 @@
 //! the higher level abstractions like `CompressorWriter<W>` or
 //! `DecompressorReader<R>`.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(warnings)]
 #![deny(missing_docs)]
 
+extern crate alloc;
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod async_io;
 pub mod decode;
 pub mod encode;
-
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::alloc::{GlobalAlloc, Layout};
+use core::convert::Infallible;
+use core::ffi::{c_int, c_void};
+use core::str::Utf8Error;
+use core::{fmt, ptr};
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::os::raw::c_int;
-use std::{fmt, io};
+#[cfg(feature = "std")]
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
 
 use brotlic_sys::*;
-pub use decode::{BrotliDecoder, BrotliDecoderOptions, DecompressorReader, DecompressorWriter};
-pub use encode::{BrotliEncoder, BrotliEncoderOptions, CompressorReader, CompressorWriter};
+pub use decode::{BrotliDecoder, BrotliDecoderOptions};
+use decode::{DecodeError, DecodeToStringError, DecoderInfo};
+#[cfg(feature = "std")]
+pub use decode::{DecompressorReader, DecompressorWriter};
+use encode::BrotliOperation;
+pub use encode::{BrotliEncoder, BrotliEncoderOptions};
+#[cfg(feature = "std")]
+pub use encode::{CompressorReader, CompressorWriter};
 
 /// Quality level of the brotli compression
 ///
@@ -139,7 +203,7 @@ pub use encode::{BrotliEncoder, BrotliEncoderOptions, CompressorReader, Compress
 /// compression ratio at the cost of run-time speed. [`Quality::worst()`]
 /// represents the worst available quality that maximizes speed at the expense
 /// of compression ratio.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Quality(u8);
 
 impl Quality {
@@ -196,6 +260,30 @@ impl Quality {
         Quality(level)
     }
 
+    /// Constructs a new quality level, clamping `level` to the valid range.
+    ///
+    /// Valid `level` range from 0 to 11 inclusive. Greater levels are silently
+    /// replaced by the [best](Quality::best) quality, so this never fails. Use
+    /// [`new`](Quality::new) to reject invalid levels instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::Quality;
+    ///
+    /// assert_eq!(Quality::from_level_clamped(0), Quality::worst());
+    /// assert_eq!(Quality::from_level_clamped(255), Quality::best());
+    /// assert_eq!(Quality::from_level_clamped(5).level(), 5);
+    /// ```
+    pub const fn from_level_clamped(level: u8) -> Quality {
+        // the minimum quality is zero, so only the upper bound can be exceeded
+        if level > BROTLI_MAX_QUALITY {
+            Quality(BROTLI_MAX_QUALITY)
+        } else {
+            Quality(level)
+        }
+    }
+
     /// The highest quality for brotli compression.
     ///
     /// This quality yields maximum compression ratio at the expense of run-time
@@ -252,6 +340,29 @@ impl Quality {
         Quality(BROTLI_MIN_QUALITY)
     }
 
+    /// Returns an iterator over all valid qualities, from [`worst`] to
+    /// [`best`].
+    ///
+    /// This is useful for benchmarks or parameter sweeps over every quality.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::Quality;
+    ///
+    /// let mut iter = Quality::iter();
+    ///
+    /// assert_eq!(iter.len(), 12);
+    /// assert_eq!(iter.next(), Some(Quality::worst()));
+    /// assert_eq!(iter.next_back(), Some(Quality::best()));
+    /// ```
+    ///
+    /// [`worst`]: Quality::worst
+    /// [`best`]: Quality::best
+    pub fn iter() -> impl DoubleEndedIterator<Item = Quality> + ExactSizeIterator + Clone {
+        (Self::worst().level()..=Self::best().level()).map(Quality)
+    }
+
     /// Returns an integer representing the quality level.
     ///
     /// # Examples
@@ -279,6 +390,35 @@ impl Default for Quality {
     }
 }
 
+impl TryFrom<u8> for Quality {
+    type Error = SetParameterError;
+
+    /// Attempts to construct a [`Quality`] from a raw quality level.
+    ///
+    /// # Errors
+    ///
+    /// See [`Quality::new`].
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        Quality::new(level)
+    }
+}
+
+impl From<Quality> for u8 {
+    /// Returns the raw quality level of a [`Quality`].
+    ///
+    /// This is equivalent to [`Quality::level`].
+    fn from(quality: Quality) -> Self {
+        quality.0
+    }
+}
+
+impl fmt::Display for Quality {
+    /// Formats the quality as `quality level N`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quality level {}", self.0)
+    }
+}
+
 /// The sliding window size (in bits) to use for compression.
 ///
 /// Its maximum size is currently limited to 16 MiB, as specified in RFC7932
@@ -289,7 +429,7 @@ impl Default for Quality {
 ///
 /// [`large_window_size`]: decode::BrotliDecoderOptions::large_window_size()
 /// [`BrotliDecoder`]: decode::BrotliDecoder
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct WindowSize(u8);
 
 impl WindowSize {
@@ -346,6 +486,32 @@ impl WindowSize {
         WindowSize(bits)
     }
 
+    /// Constructs a new sliding window size, clamping `bits` to the valid
+    /// range.
+    ///
+    /// Valid `bits` range from 10 to 24 inclusive. Out of range values are
+    /// silently replaced by the nearest valid one, so this never fails. Use
+    /// [`new`](WindowSize::new) to reject invalid values instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::WindowSize;
+    ///
+    /// assert_eq!(WindowSize::from_bits_clamped(0), WindowSize::worst());
+    /// assert_eq!(WindowSize::from_bits_clamped(255), WindowSize::best());
+    /// assert_eq!(WindowSize::from_bits_clamped(20).bits(), 20);
+    /// ```
+    pub const fn from_bits_clamped(bits: u8) -> WindowSize {
+        if bits < BROTLI_MIN_WINDOW_BITS {
+            WindowSize(BROTLI_MIN_WINDOW_BITS)
+        } else if bits > BROTLI_MAX_WINDOW_BITS {
+            WindowSize(BROTLI_MAX_WINDOW_BITS)
+        } else {
+            WindowSize(bits)
+        }
+    }
+
     /// Constructs the best sliding window size to use for brotli compression.
     ///
     /// This is currently limited to 24 bits (16 MiB) due to RFC7932 (Brotli
@@ -408,6 +574,27 @@ impl WindowSize {
         WindowSize(BROTLI_MIN_WINDOW_BITS)
     }
 
+    /// Returns an iterator over all valid window sizes, from [`worst`] to
+    /// [`best`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::WindowSize;
+    ///
+    /// let mut iter = WindowSize::iter();
+    ///
+    /// assert_eq!(iter.len(), 15);
+    /// assert_eq!(iter.next(), Some(WindowSize::worst()));
+    /// assert_eq!(iter.next_back(), Some(WindowSize::best()));
+    /// ```
+    ///
+    /// [`worst`]: WindowSize::worst
+    /// [`best`]: WindowSize::best
+    pub fn iter() -> impl DoubleEndedIterator<Item = WindowSize> + ExactSizeIterator + Clone {
+        (Self::worst().bits()..=Self::best().bits()).map(WindowSize)
+    }
+
     /// Returns an integer representing the window size in bits.
     ///
     /// # Examples
@@ -423,6 +610,55 @@ impl WindowSize {
     pub const fn bits(&self) -> u8 {
         self.0
     }
+
+    /// Returns the size of the sliding window in bytes.
+    ///
+    /// This is `(1 << bits) - 16`, the maximum distance a back reference may
+    /// span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::WindowSize;
+    ///
+    /// assert_eq!(WindowSize::best().bytes(), (1 << 24) - 16);
+    /// ```
+    pub const fn bytes(&self) -> usize {
+        (1 << self.0) - 16
+    }
+
+    /// Constructs the smallest sliding window size that spans at least
+    /// `bytes` bytes.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `bytes` exceeds the size of the
+    /// [best](WindowSize::best) window size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::WindowSize;
+    ///
+    /// let window_size = WindowSize::from_bytes(100_000)?;
+    ///
+    /// assert_eq!(window_size.bits(), 17);
+    /// assert!(window_size.bytes() >= 100_000);
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn from_bytes(bytes: usize) -> Result<WindowSize, SetParameterError> {
+        let mut bits = BROTLI_MIN_WINDOW_BITS;
+
+        while bits <= BROTLI_MAX_WINDOW_BITS {
+            if WindowSize(bits).bytes() >= bytes {
+                return Ok(WindowSize(bits));
+            }
+
+            bits += 1;
+        }
+
+        Err(SetParameterError::InvalidWindowSize)
+    }
 }
 
 impl Default for WindowSize {
@@ -435,6 +671,36 @@ impl Default for WindowSize {
     }
 }
 
+impl TryFrom<u8> for WindowSize {
+    type Error = SetParameterError;
+
+    /// Attempts to construct a [`WindowSize`] from a raw number of bits.
+    ///
+    /// # Errors
+    ///
+    /// See [`WindowSize::new`].
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        WindowSize::new(bits)
+    }
+}
+
+impl From<WindowSize> for u8 {
+    /// Returns the raw number of bits of a [`WindowSize`].
+    ///
+    /// This is equivalent to [`WindowSize::bits`].
+    fn from(window_size: WindowSize) -> Self {
+        window_size.0
+    }
+}
+
+impl fmt::Display for WindowSize {
+    /// Formats the window size as `window N bits (M bytes)`, where `M` is
+    /// [`WindowSize::bytes`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "window {} bits ({} bytes)", self.0, self.bytes())
+    }
+}
+
 impl TryFrom<LargeWindowSize> for WindowSize {
     type Error = SetParameterError;
 
@@ -459,7 +725,7 @@ impl TryFrom<LargeWindowSize> for WindowSize {
 ///
 /// [`large_window_size`]: decode::BrotliDecoderOptions::large_window_size()
 /// [`BrotliDecoder`]: decode::BrotliDecoder
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct LargeWindowSize(u8);
 
 impl LargeWindowSize {
@@ -516,6 +782,38 @@ impl LargeWindowSize {
         LargeWindowSize(bits)
     }
 
+    /// Constructs a new large sliding window size, clamping `bits` to the valid
+    /// range.
+    ///
+    /// Valid `bits` range from 10 to 30 inclusive. Out of range values are
+    /// silently replaced by the nearest valid one, so this never fails. Use
+    /// [`new`](LargeWindowSize::new) to reject invalid values instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::LargeWindowSize;
+    ///
+    /// assert_eq!(
+    ///     LargeWindowSize::from_bits_clamped(0),
+    ///     LargeWindowSize::worst()
+    /// );
+    /// assert_eq!(
+    ///     LargeWindowSize::from_bits_clamped(255),
+    ///     LargeWindowSize::best()
+    /// );
+    /// assert_eq!(LargeWindowSize::from_bits_clamped(27).bits(), 27);
+    /// ```
+    pub const fn from_bits_clamped(bits: u8) -> LargeWindowSize {
+        if bits < BROTLI_MIN_WINDOW_BITS {
+            LargeWindowSize(BROTLI_MIN_WINDOW_BITS)
+        } else if bits > BROTLI_LARGE_MAX_WINDOW_BITS {
+            LargeWindowSize(BROTLI_LARGE_MAX_WINDOW_BITS)
+        } else {
+            LargeWindowSize(bits)
+        }
+    }
+
     /// Constructs the best large sliding window size to use for brotli
     /// compression.
     ///
@@ -575,6 +873,27 @@ impl LargeWindowSize {
         LargeWindowSize(BROTLI_MIN_WINDOW_BITS)
     }
 
+    /// Returns an iterator over all valid large window sizes, from [`worst`] to
+    /// [`best`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::LargeWindowSize;
+    ///
+    /// let mut iter = LargeWindowSize::iter();
+    ///
+    /// assert_eq!(iter.len(), 21);
+    /// assert_eq!(iter.next(), Some(LargeWindowSize::worst()));
+    /// assert_eq!(iter.next_back(), Some(LargeWindowSize::best()));
+    /// ```
+    ///
+    /// [`worst`]: LargeWindowSize::worst
+    /// [`best`]: LargeWindowSize::best
+    pub fn iter() -> impl DoubleEndedIterator<Item = LargeWindowSize> + ExactSizeIterator + Clone {
+        (Self::worst().bits()..=Self::best().bits()).map(LargeWindowSize)
+    }
+
     /// Returns an integer representing the large window size in bits.
     ///
     /// # Examples
@@ -590,6 +909,68 @@ impl LargeWindowSize {
     pub const fn bits(&self) -> u8 {
         self.0
     }
+
+    /// Returns the size of the sliding window in bytes.
+    ///
+    /// This is `(1 << bits) - 16`, the maximum distance a back reference may
+    /// span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::LargeWindowSize;
+    ///
+    /// assert_eq!(LargeWindowSize::best().bytes(), (1 << 30) - 16);
+    /// ```
+    pub const fn bytes(&self) -> usize {
+        (1 << self.0) - 16
+    }
+
+    /// Checks if this large window size is also a valid [`WindowSize`].
+    ///
+    /// Streams compressed with a standard window size can be decompressed by
+    /// any decoder, whereas larger window sizes require the decoder to enable
+    /// [`large_window_size`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::LargeWindowSize;
+    ///
+    /// assert!(LargeWindowSize::new(24)?.is_standard());
+    /// assert!(!LargeWindowSize::new(25)?.is_standard());
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [`large_window_size`]: decode::BrotliDecoderOptions::large_window_size()
+    pub const fn is_standard(&self) -> bool {
+        self.0 <= BROTLI_MAX_WINDOW_BITS
+    }
+
+    /// Converts this large window size into a [`WindowSize`], or returns
+    /// [`None`] if it is not [standard].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{LargeWindowSize, WindowSize};
+    ///
+    /// assert_eq!(
+    ///     LargeWindowSize::new(22)?.try_as_standard(),
+    ///     Some(WindowSize::new(22)?)
+    /// );
+    /// assert_eq!(LargeWindowSize::best().try_as_standard(), None);
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    ///
+    /// [standard]: LargeWindowSize::is_standard
+    pub const fn try_as_standard(&self) -> Option<WindowSize> {
+        if self.is_standard() {
+            Some(WindowSize(self.0))
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for LargeWindowSize {
@@ -602,6 +983,36 @@ impl Default for LargeWindowSize {
     }
 }
 
+impl TryFrom<u8> for LargeWindowSize {
+    type Error = SetParameterError;
+
+    /// Attempts to construct a [`LargeWindowSize`] from a raw number of bits.
+    ///
+    /// # Errors
+    ///
+    /// See [`LargeWindowSize::new`].
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        LargeWindowSize::new(bits)
+    }
+}
+
+impl From<LargeWindowSize> for u8 {
+    /// Returns the raw number of bits of a [`LargeWindowSize`].
+    ///
+    /// This is equivalent to [`LargeWindowSize::bits`].
+    fn from(large_window_size: LargeWindowSize) -> Self {
+        large_window_size.0
+    }
+}
+
+impl fmt::Display for LargeWindowSize {
+    /// Formats the window size as `window N bits (M bytes)`, where `M` is
+    /// [`LargeWindowSize::bytes`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "window {} bits ({} bytes)", self.0, self.bytes())
+    }
+}
+
 impl From<WindowSize> for LargeWindowSize {
     /// Constructs a [`LargeWindowSize`] from a [`WindowSize`].
     ///
@@ -618,7 +1029,7 @@ impl From<WindowSize> for LargeWindowSize {
 /// input size is small. Larger block sizes allow better compression at the
 /// expense of using more memory. Rough formula for memory required is `3 <<
 /// bits` bytes.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct BlockSize(u8);
 
 impl BlockSize {
@@ -672,6 +1083,31 @@ impl BlockSize {
         BlockSize(bits)
     }
 
+    /// Constructs a new block size, clamping `bits` to the valid range.
+    ///
+    /// Valid `bits` range from 16 to 24 inclusive. Out of range values are
+    /// silently replaced by the nearest valid one, so this never fails. Use
+    /// [`new`](BlockSize::new) to reject invalid values instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BlockSize;
+    ///
+    /// assert_eq!(BlockSize::from_bits_clamped(0), BlockSize::worst());
+    /// assert_eq!(BlockSize::from_bits_clamped(255), BlockSize::best());
+    /// assert_eq!(BlockSize::from_bits_clamped(20).bits(), 20);
+    /// ```
+    pub const fn from_bits_clamped(bits: u8) -> BlockSize {
+        if bits < BROTLI_MIN_INPUT_BLOCK_BITS {
+            BlockSize(BROTLI_MIN_INPUT_BLOCK_BITS)
+        } else if bits > BROTLI_MAX_INPUT_BLOCK_BITS {
+            BlockSize(BROTLI_MAX_INPUT_BLOCK_BITS)
+        } else {
+            BlockSize(bits)
+        }
+    }
+
     /// Constructs the best block size (in bits) to use for brotli compression.
     ///
     /// This will allow better compression at the expense of memory usage.
@@ -710,6 +1146,27 @@ impl BlockSize {
         BlockSize(BROTLI_MIN_INPUT_BLOCK_BITS)
     }
 
+    /// Returns an iterator over all valid block sizes, from [`worst`] to
+    /// [`best`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BlockSize;
+    ///
+    /// let mut iter = BlockSize::iter();
+    ///
+    /// assert_eq!(iter.len(), 9);
+    /// assert_eq!(iter.next(), Some(BlockSize::worst()));
+    /// assert_eq!(iter.next_back(), Some(BlockSize::best()));
+    /// ```
+    ///
+    /// [`worst`]: BlockSize::worst
+    /// [`best`]: BlockSize::best
+    pub fn iter() -> impl DoubleEndedIterator<Item = BlockSize> + ExactSizeIterator + Clone {
+        (Self::worst().bits()..=Self::best().bits()).map(BlockSize)
+    }
+
     /// Returns an integer representing the block size in bits.
     ///
     /// # Examples
@@ -725,67 +1182,536 @@ impl BlockSize {
     pub const fn bits(&self) -> u8 {
         self.0
     }
-}
-
-/// Allows to tune a brotli compressor for a specific type of input.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum CompressionMode {
-    /// No known attributes about the input data.
-    Generic = BrotliEncoderMode_BROTLI_MODE_GENERIC as isize,
 
-    /// Tune compression for UTF-8 formatted text input.
-    Text = BrotliEncoderMode_BROTLI_MODE_TEXT as isize,
+    /// Returns the size of an input block in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BlockSize;
+    ///
+    /// assert_eq!(BlockSize::worst().bytes(), 1 << 16);
+    /// ```
+    pub const fn bytes(&self) -> usize {
+        1 << self.0
+    }
 
-    /// Tune compression for WOFF 2.0 fonts
-    Font = BrotliEncoderMode_BROTLI_MODE_FONT as isize,
+    /// Returns the memory used by the encoder for input blocks of this size in
+    /// bytes.
+    ///
+    /// The encoder keeps three blocks worth of input in memory, so this is
+    /// `3 << bits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::BlockSize;
+    ///
+    /// assert_eq!(BlockSize::best().memory_usage(), 3 << 24);
+    /// ```
+    pub const fn memory_usage(&self) -> usize {
+        3 << self.0
+    }
 }
 
-impl Default for CompressionMode {
-    /// Creates a `CompressionMode` using [`Generic`].
-    /// See its documentation for more.
+impl TryFrom<u8> for BlockSize {
+    type Error = SetParameterError;
+
+    /// Attempts to construct a [`BlockSize`] from a raw number of bits.
     ///
-    /// [`Generic`]: CompressionMode::Generic
-    fn default() -> Self {
-        CompressionMode::Generic
+    /// # Errors
+    ///
+    /// See [`BlockSize::new`].
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        BlockSize::new(bits)
     }
 }
 
-/// An error returned by [`compress`].
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct CompressError;
+impl From<BlockSize> for u8 {
+    /// Returns the raw number of bits of a [`BlockSize`].
+    ///
+    /// This is equivalent to [`BlockSize::bits`].
+    fn from(block_size: BlockSize) -> Self {
+        block_size.0
+    }
+}
 
-impl fmt::Display for CompressError {
+impl fmt::Display for BlockSize {
+    /// Formats the block size as `block N bits (M bytes)`, where `M` is
+    /// [`BlockSize::bytes`].
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("buffer was too small or compression error occurred")
+        write!(f, "block {} bits ({} bytes)", self.0, self.bytes())
     }
 }
 
-impl Error for CompressError {}
+/// The number of postfix bits to use for distance codes.
+///
+/// Valid values range from 0 to 3 (`BROTLI_MAX_NPOSTFIX`) inclusive. The
+/// encoder may change this value on the fly.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PostfixBits(u32);
 
-impl From<CompressError> for io::Error {
+impl PostfixBits {
+    /// Constructs a new number of postfix bits.
+    ///
+    /// Valid `bits` range from 0 to 3 inclusive.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `bits` is out of the range of valid
+    /// postfix bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{PostfixBits, SetParameterError};
+    ///
+    /// assert_eq!(PostfixBits::new(3)?.bits(), 3);
+    /// assert_eq!(PostfixBits::new(4), Err(SetParameterError::InvalidPostfix));
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn new(bits: u32) -> Result<PostfixBits, SetParameterError> {
+        match bits {
+            0..=3 => Ok(PostfixBits(bits)),
+            _ => Err(SetParameterError::InvalidPostfix),
+        }
+    }
+
+    /// Returns the number of postfix bits.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for PostfixBits {
+    type Error = SetParameterError;
+
+    /// Attempts to construct [`PostfixBits`] from a raw number of bits.
+    ///
+    /// # Errors
+    ///
+    /// See [`PostfixBits::new`].
+    fn try_from(bits: u32) -> Result<Self, Self::Error> {
+        PostfixBits::new(bits)
+    }
+}
+
+impl From<PostfixBits> for u32 {
+    /// Returns the raw number of bits of [`PostfixBits`].
+    ///
+    /// This is equivalent to [`PostfixBits::bits`].
+    fn from(postfix_bits: PostfixBits) -> Self {
+        postfix_bits.0
+    }
+}
+
+/// The recommended number of direct distance codes, together with the number
+/// of postfix bits they were validated against.
+///
+/// The number of direct distance codes ranges from 0 to `15 << postfix`
+/// inclusive in steps of `1 << postfix`, where `postfix` is the number of
+/// [`PostfixBits`]. The encoder may change these values on the fly.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DirectDistanceCodes {
+    value: u32,
+    postfix: PostfixBits,
+}
+
+impl DirectDistanceCodes {
+    /// Constructs a new number of direct distance codes for `postfix` bits.
+    ///
+    /// # Errors
+    ///
+    /// An [`Err`] will be returned if `value` is greater than `15 << postfix`
+    /// or not a multiple of `1 << postfix`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{DirectDistanceCodes, PostfixBits, SetParameterError};
+    ///
+    /// let postfix = PostfixBits::new(3)?;
+    ///
+    /// assert_eq!(DirectDistanceCodes::new(120, postfix)?.value(), 120);
+    /// assert_eq!(
+    ///     DirectDistanceCodes::new(121, postfix),
+    ///     Err(SetParameterError::InvalidDirectDistanceCodes)
+    /// );
+    /// # Ok::<(), brotlic::SetParameterError>(())
+    /// ```
+    pub const fn new(
+        value: u32,
+        postfix: PostfixBits,
+    ) -> Result<DirectDistanceCodes, SetParameterError> {
+        if value > (15 << postfix.0) || value & ((1 << postfix.0) - 1) != 0 {
+            return Err(SetParameterError::InvalidDirectDistanceCodes);
+        }
+
+        Ok(DirectDistanceCodes { value, postfix })
+    }
+
+    /// Returns the number of direct distance codes.
+    pub const fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Returns the number of postfix bits.
+    pub const fn postfix(&self) -> PostfixBits {
+        self.postfix
+    }
+}
+
+/// Allows to tune a brotli compressor for a specific type of input.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CompressionMode {
+    /// No known attributes about the input data.
+    Generic = BrotliEncoderMode_BROTLI_MODE_GENERIC as isize,
+
+    /// Tune compression for UTF-8 formatted text input.
+    Text = BrotliEncoderMode_BROTLI_MODE_TEXT as isize,
+
+    /// Tune compression for WOFF 2.0 fonts
+    Font = BrotliEncoderMode_BROTLI_MODE_FONT as isize,
+}
+
+impl Default for CompressionMode {
+    /// Creates a `CompressionMode` using [`Generic`].
+    /// See its documentation for more.
+    ///
+    /// [`Generic`]: CompressionMode::Generic
+    fn default() -> Self {
+        CompressionMode::Generic
+    }
+}
+
+impl fmt::Display for CompressionMode {
+    /// Formats the mode by its lowercase name, e.g. `text`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompressionMode::Generic => "generic",
+            CompressionMode::Text => "text",
+            CompressionMode::Font => "font",
+        })
+    }
+}
+
+impl From<CompressionMode> for u32 {
+    /// Returns the raw `BrotliEncoderMode` value of a [`CompressionMode`].
+    fn from(mode: CompressionMode) -> Self {
+        mode as u32
+    }
+}
+
+/// The format of a dictionary that is shared between the encoder and decoder.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SharedDictionaryType {
+    /// Raw LZ77 prefix dictionary.
+    ///
+    /// The dictionary is used as if it were data that preceded the input. Any
+    /// number of raw dictionaries may be attached.
+    Raw = BrotliSharedDictionaryType_BROTLI_SHARED_DICTIONARY_RAW as isize,
+
+    /// Serialized shared dictionary.
+    ///
+    /// Contains custom word lists and transforms, optionally including a LZ77
+    /// prefix dictionary. At most one serialized dictionary may be attached.
+    ///
+    /// Serialized dictionaries are not supported by the brotli library built
+    /// by `brotlic-sys`, so they are rejected with
+    /// [`DictionaryError::Unsupported`].
+    Serialized = BrotliSharedDictionaryType_BROTLI_SHARED_DICTIONARY_SERIALIZED as isize,
+}
+
+/// An error returned when attaching or preparing a dictionary.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DictionaryError {
+    /// The encoder or decoder rejected the dictionary.
+    ///
+    /// This happens when the dictionary is corrupted or the dictionary count
+    /// limit is reached.
+    Generic,
+
+    /// A serialized dictionary was already attached.
+    TooManySerializedDictionaries,
+
+    /// The dictionary data was empty.
+    Empty,
+
+    /// The dictionary failed to be allocated or initialized.
+    AllocationFailed,
+
+    /// Decoding has already started, so no more dictionaries can be attached.
+    DecodingStarted,
+
+    /// The dictionary type is not supported by the brotli library.
+    ///
+    /// Serialized dictionaries are only supported if the C library is built
+    /// with `BROTLI_EXPERIMENTAL`, which `brotlic-sys` does not enable.
+    Unsupported,
+}
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryError::Generic => f.write_str("dictionary was rejected"),
+            DictionaryError::TooManySerializedDictionaries => {
+                f.write_str("a serialized dictionary was already attached")
+            }
+            DictionaryError::Empty => f.write_str("dictionary data was empty"),
+            DictionaryError::AllocationFailed => f.write_str("failed to allocate dictionary"),
+            DictionaryError::DecodingStarted => {
+                f.write_str("dictionaries must be attached before decoding starts")
+            }
+            DictionaryError::Unsupported => f.write_str("dictionary type is not supported"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for DictionaryError {}
+
+/// An error returned by [`compress`].
+///
+/// Use [`kind`](CompressError::kind) to find out what caused the error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CompressError {
+    kind: CompressErrorKind,
+}
+
+impl CompressError {
+    const fn new(kind: CompressErrorKind) -> Self {
+        CompressError { kind }
+    }
+
+    /// Returns the kind of this error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{compress, CompressErrorKind, CompressionMode, Quality, WindowSize};
+    ///
+    /// let input = vec![0; 1024];
+    /// let mut output = [0; 1];
+    ///
+    /// let err = compress(
+    ///     &input,
+    ///     &mut output,
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(err.kind(), CompressErrorKind::BufferTooSmall);
+    /// ```
+    pub fn kind(&self) -> CompressErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CompressErrorKind::BufferTooSmall => {
+                f.write_str("output buffer is too small to hold the compressed data")
+            }
+            CompressErrorKind::InvalidParameters => f.write_str("invalid compression parameters"),
+            CompressErrorKind::InvalidDictionary => f.write_str("dictionary could not be prepared"),
+            CompressErrorKind::Other => f.write_str("compression error occurred"),
+        }
+    }
+}
+
+/// The kind of a [`CompressError`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum CompressErrorKind {
+    /// The output buffer is too small to hold the compressed data.
+    ///
+    /// Compressing again with a larger buffer, e.g. one of [`compress_bound`]
+    /// bytes, may succeed.
+    BufferTooSmall,
+
+    /// The encoder options are invalid.
+    InvalidParameters,
+
+    /// The dictionary is empty or could not be prepared.
+    InvalidDictionary,
+
+    /// The encoder failed, e.g. because the input is too large or memory
+    /// allocation failed.
+    Other,
+}
+
+#[cfg(feature = "std")]
+impl Error for CompressError {}
+
+#[cfg(feature = "std")]
+impl From<CompressError> for io::Error {
     fn from(err: CompressError) -> Self {
         io::Error::new(io::ErrorKind::Other, err)
     }
 }
 
 /// An error returned by [`decompress`].
+///
+/// Use [`kind`](DecompressError::kind) to find out what caused the error.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct DecompressError;
+pub struct DecompressError {
+    kind: DecompressErrorKind,
+}
+
+impl DecompressError {
+    const fn new(kind: DecompressErrorKind) -> Self {
+        DecompressError { kind }
+    }
+
+    /// Returns the kind of this error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use brotlic::{
+    ///     compress_to_vec, decompress, CompressionMode, DecompressErrorKind, Quality, WindowSize,
+    /// };
+    ///
+    /// let input = vec![0; 1024];
+    /// let compressed = compress_to_vec(
+    ///     &input,
+    ///     Quality::default(),
+    ///     WindowSize::default(),
+    ///     CompressionMode::Generic,
+    /// )?;
+    ///
+    /// let mut output = vec![0; 512];
+    /// let err = decompress(&compressed, &mut output).unwrap_err();
+    ///
+    /// assert_eq!(err.kind(), DecompressErrorKind::BufferTooSmall);
+    /// # Ok::<(), brotlic::CompressError>(())
+    /// ```
+    pub fn kind(&self) -> DecompressErrorKind {
+        self.kind
+    }
+}
 
 impl fmt::Display for DecompressError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("buffer was too small or decompression error occurred")
+        match self.kind {
+            DecompressErrorKind::BufferTooSmall => {
+                f.write_str("output buffer is too small to hold the decompressed data")
+            }
+            DecompressErrorKind::IncompleteInput => f.write_str("compressed input is incomplete"),
+            DecompressErrorKind::CorruptedInput => f.write_str("compressed input is corrupted"),
+            DecompressErrorKind::AllocationFailed => {
+                f.write_str("failed to allocate memory for decompression")
+            }
+            DecompressErrorKind::OutputLimitExceeded => {
+                f.write_str("decompressed data exceeds the maximum output size")
+            }
+            DecompressErrorKind::InvalidDictionary => {
+                f.write_str("dictionary could not be attached")
+            }
+            DecompressErrorKind::Other => f.write_str("decompression error occurred"),
+        }
+    }
+}
+
+impl From<DecodeError> for DecompressError {
+    fn from(err: DecodeError) -> Self {
+        let kind = match err {
+            DecodeError::AllocContextModes
+            | DecodeError::AllocTreeGroups
+            | DecodeError::AllocContextMap
+            | DecodeError::AllocRingBuffer1
+            | DecodeError::AllocRingBuffer2
+            | DecodeError::AllocBlockTypeTrees => DecompressErrorKind::AllocationFailed,
+            _ => DecompressErrorKind::CorruptedInput,
+        };
+
+        DecompressError::new(kind)
     }
 }
 
+/// The kind of a [`DecompressError`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum DecompressErrorKind {
+    /// The output buffer is too small to hold the decompressed data.
+    BufferTooSmall,
+
+    /// The input ended before the compression stream was finished.
+    IncompleteInput,
+
+    /// The input is not a valid compression stream.
+    CorruptedInput,
+
+    /// Memory allocation failed.
+    AllocationFailed,
+
+    /// The decompressed data exceeds the maximum output size given to e.g.
+    /// [`decompress_to_vec_bounded`].
+    OutputLimitExceeded,
+
+    /// The dictionary is empty or could not be attached.
+    InvalidDictionary,
+
+    /// Decompression failed for another reason, e.g. the runtime shutting
+    /// down.
+    Other,
+}
+
+#[cfg(feature = "std")]
 impl Error for DecompressError {}
 
+#[cfg(feature = "std")]
 impl From<DecompressError> for io::Error {
     fn from(err: DecompressError) -> Self {
         io::Error::new(io::ErrorKind::Other, err)
     }
 }
 
+/// An error returned by [`decompress_to_string`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DecompressToStringError {
+    /// The input is corrupted or incomplete.
+    Decompress(DecompressError),
+    /// The decompressed data is not valid UTF-8.
+    Utf8(Utf8Error),
+}
+
+impl fmt::Display for DecompressToStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressToStringError::Decompress(err) => err.fmt(f),
+            DecompressToStringError::Utf8(err) => {
+                write!(f, "decompressed data is not utf-8: {}", err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for DecompressToStringError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecompressToStringError::Decompress(err) => Some(err),
+            DecompressToStringError::Utf8(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<DecompressToStringError> for io::Error {
+    fn from(err: DecompressToStringError) -> Self {
+        match err {
+            DecompressToStringError::Decompress(err) => err.into(),
+            DecompressToStringError::Utf8(err) => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
 /// An error returned by [`BrotliEncoderOptions::build`] and
 /// [`BrotliDecoderOptions::build`]
 ///
@@ -818,6 +1744,13 @@ pub enum SetParameterError {
 
     /// Block size bits were out of range.
     InvalidBlockSize,
+
+    /// A dictionary could not be attached.
+    InvalidDictionary,
+
+    /// The decoder already started decoding, so it can no longer be
+    /// configured.
+    InUse,
 }
 
 impl fmt::Display for SetParameterError {
@@ -832,12 +1765,21 @@ impl fmt::Display for SetParameterError {
             SetParameterError::InvalidQuality => f.write_str("quality out of range"),
             SetParameterError::InvalidWindowSize => f.write_str("window size out of range"),
             SetParameterError::InvalidBlockSize => f.write_str("block size out of range"),
+            SetParameterError::InvalidDictionary => f.write_str("dictionary could not be attached"),
+            SetParameterError::InUse => f.write_str("decoder is already in use"),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for SetParameterError {}
 
+impl From<Infallible> for SetParameterError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
 /// Read all bytes from `input` and compress them into `output`, returning how
 /// many bytes were written.
 ///
@@ -856,6 +1798,8 @@ impl Error for SetParameterError {}
 /// * A generic compression error occurs
 /// * memory allocation failed
 ///
+/// A too small `output` can be detected using [`CompressError::kind`].
+///
 /// # Examples
 ///
 /// ```
@@ -899,8 +1843,10 @@ pub fn compress(
 
     if res != 0 {
         Ok(output_size)
+    } else if output.len() < unsafe { BrotliEncoderMaxCompressedSize(input.len()) } {
+        Err(CompressError::new(CompressErrorKind::BufferTooSmall))
     } else {
-        Err(CompressError)
+        Err(CompressError::new(CompressErrorKind::Other))
     }
 }
 
@@ -920,77 +1866,1608 @@ pub fn compress_bound(input_size: usize, quality: Quality) -> Option<usize> {
     }
 }
 
-/// Returns peak memory usage for a given quality and window size
-///
-/// Given an input of `input_size` bytes in size, a `quality` and a
-/// `window_size`, estimate the peak memory usage in bytes, not counting the
-/// memory needed for the input and output.
-#[doc(alias = "BrotliEncoderEstimatePeakMemoryUsage")]
-pub fn compress_estimate_max_mem_usage(
-    input_size: usize,
-    quality: Quality,
-    window_size: impl Into<LargeWindowSize>,
-) -> usize {
-    unsafe {
-        BrotliEncoderEstimatePeakMemoryUsage(quality.0 as _, window_size.into().0 as _, input_size)
-    }
-}
-
-/// Read all bytes from `input` and decompress them into `output`, returning how
-/// many bytes were written.
+/// Compresses all of `input` into a newly allocated [`Vec<u8>`].
 ///
-/// The uncompressed `input` must fit into `output`, otherwise an error is
-/// returned and the decompression will be aborted.
+/// This is a convenience wrapper around [`compress`] that takes care of
+/// allocating an output buffer large enough to hold the compressed data. For a
+/// `quality` of 2 or higher, the buffer is sized using [`compress_bound`].
+/// Otherwise no upper bound is known upfront, so the buffer is grown until the
+/// compressed data fits.
 ///
 /// # Errors
 ///
 /// An [`Err`] will be returned if:
 ///
-/// * `input` is corrupted
+/// * A generic compression error occurs
 /// * memory allocation failed
-/// * `output` is not large enough to hold uncompressed `input`
 ///
 /// # Examples
 ///
 /// ```
-/// use brotlic::{compress, decompress, CompressionMode, Quality, WindowSize};
+/// use brotlic::{compress_to_vec, CompressionMode, Quality, WindowSize};
 ///
 /// let input = vec![0; 1024];
-/// let mut encoded = vec![1; 1024];
-/// let mut decoded = vec![2; 1024];
-///
-/// let bytes_written = compress(
-///     input.as_slice(),
-///     encoded.as_mut_slice(),
+/// let compressed = compress_to_vec(
+///     &input,
 ///     Quality::default(),
 ///     WindowSize::default(),
 ///     CompressionMode::Generic,
 /// )?;
 ///
-/// let encoded = &encoded[..bytes_written];
-/// let bytes_written = decompress(encoded, decoded.as_mut_slice())?;
-/// let decoded = &decoded[..bytes_written];
-///
-/// assert_eq!(input, decoded);
-/// # Ok::<(), std::io::Error>(())
+/// assert!(compressed.len() < input.len());
+/// # Ok::<(), brotlic::CompressError>(())
 /// ```
-#[doc(alias = "BrotliDecoderDecompress")]
-pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<usize, DecompressError> {
-    let mut output_size = output.len();
+#[doc(alias = "BrotliEncoderCompress")]
+pub fn compress_to_vec(
+    input: &[u8],
+    quality: Quality,
+    window_size: WindowSize,
+    mode: CompressionMode,
+) -> Result<Vec<u8>, CompressError> {
+    let bound = unsafe { BrotliEncoderMaxCompressedSize(input.len()) };
 
-    let res = unsafe {
-        BrotliDecoderDecompress(
-            input.len(),
-            input.as_ptr(),
-            &mut output_size as *mut usize,
-            output.as_mut_ptr(),
-        )
-    };
+    if bound == 0 {
+        return Err(CompressError::new(CompressErrorKind::Other));
+    }
 
-    if res == BrotliDecoderResult_BROTLI_DECODER_RESULT_SUCCESS {
-        Ok(output_size)
-    } else {
-        Err(DecompressError)
+    let mut output = vec![0; bound];
+
+    loop {
+        match compress(input, &mut output, quality, window_size, mode) {
+            Ok(size) => {
+                output.truncate(size);
+                return Ok(output);
+            }
+            // the bound only holds for a quality of 2 or higher
+            Err(_) if quality.0 < 2 && output.len() < bound.saturating_mul(4) => {
+                output.resize(output.len().saturating_mul(2), 0);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Compresses all of `input` using a raw LZ77 prefix `dictionary` into a newly
+/// allocated [`Vec<u8>`].
+///
+/// The `dictionary` is used as if it preceded `input`, which greatly improves
+/// compression of small inputs that share content with it. The compressed data
+/// can only be decompressed with the same dictionary, e.g. using
+/// [`decompress_with_dictionary`]. For more control, attach a
+/// [`PreparedDictionary`] using [`BrotliEncoderOptions::dictionary`] instead.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `dictionary` is empty or fails to be prepared, in which case the error
+///   kind is [`CompressErrorKind::InvalidDictionary`]
+/// * A generic compression error occurs
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_with_dictionary, decompress_with_dictionary, Quality, WindowSize};
+///
+/// let dictionary = b"content-type: text/html; charset=utf-8";
+/// let input = b"content-type: text/html; charset=utf-8\r\n";
+///
+/// let compressed =
+///     compress_with_dictionary(input, dictionary, Quality::best(), WindowSize::default())?;
+///
+/// assert_eq!(decompress_with_dictionary(&compressed, dictionary)?, input);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`PreparedDictionary`]: encode::PreparedDictionary
+pub fn compress_with_dictionary(
+    input: &[u8],
+    dictionary: &[u8],
+    quality: Quality,
+    window_size: WindowSize,
+) -> Result<Vec<u8>, CompressError> {
+    let dictionary = encode::PreparedDictionary::from_raw_data(dictionary, quality)
+        .map_err(|_| CompressError::new(CompressErrorKind::InvalidDictionary))?;
+    let mut options = BrotliEncoderOptions::new();
+    let mut encoder = options
+        .dictionary(&dictionary)
+        .quality(quality)
+        .and_then(|options| options.window_size(window_size))
+        .and_then(|options| options.build())
+        .map_err(|err| match err {
+            SetParameterError::InvalidDictionary => {
+                CompressError::new(CompressErrorKind::InvalidDictionary)
+            }
+            _ => CompressError::new(CompressErrorKind::InvalidParameters),
+        })?;
+
+    let mut output = Vec::new();
+    encoder
+        .compress_all_into(input, &mut output, BrotliOperation::Finish)
+        .map_err(|_| CompressError::new(CompressErrorKind::Other))?;
+
+    Ok(output)
+}
+
+/// Compresses `input` in parallel using up to `num_threads` threads.
+///
+/// The input is split into `num_threads` chunks of roughly equal size, each of
+/// which is compressed by a separate encoder built from `options`. Every
+/// encoder but the first has its [`stream_offset`] set to the position of its
+/// chunk, so the stream header is only emitted once and the compressed chunks
+/// can be concatenated into a single compression stream. Any stream offset set
+/// in `options` is overridden. Since each chunk is compressed independently,
+/// the result is usually slightly larger than compressing `input` at once.
+///
+/// A `num_threads` of 0 is treated as 1.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `options` are invalid
+/// * A generic compression error occurs
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_parallel, decompress_to_vec, BrotliEncoderOptions};
+///
+/// let input = vec![0; 1024 * 1024];
+/// let compressed = compress_parallel(&input, &BrotliEncoderOptions::new(), 4)?;
+///
+/// assert_eq!(decompress_to_vec(&compressed)?, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`stream_offset`]: BrotliEncoderOptions::stream_offset_from_bytes
+#[cfg(feature = "std")]
+pub fn compress_parallel(
+    input: &[u8],
+    options: &BrotliEncoderOptions,
+    num_threads: usize,
+) -> Result<Vec<u8>, CompressError> {
+    let chunk_size = input.len().div_ceil(num_threads.max(1)).max(1);
+    let chunk_count = input.len().div_ceil(chunk_size).max(1);
+
+    let mut encoders = Vec::with_capacity(chunk_count);
+
+    for i in 0..chunk_count {
+        let offset = (i * chunk_size).min(1 << 30);
+        let encoder = options
+            .clone()
+            .with_stream_offset_from_bytes(offset)
+            .and_then(|options| options.build())
+            .map_err(|_| CompressError::new(CompressErrorKind::InvalidParameters))?;
+
+        encoders.push(encoder);
+    }
+
+    let parts = std::thread::scope(|scope| {
+        let handles: Vec<_> = encoders
+            .into_iter()
+            .enumerate()
+            .map(|(i, encoder)| {
+                let start = (i * chunk_size).min(input.len());
+                let end = (start + chunk_size).min(input.len());
+                let last = i + 1 == chunk_count;
+
+                scope.spawn(move || compress_chunk(encoder, &input[start..end], last))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    Ok(parts.concat())
+}
+
+/// Compresses `input` as one part of a compression stream.
+///
+/// The stream is finished if this is the `last` part, otherwise it is flushed
+/// so that the next part can be appended to the output.
+#[cfg(feature = "std")]
+fn compress_chunk(
+    mut encoder: BrotliEncoder,
+    mut input: &[u8],
+    last: bool,
+) -> Result<Vec<u8>, CompressError> {
+    let op = if last {
+        BrotliOperation::Finish
+    } else {
+        BrotliOperation::Flush
+    };
+
+    let mut output = Vec::new();
+    let mut buf = [0; 4096];
+
+    loop {
+        let result = encoder
+            .compress(input, &mut buf, op)
+            .map_err(|_| CompressError::new(CompressErrorKind::Other))?;
+
+        input = &input[result.bytes_read..];
+        output.extend_from_slice(&buf[..result.bytes_written]);
+
+        let done = if last {
+            encoder.is_finished()
+        } else {
+            input.is_empty() && !encoder.has_output()
+        };
+
+        if done {
+            return Ok(output);
+        }
+    }
+}
+
+/// Compresses all of `input` and writes it to `writer`.
+///
+/// An encoder is built from `options` and used by a [`CompressorWriter`] that
+/// writes to `writer`, after which the compression stream is finished. This
+/// avoids compressing into an intermediate [`Vec<u8>`] first. Returns the
+/// number of compressed bytes written to `writer`.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `options` are invalid
+/// * writing to `writer` fails
+/// * A generic compression error occurs
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_to_writer, decompress_to_vec, BrotliEncoderOptions};
+///
+/// let input = vec![0; 100_000];
+/// let mut compressed = Vec::new();
+/// let written = compress_to_writer(&input, &mut compressed, &BrotliEncoderOptions::new())?;
+///
+/// assert_eq!(written, compressed.len());
+/// assert_eq!(decompress_to_vec(&compressed)?, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn compress_to_writer<W, M>(
+    input: &[u8],
+    writer: &mut W,
+    options: &BrotliEncoderOptions<M>,
+) -> io::Result<usize>
+where
+    W: Write + ?Sized,
+    M: encode::WindowMode,
+{
+    let encoder = options
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut compressor = CompressorWriter::with_encoder(encoder, writer);
+
+    compressor.write_all(input)?;
+    compressor.try_finish()?;
+
+    let total = compressor.total_out();
+    compressor.into_inner()?;
+
+    Ok(total as usize)
+}
+
+/// Returns a function that compresses its input using an encoder pooled per
+/// thread.
+///
+/// Every thread keeps a pool of encoders keyed by a fingerprint of the options
+/// they were built with. All functions returned from `thread_local_encoder` on
+/// the same thread with equal options share one encoder, which is reset after
+/// each call. The memory of a pooled encoder is recycled when it is reset, so
+/// after the first call, compressing another input of similar size does not
+/// allocate memory for the encoder again. This reduces the overhead of
+/// compressing many small inputs, such as the responses of a server.
+///
+/// Each call compresses its input into a complete compression stream. Pooled
+/// encoders and their memory are kept until the thread exits.
+///
+/// # Errors
+///
+/// The returned function returns an [`Err`] if:
+///
+/// * `options` are invalid
+/// * A generic compression error occurs
+/// * memory allocation failed
+///
+/// If an error occurs, the pooled encoder is discarded and a new one is
+/// created by the next call.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{thread_local_encoder, BrotliEncoderOptions};
+///
+/// let mut compress = thread_local_encoder(&BrotliEncoderOptions::for_http_response());
+///
+/// for body in [&b"first response"[..], b"second response"] {
+///     let compressed = compress(body)?;
+///     assert_eq!(brotlic::decompress_to_vec(&compressed)?, body);
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn thread_local_encoder(
+    options: &BrotliEncoderOptions,
+) -> impl FnMut(&[u8]) -> io::Result<Vec<u8>> {
+    std::thread_local! {
+        static POOL: RefCell<HashMap<u64, (BrotliEncoderOptions, BrotliEncoder)>> =
+            RefCell::new(HashMap::new());
+    }
+
+    let options = options.clone();
+    let fingerprint = {
+        let mut hasher = DefaultHasher::new();
+        options.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    move |input| {
+        // the encoder is taken out of the pool while in use, so that it is
+        // discarded if an error leaves it in an unknown state.
+        let pooled = POOL.with(|pool| pool.borrow_mut().remove(&fingerprint));
+        let mut encoder = match pooled {
+            Some((pooled_options, encoder)) if pooled_options == options => encoder,
+            _ => options
+                .build_in(RecyclingAlloc::default())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+        };
+
+        let mut output = Vec::new();
+        let mut remaining = input;
+
+        while !encoder.is_finished() {
+            let bytes_read = encoder.give_input(remaining, BrotliOperation::Finish)?;
+            remaining = &remaining[bytes_read..];
+
+            encoder.drain_to_writer(&mut output)?;
+        }
+
+        encoder
+            .reset_with_options(&options)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        POOL.with(|pool| {
+            pool.borrow_mut()
+                .insert(fingerprint, (options.clone(), encoder))
+        });
+
+        Ok(output)
+    }
+}
+
+/// An allocator that keeps freed memory around to hand it out again.
+///
+/// Resetting an encoder destroys its internal state and creates a new one,
+/// which allocates blocks of the same sizes as before. Recycling them avoids
+/// going through the global allocator again.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct RecyclingAlloc {
+    free: Mutex<Vec<(Layout, usize)>>,
+}
+
+#[cfg(feature = "std")]
+unsafe impl GlobalAlloc for RecyclingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut free = self.free.lock().unwrap_or_else(|err| err.into_inner());
+
+        match free.iter().position(|&(block, _)| block == layout) {
+            Some(i) => free.swap_remove(i).1 as *mut u8,
+            None => std::alloc::alloc(layout),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut free = self.free.lock().unwrap_or_else(|err| err.into_inner());
+
+        free.push((layout, ptr as usize));
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for RecyclingAlloc {
+    fn drop(&mut self) {
+        let free = self.free.get_mut().unwrap_or_else(|err| err.into_inner());
+
+        for (layout, ptr) in free.drain(..) {
+            unsafe { std::alloc::dealloc(ptr as *mut u8, layout) }
+        }
+    }
+}
+
+/// Compresses all of `input` into a newly allocated [`Vec<u8>`] without
+/// blocking the asynchronous runtime.
+///
+/// The input is copied and compressed using [`compress_to_vec`] on the
+/// blocking thread pool of the current `tokio` runtime. Requires the `tokio`
+/// feature.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * A generic compression error occurs
+/// * memory allocation failed
+/// * the runtime is shutting down
+///
+/// # Panics
+///
+/// Panics if called outside of a `tokio` runtime.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_async, CompressionMode, Quality, WindowSize};
+///
+/// # tokio_test::block_on(async {
+/// let input = vec![0; 1024];
+/// let compressed = compress_async(
+///     &input,
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )
+/// .await?;
+///
+/// assert!(compressed.len() < input.len());
+/// # Ok::<(), brotlic::CompressError>(())
+/// # }).unwrap();
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn compress_async(
+    input: &[u8],
+    quality: Quality,
+    window_size: WindowSize,
+    mode: CompressionMode,
+) -> Result<Vec<u8>, CompressError> {
+    let input = input.to_vec();
+    let task =
+        tokio::task::spawn_blocking(move || compress_to_vec(&input, quality, window_size, mode));
+
+    match task.await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => Err(CompressError::new(CompressErrorKind::Other)),
+    }
+}
+
+/// Compresses all of `input` into a newly allocated [`Bytes`].
+///
+/// This is a wrapper around [`compress_to_vec`] for code that passes data
+/// around as [`Bytes`]. The compressed output is handed over to the returned
+/// [`Bytes`] without copying it.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if the encoder fails to compress `input`.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_bytes, CompressionMode, Quality, WindowSize};
+/// use bytes::Bytes;
+///
+/// let input = Bytes::from_static(&[0; 1024]);
+/// let compressed = compress_bytes(
+///     input.clone(),
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// assert!(compressed.len() < input.len());
+/// # Ok::<(), brotlic::CompressError>(())
+/// ```
+///
+/// [`Bytes`]: bytes::Bytes
+#[cfg(feature = "bytes")]
+pub fn compress_bytes(
+    input: bytes::Bytes,
+    quality: Quality,
+    window_size: WindowSize,
+    mode: CompressionMode,
+) -> Result<bytes::Bytes, CompressError> {
+    compress_to_vec(&input, quality, window_size, mode).map(bytes::Bytes::from)
+}
+
+/// Returns peak memory usage for a given quality and window size
+///
+/// Given an input of `input_size` bytes in size, a `quality` and a
+/// `window_size`, estimate the peak memory usage in bytes, not counting the
+/// memory needed for the input and output.
+#[deprecated(note = "use `encoder_estimate_peak_memory` or `encoder_estimate_peak_memory_large`")]
+#[doc(alias = "BrotliEncoderEstimatePeakMemoryUsage")]
+pub fn compress_estimate_max_mem_usage(
+    input_size: usize,
+    quality: Quality,
+    window_size: impl Into<LargeWindowSize>,
+) -> usize {
+    encoder_estimate_peak_memory_large(quality, window_size.into(), input_size)
+}
+
+/// Estimates the peak memory usage in bytes of an encoder compressing
+/// `input_size` bytes at the given `quality` and `window_size`.
+///
+/// Peak memory usage is the largest amount of memory the encoder allocates at
+/// any point while compressing, i.e. its internal state including the ring
+/// buffer holding the sliding window, the hash tables used for finding
+/// matches and the buffers for pending commands and output. The buffers the
+/// caller provides for input and output are not included. This is useful to
+/// choose parameters that stay within a memory limit.
+///
+/// Use [`encoder_estimate_peak_memory_large`] for large window sizes.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{encoder_estimate_peak_memory, Quality, WindowSize};
+///
+/// let fast = encoder_estimate_peak_memory(Quality::worst(), WindowSize::worst(), 1 << 20);
+/// let best = encoder_estimate_peak_memory(Quality::best(), WindowSize::best(), 1 << 20);
+///
+/// assert!(fast < best);
+/// ```
+#[doc(alias = "BrotliEncoderEstimatePeakMemoryUsage")]
+pub fn encoder_estimate_peak_memory(
+    quality: Quality,
+    window_size: WindowSize,
+    input_size: usize,
+) -> usize {
+    encoder_estimate_peak_memory_large(quality, window_size.into(), input_size)
+}
+
+/// Estimates the peak memory usage in bytes of an encoder compressing
+/// `input_size` bytes at the given `quality` and large `window_size`.
+///
+/// See [`encoder_estimate_peak_memory`] for what is included in the estimate.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{encoder_estimate_peak_memory_large, LargeWindowSize, Quality};
+///
+/// let usage =
+///     encoder_estimate_peak_memory_large(Quality::best(), LargeWindowSize::best(), 1 << 20);
+///
+/// assert!(usage > 0);
+/// ```
+#[doc(alias = "BrotliEncoderEstimatePeakMemoryUsage")]
+pub fn encoder_estimate_peak_memory_large(
+    quality: Quality,
+    window_size: LargeWindowSize,
+    input_size: usize,
+) -> usize {
+    unsafe { BrotliEncoderEstimatePeakMemoryUsage(quality.0 as _, window_size.0 as _, input_size) }
+}
+
+/// Read all bytes from `input` and decompress them into `output`, returning how
+/// many bytes were written.
+///
+/// The uncompressed `input` must fit into `output`, otherwise an error is
+/// returned and the decompression will be aborted.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+/// * `output` is not large enough to hold uncompressed `input`
+///
+/// These cases can be told apart using [`DecompressError::kind`].
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress, decompress, CompressionMode, Quality, WindowSize};
+///
+/// let input = vec![0; 1024];
+/// let mut encoded = vec![1; 1024];
+/// let mut decoded = vec![2; 1024];
+///
+/// let bytes_written = compress(
+///     input.as_slice(),
+///     encoded.as_mut_slice(),
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// let encoded = &encoded[..bytes_written];
+/// let bytes_written = decompress(encoded, decoded.as_mut_slice())?;
+/// let decoded = &decoded[..bytes_written];
+///
+/// assert_eq!(input, decoded);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<usize, DecompressError> {
+    // a streaming decoder is used instead of the one-shot decoder, as only it
+    // reports the cause of an error
+    let res = BrotliDecoder::new().decompress(input, output)?;
+
+    let kind = match res.info {
+        DecoderInfo::Finished => return Ok(res.bytes_written),
+        DecoderInfo::NeedsMoreOutput => DecompressErrorKind::BufferTooSmall,
+        DecoderInfo::NeedsMoreInput => DecompressErrorKind::IncompleteInput,
+    };
+
+    Err(DecompressError::new(kind))
+}
+
+/// Decompresses `input` into `output`, producing at most `max_output_size`
+/// bytes.
+///
+/// Works like [`decompress`], except that at most `max_output_size` bytes of
+/// `output` are used. This protects against decompression bombs, small inputs
+/// that decompress to a huge amount of data, when `output` is large.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+/// * `output` is not large enough to hold uncompressed `input`
+/// * the decompressed data exceeds `max_output_size` bytes, in which case the
+///   error kind is [`DecompressErrorKind::OutputLimitExceeded`]
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{
+///     compress_to_vec, decompress_bounded, CompressionMode, DecompressErrorKind, Quality,
+///     WindowSize,
+/// };
+///
+/// let input = vec![0; 1024 * 1024];
+/// let compressed = compress_to_vec(
+///     &input,
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// let mut output = vec![0; 2 * 1024 * 1024];
+/// let err = decompress_bounded(&compressed, &mut output, 1024).unwrap_err();
+///
+/// assert_eq!(err.kind(), DecompressErrorKind::OutputLimitExceeded);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn decompress_bounded(
+    input: &[u8],
+    output: &mut [u8],
+    max_output_size: usize,
+) -> Result<usize, DecompressError> {
+    let limit = output.len().min(max_output_size);
+
+    match decompress(input, &mut output[..limit]) {
+        Err(err)
+            if err.kind() == DecompressErrorKind::BufferTooSmall && limit == max_output_size =>
+        {
+            Err(DecompressError::new(
+                DecompressErrorKind::OutputLimitExceeded,
+            ))
+        }
+        res => res,
+    }
+}
+
+/// Decompresses all of `input` into a newly allocated [`Vec<u8>`].
+///
+/// Unlike [`decompress`], the size of the decompressed data does not need to
+/// be known upfront. The output buffer is doubled in size whenever the decoder
+/// runs out of space, until `input` is fully decompressed.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_to_vec, decompress_to_vec, CompressionMode, Quality, WindowSize};
+///
+/// let input = vec![0; 1024];
+/// let compressed = compress_to_vec(
+///     &input,
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// assert_eq!(decompress_to_vec(&compressed)?, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn decompress_to_vec(input: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    decompress_to_vec_bounded(input, usize::MAX)
+}
+
+/// Decompresses all of `input` into a newly allocated [`Vec<u8>`] of at most
+/// `max_output_size` bytes.
+///
+/// Works like [`decompress_to_vec`], except that the output buffer never grows
+/// beyond `max_output_size` bytes. This protects against decompression bombs,
+/// small inputs that decompress to a huge amount of data.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+/// * the decompressed data exceeds `max_output_size` bytes, in which case the
+///   error kind is [`DecompressErrorKind::OutputLimitExceeded`]
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{
+///     compress_to_vec, decompress_to_vec_bounded, CompressionMode, DecompressErrorKind, Quality,
+///     WindowSize,
+/// };
+///
+/// let input = vec![0; 1024 * 1024];
+/// let compressed = compress_to_vec(
+///     &input,
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// let err = decompress_to_vec_bounded(&compressed, 64 * 1024).unwrap_err();
+///
+/// assert_eq!(err.kind(), DecompressErrorKind::OutputLimitExceeded);
+/// assert_eq!(decompress_to_vec_bounded(&compressed, input.len())?, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn decompress_to_vec_bounded(
+    input: &[u8],
+    max_output_size: usize,
+) -> Result<Vec<u8>, DecompressError> {
+    decompress_to_vec_with(&mut BrotliDecoder::new(), input, max_output_size)
+}
+
+/// Decompresses all of `input` using a raw LZ77 prefix `dictionary` into a
+/// newly allocated [`Vec<u8>`].
+///
+/// This is the counterpart of [`compress_with_dictionary`]. The `dictionary`
+/// must be the same one that was used during compression, otherwise the input
+/// is either rejected as corrupted or decompresses to garbage.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `dictionary` is empty or fails to be attached, in which case the error
+///   kind is [`DecompressErrorKind::InvalidDictionary`]
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{decompress_with_dictionary, DecompressErrorKind};
+///
+/// let err = decompress_with_dictionary(&[6], &[]).unwrap_err();
+///
+/// assert_eq!(err.kind(), DecompressErrorKind::InvalidDictionary);
+/// ```
+pub fn decompress_with_dictionary(
+    input: &[u8],
+    dictionary: &[u8],
+) -> Result<Vec<u8>, DecompressError> {
+    let mut decoder = BrotliDecoder::new();
+
+    if dictionary.is_empty() || decoder.attach_raw_dictionary(dictionary).is_err() {
+        return Err(DecompressError::new(DecompressErrorKind::InvalidDictionary));
+    }
+
+    decompress_to_vec_with(&mut decoder, input, usize::MAX)
+}
+
+fn decompress_to_vec_with(
+    decoder: &mut BrotliDecoder,
+    input: &[u8],
+    max_output_size: usize,
+) -> Result<Vec<u8>, DecompressError> {
+    let initial_size = input.len().saturating_mul(4).max(1024);
+    let mut output = vec![0; initial_size.min(max_output_size)];
+    let mut bytes_read = 0;
+    let mut bytes_written = 0;
+
+    loop {
+        let res = decoder.decompress(&input[bytes_read..], &mut output[bytes_written..])?;
+
+        bytes_read += res.bytes_read;
+        bytes_written += res.bytes_written;
+
+        match res.info {
+            DecoderInfo::Finished => {
+                output.truncate(bytes_written);
+                return Ok(output);
+            }
+            DecoderInfo::NeedsMoreOutput if output.len() >= max_output_size => {
+                return Err(DecompressError::new(
+                    DecompressErrorKind::OutputLimitExceeded,
+                ))
+            }
+            DecoderInfo::NeedsMoreOutput => {
+                let size = output.len().saturating_mul(2).min(max_output_size);
+                output.resize(size, 0);
+            }
+            DecoderInfo::NeedsMoreInput => {
+                return Err(DecompressError::new(DecompressErrorKind::IncompleteInput))
+            }
+        }
+    }
+}
+
+/// Compresses everything read from `input` and writes it to `output`.
+///
+/// An encoder is built from `options` and fed with chunks of at most
+/// `chunk_size` bytes read from `input` until it reaches end of file, after
+/// which the compression stream is finished. A `chunk_size` of 0 is treated
+/// as 1. Returns the number of compressed bytes written to `output`.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `options` are invalid
+/// * reading from `input` or writing to `output` fails
+/// * A generic compression error occurs
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_stream, decompress_to_vec, BrotliEncoderOptions};
+///
+/// let input = vec![0; 100_000];
+/// let mut compressed = Vec::new();
+/// let written = compress_stream(
+///     &mut input.as_slice(),
+///     &mut compressed,
+///     &BrotliEncoderOptions::new(),
+///     4096,
+/// )?;
+///
+/// assert_eq!(written, compressed.len() as u64);
+/// assert_eq!(decompress_to_vec(&compressed)?, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn compress_stream<R, W, M>(
+    input: &mut R,
+    output: &mut W,
+    options: &BrotliEncoderOptions<M>,
+    chunk_size: usize,
+) -> io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+    M: encode::WindowMode,
+{
+    let mut encoder = options
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut buf = vec![0; chunk_size.max(1)];
+    let mut total = 0;
+
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        let op = if n == 0 {
+            BrotliOperation::Finish
+        } else {
+            BrotliOperation::Process
+        };
+        let mut chunk = &buf[..n];
+
+        loop {
+            let bytes_read = encoder.give_input(chunk, op)?;
+            chunk = &chunk[bytes_read..];
+
+            // SAFETY: the output is written before the encoder is used again
+            while let Some(compressed) = unsafe { encoder.take_output() } {
+                output.write_all(compressed)?;
+                total += compressed.len() as u64;
+            }
+
+            if n == 0 {
+                if encoder.is_finished() {
+                    return Ok(total);
+                }
+            } else if chunk.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+/// Decompresses everything read from `input` and writes it to `output`.
+///
+/// A decoder is built from `options` and fed with chunks of at most
+/// `chunk_size` bytes read from `input` until the compression stream is
+/// finished. Once it is finished, `input` is read once more to make sure that
+/// no data follows the end of the compression stream. A `chunk_size` of 0 is
+/// treated as 1. Returns the number of decompressed bytes written to `output`.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `options` are invalid
+/// * reading from `input` or writing to `output` fails
+/// * `input` is corrupted or ends before the compression stream is finished
+/// * `input` contains data following the end of the compression stream, in
+///   which case the error is of kind [`io::ErrorKind::InvalidData`]
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_stream, decompress_stream, BrotliDecoderOptions, BrotliEncoderOptions};
+///
+/// let input = b"hello hello hello hello hello".repeat(100);
+/// let mut compressed = Vec::new();
+/// compress_stream(
+///     &mut input.as_slice(),
+///     &mut compressed,
+///     &BrotliEncoderOptions::new(),
+///     4096,
+/// )?;
+///
+/// let mut decompressed = Vec::new();
+/// let written = decompress_stream(
+///     &mut compressed.as_slice(),
+///     &mut decompressed,
+///     &BrotliDecoderOptions::new(),
+///     4096,
+/// )?;
+///
+/// assert_eq!(written, input.len() as u64);
+/// assert_eq!(decompressed, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn decompress_stream<R, W>(
+    input: &mut R,
+    output: &mut W,
+    options: &BrotliDecoderOptions,
+    chunk_size: usize,
+) -> io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut decoder = options
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut buf = vec![0; chunk_size.max(1)];
+    let mut total = 0;
+
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut chunk = &buf[..n];
+
+        loop {
+            let (bytes_read, info) = decoder.give_input(chunk)?;
+            chunk = &chunk[bytes_read..];
+
+            // SAFETY: the output is written before the decoder is used again
+            while let Some(decompressed) = unsafe { decoder.take_output() } {
+                output.write_all(decompressed)?;
+                total += decompressed.len() as u64;
+            }
+
+            match info {
+                DecoderInfo::Finished => {
+                    if chunk.is_empty() && !has_trailing_data(input, &mut buf)? {
+                        return Ok(total);
+                    }
+
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "data following the end of the compression stream",
+                    ));
+                }
+                DecoderInfo::NeedsMoreInput => break,
+                DecoderInfo::NeedsMoreOutput => {}
+            }
+        }
+    }
+}
+
+/// Returns whether any more data can be read from `input`.
+#[cfg(feature = "std")]
+fn has_trailing_data<R: Read + ?Sized>(input: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    loop {
+        match input.read(buf) {
+            Ok(n) => return Ok(n != 0),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decompresses all of `input` and writes it to `writer`.
+///
+/// A [`DecompressorWriter`] with a fresh decoder is used to decompress
+/// `input`, which must contain a complete compression stream. This avoids
+/// decompressing into an intermediate [`Vec<u8>`] first. Returns the number of
+/// decompressed bytes written to `writer`.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * writing to `writer` fails
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_to_writer, decompress_to_writer, BrotliEncoderOptions};
+///
+/// let input = b"hello hello hello hello hello".repeat(100);
+/// let mut compressed = Vec::new();
+/// compress_to_writer(&input, &mut compressed, &BrotliEncoderOptions::new())?;
+///
+/// let mut decompressed = Vec::new();
+/// let written = decompress_to_writer(&compressed, &mut decompressed)?;
+///
+/// assert_eq!(written, input.len());
+/// assert_eq!(decompressed, input);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn decompress_to_writer<W: Write + ?Sized>(input: &[u8], writer: &mut W) -> io::Result<usize> {
+    let mut decompressor = DecompressorWriter::new(writer);
+
+    decompressor.write_all(input)?;
+
+    let total = decompressor.total_out();
+    decompressor.into_inner()?;
+
+    Ok(total)
+}
+
+/// Decompresses all of `input` into a newly allocated [`Vec<u8>`] without
+/// blocking the asynchronous runtime.
+///
+/// The input is copied and decompressed using [`decompress_to_vec`] on the
+/// blocking thread pool of the current `tokio` runtime. Requires the `tokio`
+/// feature.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+/// * the runtime is shutting down
+///
+/// # Panics
+///
+/// Panics if called outside of a `tokio` runtime.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_to_vec, decompress_async, CompressionMode, Quality, WindowSize};
+///
+/// # tokio_test::block_on(async {
+/// let input = vec![0; 1024];
+/// let compressed = compress_to_vec(
+///     &input,
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// assert_eq!(decompress_async(&compressed).await?, input);
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn decompress_async(input: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    let input = input.to_vec();
+    let task = tokio::task::spawn_blocking(move || decompress_to_vec(&input));
+
+    match task.await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => Err(DecompressError::new(DecompressErrorKind::Other)),
+    }
+}
+
+/// Decompresses all of `input` into a newly allocated [`Bytes`].
+///
+/// This is a wrapper around [`decompress_to_vec`] for code that passes data
+/// around as [`Bytes`]. The decompressed output is handed over to the
+/// returned [`Bytes`] without copying it.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if `input` is corrupted or incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use brotlic::{compress_bytes, decompress_bytes, CompressionMode, Quality, WindowSize};
+/// use bytes::Bytes;
+///
+/// let input = Bytes::from_static(b"hello world");
+/// let compressed = compress_bytes(
+///     input.clone(),
+///     Quality::default(),
+///     WindowSize::default(),
+///     CompressionMode::Generic,
+/// )?;
+///
+/// assert_eq!(decompress_bytes(compressed)?, input);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Bytes`]: bytes::Bytes
+#[cfg(feature = "bytes")]
+pub fn decompress_bytes(input: bytes::Bytes) -> Result<bytes::Bytes, DecompressError> {
+    decompress_to_vec(&input).map(bytes::Bytes::from)
+}
+
+/// A writer that appends all written data to a [`BytesMut`].
+///
+/// This allows to compress or decompress directly into a [`BytesMut`], e.g.
+/// by using a `CompressorWriter<BytesMutWriter>`. Writing never fails, the
+/// buffer grows as needed.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// use brotlic::{BytesMutWriter, CompressorWriter};
+///
+/// let mut compressor = CompressorWriter::new(BytesMutWriter::new());
+/// compressor.write_all(b"hello world")?;
+///
+/// let compressed = compressor.into_inner()?.freeze();
+/// assert_eq!(brotlic::decompress_bytes(compressed)?, &b"hello world"[..]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`BytesMut`]: bytes::BytesMut
+#[cfg(feature = "bytes")]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct BytesMutWriter(bytes::BytesMut);
+
+#[cfg(feature = "bytes")]
+impl BytesMutWriter {
+    /// Creates a new writer with an empty buffer.
+    pub fn new() -> Self {
+        BytesMutWriter(bytes::BytesMut::new())
+    }
+
+    /// Creates a new writer with an empty buffer that can hold at least
+    /// `capacity` bytes without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        BytesMutWriter(bytes::BytesMut::with_capacity(capacity))
+    }
+
+    /// Gets a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &bytes::BytesMut {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the underlying buffer.
+    pub fn get_mut(&mut self) -> &mut bytes::BytesMut {
+        &mut self.0
+    }
+
+    /// Unwraps this `BytesMutWriter`, returning the underlying buffer.
+    pub fn into_inner(self) -> bytes::BytesMut {
+        self.0
+    }
+
+    /// Converts the written data into an immutable [`Bytes`] without copying.
+    ///
+    /// [`Bytes`]: bytes::Bytes
+    pub fn freeze(self) -> bytes::Bytes {
+        self.0.freeze()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<bytes::BytesMut> for BytesMutWriter {
+    /// Creates a writer that appends to `buf`.
+    fn from(buf: bytes::BytesMut) -> Self {
+        BytesMutWriter(buf)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Write for BytesMutWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Creates a new [`NullWriter`], which discards all data written to it.
+///
+/// This is a shorthand for [`NullWriter::new`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// use brotlic::CompressorWriter;
+///
+/// let mut compressor = CompressorWriter::new(brotlic::null_sink());
+/// compressor.write_all(b"hello world")?;
+///
+/// let compressed_size = compressor.into_inner()?.bytes_written();
+/// assert!(compressed_size > 0);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn null_sink() -> NullWriter {
+    NullWriter::new()
+}
+
+/// A writer that discards all data written to it, while counting the number
+/// of bytes written.
+///
+/// Wrapping a `NullWriter` in a [`CompressorWriter`] measures the compressed
+/// size of some data without storing the compressed data anywhere. It is also
+/// useful for measuring throughput without being limited by I/O.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// use brotlic::{CompressorWriter, NullWriter};
+///
+/// let mut compressor = CompressorWriter::new(NullWriter::new());
+/// compressor.write_all(b"hello world")?;
+///
+/// let compressed_size = compressor.into_inner()?.bytes_written();
+/// println!("compressed to {compressed_size} bytes");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct NullWriter {
+    bytes_written: usize,
+}
+
+#[cfg(feature = "std")]
+impl NullWriter {
+    /// Creates a new writer that has not written any bytes yet.
+    pub const fn new() -> Self {
+        NullWriter { bytes_written: 0 }
+    }
+
+    /// Returns the number of bytes written to this writer so far.
+    pub const fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for NullWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes_written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Decompresses all of `input` into a newly allocated [`String`].
+///
+/// A fresh [`BrotliDecoder`] is used to decompress `input`, which must contain
+/// a complete compression stream. This is useful for e.g. HTTP response bodies
+/// that are known to be UTF-8.
+///
+/// # Errors
+///
+/// An [`Err`] will be returned if:
+///
+/// * `input` is corrupted or incomplete
+/// * memory allocation failed
+/// * the decompressed data is not valid UTF-8
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// use brotlic::CompressorWriter;
+///
+/// let mut compressor = CompressorWriter::new(Vec::new());
+/// compressor.write_all(b"hello world")?;
+/// let compressed = compressor.into_inner()?;
+///
+/// assert_eq!(brotlic::decompress_to_string(&compressed)?, "hello world");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn decompress_to_string(input: &[u8]) -> Result<String, DecompressToStringError> {
+    let mut decoder = BrotliDecoder::new();
+    let mut output = String::new();
+
+    let res = decoder
+        .decompress_to_string(input, &mut output)
+        .map_err(|err| match err {
+            DecodeToStringError::Utf8(err) => DecompressToStringError::Utf8(err),
+            DecodeToStringError::Decode(err) => DecompressToStringError::Decompress(err.into()),
+        })?;
+
+    if res.info == DecoderInfo::Finished {
+        Ok(output)
+    } else {
+        Err(DecompressToStringError::Decompress(DecompressError::new(
+            DecompressErrorKind::IncompleteInput,
+        )))
+    }
+}
+
+/// Checks whether the linked brotli encoder and decoder libraries agree on
+/// their version.
+///
+/// Both libraries are built from the same source by `brotlic-sys`, so this
+/// should always return `true`. A mismatch indicates a serious configuration
+/// problem, e.g. when dynamically linking against differing library versions.
+///
+/// # Examples
+///
+/// ```
+/// assert!(brotlic::versions_match());
+/// ```
+pub fn versions_match() -> bool {
+    BrotliEncoder::version() == BrotliDecoder::version()
+}
+
+/// Returns the `(major, minor, patch)` version of the linked brotli encoder
+/// library.
+///
+/// This unpacks the version returned by [`BrotliEncoder::version`].
+///
+/// # Examples
+///
+/// ```
+/// let (major, minor, patch) = brotlic::encoder_library_version();
+///
+/// assert!(major >= 1);
+/// ```
+#[doc(alias = "BrotliEncoderVersion")]
+pub fn encoder_library_version() -> (u32, u32, u32) {
+    unpack_version(BrotliEncoder::version())
+}
+
+/// Returns the `(major, minor, patch)` version of the linked brotli decoder
+/// library.
+///
+/// This unpacks the version returned by [`BrotliDecoder::version`].
+///
+/// # Examples
+///
+/// ```
+/// let (major, minor, patch) = brotlic::decoder_library_version();
+///
+/// assert!(major >= 1);
+/// ```
+#[doc(alias = "BrotliDecoderVersion")]
+pub fn decoder_library_version() -> (u32, u32, u32) {
+    unpack_version(BrotliDecoder::version())
+}
+
+/// Returns the version of the linked brotli encoder library formatted as
+/// `major.minor.patch`, e.g. for diagnostic logging.
+///
+/// # Examples
+///
+/// ```
+/// let version = brotlic::encoder_library_version_string();
+///
+/// assert_eq!(version.split('.').count(), 3);
+/// ```
+pub fn encoder_library_version_string() -> String {
+    let (major, minor, patch) = encoder_library_version();
+
+    format!("{major}.{minor}.{patch}")
+}
+
+/// Unpacks a version encoded as `(major << 24) | (minor << 12) | patch`.
+fn unpack_version(version: u32) -> (u32, u32, u32) {
+    (version >> 24, (version >> 12) & 0xfff, version & 0xfff)
+}
+
+/// Output of an encoder or decoder buffered before it is written to the
+/// underlying writer.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct OutputBuffer {
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+#[cfg(feature = "std")]
+impl OutputBuffer {
+    /// Creates a buffer holding up to `capacity` bytes, or `None` if
+    /// `capacity` is zero and output should not be buffered.
+    pub(crate) fn new(capacity: usize) -> Option<Box<Self>> {
+        if capacity > 0 {
+            Some(Box::new(OutputBuffer {
+                buf: Vec::with_capacity(capacity),
+                capacity,
+            }))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of bytes that can be appended before the buffer is
+    /// full.
+    pub(crate) fn spare_capacity(&self) -> usize {
+        self.capacity - self.buf.len()
+    }
+
+    /// Appends `output`, which must fit into the spare capacity.
+    pub(crate) fn extend(&mut self, output: &[u8]) {
+        debug_assert!(output.len() <= self.spare_capacity());
+
+        self.buf.extend_from_slice(output);
+    }
+
+    /// Writes all buffered output to `inner`.
+    ///
+    /// Output that was written is removed from the buffer even if an error
+    /// occurs, so that writing can be resumed afterwards.
+    pub(crate) fn write_to<W: Write + ?Sized>(&mut self, inner: &mut W) -> io::Result<()> {
+        let (written, result) = write_until_error(inner, &self.buf);
+
+        self.buf.drain(..written);
+        result
+    }
+}
+
+/// Writes as much of `buf` to `inner` as possible.
+///
+/// Returns the number of bytes written, together with the error that stopped
+/// writing before all of `buf` was written, if any.
+#[cfg(feature = "std")]
+pub(crate) fn write_until_error<W: Write + ?Sized>(
+    inner: &mut W,
+    buf: &[u8],
+) -> (usize, io::Result<()>) {
+    let mut written = 0;
+
+    while written < buf.len() {
+        match inner.write(&buf[written..]) {
+            Ok(0) => return (written, Err(io::ErrorKind::WriteZero.into())),
+            Ok(n) => written += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return (written, Err(e)),
+        }
+    }
+
+    (written, Ok(()))
+}
+
+/// A custom allocator handed to the brotli C library.
+///
+/// The allocator is boxed twice to obtain a thin pointer which is passed as the
+/// opaque handle to the C library. It must outlive the instance using it.
+pub(crate) struct BrotliAllocator(Box<Box<dyn GlobalAlloc + Send + Sync>>);
+
+impl BrotliAllocator {
+    /// Size of the header storing the requested size in front of every
+    /// allocation, chosen to preserve the alignment of the allocation.
+    const HEADER: usize = 16;
+
+    pub(crate) fn new<A: GlobalAlloc + Send + Sync + 'static>(alloc: A) -> Self {
+        BrotliAllocator(Box::new(Box::new(alloc)))
+    }
+
+    /// Returns the allocation functions and opaque handle to pass to the C
+    /// library, or the defaults if no allocator is present.
+    pub(crate) fn funcs(
+        alloc: Option<&BrotliAllocator>,
+    ) -> (brotli_alloc_func, brotli_free_func, *mut c_void) {
+        match alloc {
+            Some(alloc) => (
+                Some(Self::alloc),
+                Some(Self::free),
+                &*alloc.0 as *const Box<dyn GlobalAlloc + Send + Sync> as *mut c_void,
+            ),
+            None => (None, None, ptr::null_mut()),
+        }
+    }
+
+    extern "C" fn alloc(opaque: *mut c_void, size: usize) -> *mut c_void {
+        let alloc = unsafe { &*(opaque as *const Box<dyn GlobalAlloc + Send + Sync>) };
+        let layout = match size
+            .checked_add(Self::HEADER)
+            .map(|size| Layout::from_size_align(size, Self::HEADER))
+        {
+            Some(Ok(layout)) => layout,
+            _ => return ptr::null_mut(),
+        };
+
+        unsafe {
+            let ptr = alloc.alloc(layout);
+
+            if ptr.is_null() {
+                return ptr::null_mut();
+            }
+
+            (ptr as *mut usize).write(size);
+            ptr.add(Self::HEADER) as *mut c_void
+        }
+    }
+
+    extern "C" fn free(opaque: *mut c_void, address: *mut c_void) {
+        if address.is_null() {
+            return;
+        }
+
+        let alloc = unsafe { &*(opaque as *const Box<dyn GlobalAlloc + Send + Sync>) };
+
+        unsafe {
+            let ptr = (address as *mut u8).sub(Self::HEADER);
+            let size = (ptr as *const usize).read();
+            let layout = Layout::from_size_align_unchecked(size + Self::HEADER, Self::HEADER);
+
+            alloc.dealloc(ptr, layout);
+        }
+    }
+}
+
+impl fmt::Debug for BrotliAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrotliAllocator").finish_non_exhaustive()
     }
 }
 
@@ -998,9 +3475,11 @@ pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<usize, DecompressEr
 ///
 /// This error combines an error that happened while processing data, and the
 /// instance object which may be used to recover from the condition.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct IntoInnerError<I>(I, io::Error);
 
+#[cfg(feature = "std")]
 impl<I> IntoInnerError<I> {
     fn new(inner: I, error: io::Error) -> Self {
         Self(inner, error)
@@ -1029,14 +3508,17 @@ impl<I> IntoInnerError<I> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<I> From<IntoInnerError<I>> for io::Error {
     fn from(iie: IntoInnerError<I>) -> io::Error {
         iie.1
     }
 }
 
+#[cfg(feature = "std")]
 impl<I: fmt::Debug + Send> Error for IntoInnerError<I> {}
 
+#[cfg(feature = "std")]
 impl<I> fmt::Display for IntoInnerError<I> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.error().fmt(f)
@@ -0,0 +1,62 @@
+//! `Serialize` and `Deserialize` implementations of the parameter types.
+//!
+//! The size types and [`Quality`] are represented by their `u8` value and
+//! validated on deserialization, [`CompressionMode`] is represented by its
+//! lowercase name.
+
+use alloc::string::String;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{BlockSize, CompressionMode, LargeWindowSize, Quality, WindowSize};
+
+macro_rules! impl_serde_u8 {
+    ($ty:ident) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_u8(self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = u8::deserialize(deserializer)?;
+
+                $ty::new(value).map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde_u8!(Quality);
+impl_serde_u8!(WindowSize);
+impl_serde_u8!(LargeWindowSize);
+impl_serde_u8!(BlockSize);
+
+const COMPRESSION_MODES: &[&str] = &["generic", "text", "font"];
+
+impl Serialize for CompressionMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self {
+            CompressionMode::Generic => "generic",
+            CompressionMode::Text => "text",
+            CompressionMode::Font => "font",
+        };
+
+        serializer.serialize_str(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompressionMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+
+        match name.as_str() {
+            "generic" => Ok(CompressionMode::Generic),
+            "text" => Ok(CompressionMode::Text),
+            "font" => Ok(CompressionMode::Font),
+            _ => Err(D::Error::unknown_variant(&name, COMPRESSION_MODES)),
+        }
+    }
+}
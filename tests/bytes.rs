@@ -0,0 +1,57 @@
+#![cfg(feature = "bytes")]
+
+use std::io::Write;
+
+use brotlic::{
+    BytesMutWriter, CompressionMode, CompressorWriter, DecompressorWriter, Quality, WindowSize,
+};
+use bytes::{Bytes, BytesMut};
+
+mod common;
+
+fn compress(input: Bytes) -> Bytes {
+    brotlic::compress_bytes(
+        input,
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_bytes_round_trip() {
+    for input in [
+        common::gen_min_entropy(64 * 1024),
+        common::gen_medium_entropy(64 * 1024),
+        common::gen_max_entropy(64 * 1024),
+    ] {
+        let input = Bytes::from(input);
+        let compressed = compress(input.clone());
+
+        assert_eq!(brotlic::decompress_bytes(compressed).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_decompress_bytes_corrupt() {
+    let compressed = compress(Bytes::from(common::gen_medium_entropy(4096)));
+
+    assert!(brotlic::decompress_bytes(compressed.slice(..compressed.len() / 2)).is_err());
+}
+
+#[test]
+fn test_bytes_mut_writer() {
+    let input = common::gen_medium_entropy(64 * 1024);
+
+    let mut compressor = CompressorWriter::new(BytesMutWriter::with_capacity(1024));
+    compressor.write_all(&input).unwrap();
+    let compressed = compressor.into_inner().unwrap().freeze();
+
+    assert_eq!(compressed, compress(Bytes::from(input.clone())));
+
+    let mut decompressor = DecompressorWriter::new(BytesMutWriter::from(BytesMut::new()));
+    decompressor.write_all(&compressed).unwrap();
+
+    assert_eq!(decompressor.into_inner().unwrap().into_inner(), input);
+}
@@ -19,3 +19,14 @@ pub fn gen_max_entropy(len: usize) -> Vec<u8> {
     rng.fill(res.as_mut_slice());
     res
 }
+
+/// Inputs of increasing size and entropy, including an empty one.
+#[allow(dead_code)]
+pub fn gen_inputs() -> [Vec<u8>; 4] {
+    [
+        Vec::new(),
+        gen_min_entropy(1),
+        gen_medium_entropy(4096),
+        gen_max_entropy(100_000),
+    ]
+}
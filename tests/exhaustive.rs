@@ -0,0 +1,89 @@
+use brotlic::{BlockSize, CompressionMode, LargeWindowSize, Quality, WindowSize};
+
+mod common;
+
+fn verify(input: &[u8], quality: Quality, window_size: WindowSize) {
+    let bound = brotlic::compress_bound(input.len(), quality);
+    let compressed = {
+        // the bound is only valid for a quality of at least 2, so give lower
+        // qualities plenty of room.
+        let mut buf = vec![0; bound.unwrap_or(input.len() * 2 + 1024)];
+        let size = brotlic::compress(
+            input,
+            buf.as_mut_slice(),
+            quality,
+            window_size,
+            CompressionMode::Generic,
+        )
+        .unwrap();
+
+        buf.truncate(size);
+        buf
+    };
+
+    if let Some(bound) = bound {
+        assert!(compressed.len() <= bound);
+    }
+
+    let decompressed = {
+        let mut buf = vec![0; input.len()];
+        let size = brotlic::decompress(compressed.as_slice(), buf.as_mut_slice()).unwrap();
+
+        buf.truncate(size);
+        buf
+    };
+
+    assert_eq!(input, decompressed);
+}
+
+#[test]
+fn test_all_quality_window_size_combinations() {
+    let inputs = [
+        common::gen_min_entropy(1024),
+        common::gen_medium_entropy(1024),
+        common::gen_max_entropy(1024),
+    ];
+
+    for input in &inputs {
+        for quality in Quality::iter() {
+            for window_size in WindowSize::iter() {
+                verify(input.as_slice(), quality, window_size);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_iter_yields_all_valid_values() {
+    assert_eq!(Quality::iter().len(), 12);
+    assert_eq!(WindowSize::iter().len(), 15);
+    assert_eq!(LargeWindowSize::iter().len(), 21);
+    assert_eq!(BlockSize::iter().len(), 9);
+
+    assert!(Quality::iter().eq((0..=11).map(|level| Quality::new(level).unwrap())));
+    assert!(WindowSize::iter().eq((10..=24).map(|bits| WindowSize::new(bits).unwrap())));
+    assert!(LargeWindowSize::iter().eq((10..=30).map(|bits| LargeWindowSize::new(bits).unwrap())));
+    assert!(BlockSize::iter().eq((16..=24).map(|bits| BlockSize::new(bits).unwrap())));
+
+    assert_eq!(Quality::iter().next_back(), Some(Quality::best()));
+    assert!(BlockSize::iter()
+        .rev()
+        .eq((16..=24).rev().map(|bits| BlockSize::new(bits).unwrap())));
+}
+
+#[test]
+fn test_large_window_size_is_standard() {
+    for large_window_size in LargeWindowSize::iter() {
+        let standard = WindowSize::try_from(large_window_size).ok();
+
+        assert_eq!(large_window_size.is_standard(), standard.is_some());
+        assert_eq!(large_window_size.try_as_standard(), standard);
+    }
+
+    assert_eq!(
+        LargeWindowSize::iter()
+            .filter(LargeWindowSize::is_standard)
+            .count(),
+        WindowSize::iter().len()
+    );
+}
@@ -0,0 +1,172 @@
+#![cfg(feature = "futures-io")]
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use brotlic::{CompressorReader, CompressorWriter, DecompressorReader, DecompressorWriter};
+use futures::executor::block_on;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, Cursor};
+
+mod common;
+
+fn compress(input: &[u8]) -> Vec<u8> {
+    let mut compressor = CompressorWriter::new(Vec::new());
+    Write::write_all(&mut compressor, input).unwrap();
+    compressor.into_inner().unwrap()
+}
+
+/// Writer that is pending on every other call and accepts at most 7 bytes.
+#[derive(Debug, Default)]
+struct TrickleWriter {
+    data: Vec<u8>,
+    ready: bool,
+}
+
+impl AsyncWrite for TrickleWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let n = buf.len().min(7);
+        self.data.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reader that is pending on every other call and yields at most 5 bytes.
+#[derive(Debug)]
+struct TrickleReader<'a> {
+    data: &'a [u8],
+    ready: bool,
+}
+
+impl<'a> TrickleReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        TrickleReader { data, ready: false }
+    }
+}
+
+impl AsyncRead for TrickleReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let n = buf.len().min(self.data.len()).min(5);
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn test_async_write_comp() {
+    let input = common::gen_medium_entropy(256 * 1024);
+    let expected = compress(&input);
+
+    let mut compressor = CompressorWriter::new_async(Cursor::new(Vec::new()));
+
+    block_on(async {
+        compressor.write_all(&input).await.unwrap();
+        compressor.close().await.unwrap();
+    });
+
+    assert_eq!(*compressor.get_ref().get_ref(), expected);
+}
+
+#[test]
+fn test_async_write_comp_trickle() {
+    let input = common::gen_max_entropy(4096);
+
+    let mut compressor = CompressorWriter::new_async(TrickleWriter::default());
+
+    block_on(async {
+        compressor.write_all(&input[..1000]).await.unwrap();
+        compressor.flush().await.unwrap();
+        compressor.write_all(&input[1000..]).await.unwrap();
+        compressor.close().await.unwrap();
+    });
+
+    let compressed = &compressor.get_ref().data;
+    assert_eq!(brotlic::decompress_to_vec(compressed).unwrap(), input);
+}
+
+#[test]
+fn test_async_write_decomp_trickle() {
+    let input = common::gen_min_entropy(4096);
+    let compressed = compress(&input);
+
+    let mut decompressor = DecompressorWriter::new(TrickleWriter::default());
+
+    block_on(async {
+        decompressor.write_all(&compressed).await.unwrap();
+        decompressor.close().await.unwrap();
+    });
+
+    assert_eq!(decompressor.get_ref().data, input);
+}
+
+#[test]
+fn test_async_write_decomp_unfinished() {
+    let compressed = compress(&common::gen_medium_entropy(4096));
+    let truncated = &compressed[..compressed.len() / 2];
+
+    let mut decompressor = DecompressorWriter::new(TrickleWriter::default());
+
+    let err = block_on(async {
+        decompressor.write_all(truncated).await.unwrap();
+        decompressor.close().await.unwrap_err()
+    });
+
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_async_read_comp() {
+    let input = common::gen_medium_entropy(64 * 1024);
+
+    let mut compressor = CompressorReader::new(BufReader::new(TrickleReader::new(&input)));
+    let mut compressed = Vec::new();
+
+    block_on(compressor.read_to_end(&mut compressed)).unwrap();
+
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+#[test]
+fn test_async_read_decomp() {
+    let input = common::gen_max_entropy(64 * 1024);
+    let compressed = compress(&input);
+
+    let mut decompressor = DecompressorReader::new(BufReader::new(TrickleReader::new(&compressed)));
+    let mut decompressed = Vec::new();
+
+    block_on(decompressor.read_to_end(&mut decompressed)).unwrap();
+
+    assert_eq!(decompressed, input);
+    assert!(decompressor.is_finished());
+}
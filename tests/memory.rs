@@ -1,4 +1,9 @@
-use brotlic::{CompressionMode, LargeWindowSize, Quality, WindowSize};
+#[cfg(feature = "std")]
+use brotlic::BrotliEncoderOptions;
+use brotlic::{
+    BrotliDecoder, BrotliEncoder, CompressErrorKind, CompressionMode, DecompressErrorKind,
+    DecompressToStringError, LargeWindowSize, Quality, WindowSize,
+};
 
 mod common;
 
@@ -26,6 +31,12 @@ fn verify(input: &[u8]) {
     };
 
     assert_eq!(input, decompressed);
+
+    let compressed_vec = brotlic::compress_to_vec(input, quality, window_size, mode).unwrap();
+    assert_eq!(compressed, compressed_vec);
+
+    let decompressed_vec = brotlic::decompress_to_vec(compressed.as_slice()).unwrap();
+    assert_eq!(input, decompressed_vec);
 }
 
 #[test]
@@ -74,6 +85,7 @@ fn test_max_entropy_large() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_encoder_estimate_peak_memory_usage() {
     let usage100 =
         brotlic::compress_estimate_max_mem_usage(100, Quality::best(), WindowSize::best());
@@ -82,6 +94,7 @@ fn test_encoder_estimate_peak_memory_usage() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_google_brotli_issue_1001() {
     let window_size =
         brotlic::compress_estimate_max_mem_usage(1024 * 1024, Quality::best(), WindowSize::best());
@@ -93,3 +106,447 @@ fn test_google_brotli_issue_1001() {
 
     assert!(large_window_size > window_size);
 }
+
+#[test]
+fn test_encoder_estimate_peak_memory() {
+    let usage100 = brotlic::encoder_estimate_peak_memory(Quality::best(), WindowSize::best(), 100);
+
+    assert!(usage100 > 0);
+}
+
+#[test]
+fn test_encoder_estimate_peak_memory_large() {
+    let window_size =
+        brotlic::encoder_estimate_peak_memory(Quality::best(), WindowSize::best(), 1024 * 1024);
+    let large_window_size = brotlic::encoder_estimate_peak_memory_large(
+        Quality::best(),
+        LargeWindowSize::best(),
+        1024 * 1024,
+    );
+
+    assert!(large_window_size > window_size);
+}
+
+#[test]
+fn test_versions_match() {
+    assert_eq!(BrotliEncoder::version(), BrotliDecoder::version());
+    assert!(brotlic::versions_match());
+}
+
+#[test]
+fn test_library_versions() {
+    let (major, minor, patch) = brotlic::encoder_library_version();
+
+    assert!(major >= 1);
+    assert_eq!(
+        brotlic::encoder_library_version(),
+        brotlic::decoder_library_version()
+    );
+    assert_eq!(
+        brotlic::encoder_library_version_string(),
+        format!("{major}.{minor}.{patch}")
+    );
+    assert_eq!(
+        BrotliEncoder::version(),
+        (major << 24) | (minor << 12) | patch
+    );
+}
+
+#[test]
+fn test_decompress_to_string() {
+    let input = "hello brotli ".repeat(64);
+    let mut compressed = vec![0; brotlic::compress_bound(input.len(), Quality::best()).unwrap()];
+    let size = brotlic::compress(
+        input.as_bytes(),
+        compressed.as_mut_slice(),
+        Quality::best(),
+        WindowSize::best(),
+        CompressionMode::Text,
+    )
+    .unwrap();
+
+    assert_eq!(
+        brotlic::decompress_to_string(&compressed[..size]).unwrap(),
+        input
+    );
+    assert!(matches!(
+        brotlic::decompress_to_string(&compressed[..size / 2]),
+        Err(DecompressToStringError::Decompress(err)) if err.kind() == DecompressErrorKind::IncompleteInput
+    ));
+
+    let invalid_utf8 = brotlic::compress_to_vec(
+        &[b'h', b'i', 0xff, 0xfe],
+        Quality::best(),
+        WindowSize::best(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        brotlic::decompress_to_string(&invalid_utf8),
+        Err(DecompressToStringError::Utf8(err)) if err.valid_up_to() == 2
+    ));
+
+    #[cfg(feature = "std")]
+    {
+        let err = std::io::Error::from(brotlic::decompress_to_string(&invalid_utf8).unwrap_err());
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn test_compress_to_vec_low_quality() {
+    let input = common::gen_max_entropy(100_000);
+
+    for quality in [0, 1] {
+        let quality = Quality::new(quality).unwrap();
+        let compressed = brotlic::compress_to_vec(
+            input.as_slice(),
+            quality,
+            WindowSize::default(),
+            CompressionMode::Generic,
+        )
+        .unwrap();
+
+        assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_decompress_to_vec_incomplete() {
+    let input = common::gen_medium_entropy(4096);
+    let compressed = brotlic::compress_to_vec(
+        input.as_slice(),
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+
+    assert!(brotlic::decompress_to_vec(&compressed[..compressed.len() / 2]).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_compress_parallel() {
+    let input = common::gen_medium_entropy(1_000_003);
+    let options = BrotliEncoderOptions::new()
+        .with_quality(Quality::new(5).unwrap())
+        .unwrap();
+
+    for num_threads in [0, 1, 3, 4, 7] {
+        let compressed = brotlic::compress_parallel(&input, &options, num_threads).unwrap();
+
+        assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_compress_parallel_all_qualities() {
+    for quality in Quality::iter() {
+        let options = BrotliEncoderOptions::new().with_quality(quality).unwrap();
+
+        for input in [
+            common::gen_medium_entropy(65_537),
+            common::gen_max_entropy(100),
+            common::gen_min_entropy(3),
+        ] {
+            for num_threads in [1, 2, 7, 64] {
+                let compressed = brotlic::compress_parallel(&input, &options, num_threads).unwrap();
+
+                assert_eq!(
+                    brotlic::decompress_to_vec(&compressed).unwrap(),
+                    input,
+                    "{quality:?} with {num_threads} threads"
+                );
+            }
+        }
+
+        // chunks are at least one byte long, which is longer than empty input
+        let compressed = brotlic::compress_parallel(&[], &options, 4).unwrap();
+        assert!(brotlic::decompress_to_vec(&compressed).unwrap().is_empty());
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_compress_parallel_small_input() {
+    let options = BrotliEncoderOptions::new();
+
+    for input in [&b""[..], b"a", b"hello world"] {
+        let compressed = brotlic::compress_parallel(input, &options, 8).unwrap();
+
+        assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_compress_to_writer() {
+    let quality = Quality::new(9).unwrap();
+    let window_size = WindowSize::new(22).unwrap();
+    let options = BrotliEncoderOptions::new()
+        .with_quality(quality)
+        .unwrap()
+        .with_window_size(window_size)
+        .unwrap();
+
+    // empty input is left out, for which the one-shot encoder emits a
+    // shorter stream that omits the window size
+    for input in common::gen_inputs().into_iter().skip(1) {
+        let mut compressed = Vec::new();
+        let written = brotlic::compress_to_writer(&input, &mut compressed, &options).unwrap();
+
+        let bound = brotlic::compress_bound(input.len(), quality).unwrap();
+        let mut expected = vec![0; bound];
+        let size = brotlic::compress(
+            &input,
+            &mut expected,
+            quality,
+            window_size,
+            CompressionMode::Generic,
+        )
+        .unwrap();
+        expected.truncate(size);
+
+        assert_eq!(written, compressed.len());
+        assert_eq!(compressed, expected);
+
+        let mut decompressed = Vec::new();
+        let written = brotlic::decompress_to_writer(&compressed, &mut decompressed).unwrap();
+
+        assert_eq!(written, input.len());
+        assert_eq!(decompressed, input);
+
+        let truncated = &compressed[..compressed.len() - 1];
+        let err = brotlic::decompress_to_writer(truncated, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_compressor_writer_fmt_write() {
+    use std::fmt::Write;
+
+    let mut compressor = brotlic::CompressorWriter::new(Vec::new());
+    let mut expected = String::new();
+
+    for (i, name) in ["alpha", "beta", "gamma"]
+        .iter()
+        .cycle()
+        .take(1000)
+        .enumerate()
+    {
+        writeln!(compressor, "{i}: hello {name}").unwrap();
+        writeln!(expected, "{i}: hello {name}").unwrap();
+    }
+
+    let compressed = compressor.into_inner().unwrap();
+    assert_eq!(
+        brotlic::decompress_to_string(&compressed).unwrap(),
+        expected
+    );
+}
+
+#[cfg(feature = "std")]
+fn verify_compressed(input: &[u8], compressed: &[u8], options: &BrotliEncoderOptions) {
+    let mut expected = Vec::new();
+    brotlic::compress_to_writer(input, &mut expected, options).unwrap();
+
+    assert_eq!(compressed, expected);
+    assert_eq!(brotlic::decompress_to_vec(compressed).unwrap(), input);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_thread_local_encoder() {
+    let fast = BrotliEncoderOptions::fast();
+    let best = BrotliEncoderOptions::best();
+
+    let mut compress_fast = brotlic::thread_local_encoder(&fast);
+    let mut compress_best = brotlic::thread_local_encoder(&best);
+
+    for input in common::gen_inputs() {
+        // alternate between the options, so that both encoders are pooled
+        verify_compressed(&input, &compress_fast(&input).unwrap(), &fast);
+        verify_compressed(&input, &compress_fast(&input).unwrap(), &fast);
+        verify_compressed(&input, &compress_best(&input).unwrap(), &best);
+        verify_compressed(&input, &compress_fast(&input).unwrap(), &fast);
+    }
+
+    let input = common::gen_medium_entropy(4096);
+    let expected = compress_fast(&input).unwrap();
+
+    std::thread::spawn(move || {
+        let mut compress = brotlic::thread_local_encoder(&BrotliEncoderOptions::fast());
+        assert_eq!(compress(&input).unwrap(), expected);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[allow(deprecated)]
+fn test_thread_local_encoder_invalid_options() {
+    let mut compress =
+        brotlic::thread_local_encoder(BrotliEncoderOptions::new().stream_offset(1 << 31));
+    let err = compress(b"hello world").unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_compress_error_kind() {
+    let input = common::gen_max_entropy(4096);
+    let mut output = vec![0; 1024];
+
+    let err = brotlic::compress(
+        &input,
+        &mut output,
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), CompressErrorKind::BufferTooSmall);
+
+    #[cfg(feature = "std")]
+    {
+        let mut options = BrotliEncoderOptions::new();
+        options.postfix_bits(4);
+        let err = brotlic::compress_parallel(&input, &options, 2).unwrap_err();
+
+        assert_eq!(err.kind(), CompressErrorKind::InvalidParameters);
+    }
+}
+
+#[test]
+fn test_decompress_error_kind() {
+    let input = common::gen_medium_entropy(4096);
+    let compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+    let mut output = vec![0; input.len()];
+
+    let err = brotlic::decompress(&compressed, &mut output[..100]).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::BufferTooSmall);
+
+    let err = brotlic::decompress(&compressed[..compressed.len() / 2], &mut output).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::IncompleteInput);
+
+    let err = brotlic::decompress(&[0xff; 64], &mut output).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::CorruptedInput);
+
+    let err = brotlic::decompress_to_vec(&compressed[..compressed.len() / 2]).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::IncompleteInput);
+
+    let err = brotlic::decompress_to_vec(&[0xff; 64]).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::CorruptedInput);
+}
+
+#[test]
+fn test_decompress_bounded() {
+    let input = common::gen_min_entropy(1024 * 1024);
+    let compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+
+    assert!(compressed.len() < 64);
+
+    for limit in [0, 1, 1024, input.len() - 1] {
+        let err = brotlic::decompress_to_vec_bounded(&compressed, limit).unwrap_err();
+        assert_eq!(err.kind(), DecompressErrorKind::OutputLimitExceeded);
+
+        let mut output = vec![0; input.len()];
+        let err = brotlic::decompress_bounded(&compressed, &mut output, limit).unwrap_err();
+        assert_eq!(err.kind(), DecompressErrorKind::OutputLimitExceeded);
+    }
+
+    assert_eq!(
+        brotlic::decompress_to_vec_bounded(&compressed, input.len()).unwrap(),
+        input
+    );
+
+    let mut output = vec![0; input.len() + 1];
+    let size = brotlic::decompress_bounded(&compressed, &mut output, input.len()).unwrap();
+    assert_eq!(&output[..size], input.as_slice());
+
+    let err = brotlic::decompress_bounded(&compressed, &mut output[..1024], 2048).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::BufferTooSmall);
+}
+
+const HTTP_HEADERS: &[u8] = b"HTTP/1.1 200 OK\r\n\
+content-type: text/html; charset=utf-8\r\n\
+cache-control: public, max-age=31536000, immutable\r\n\
+content-encoding: br\r\n\
+vary: accept-encoding\r\n\
+strict-transport-security: max-age=63072000; includeSubDomains; preload\r\n\
+x-content-type-options: nosniff\r\n\
+access-control-allow-origin: *\r\n\r\n";
+
+#[test]
+fn test_compress_with_dictionary() {
+    let input = b"HTTP/1.1 200 OK\r\n\
+content-type: text/html; charset=utf-8\r\n\
+cache-control: public, max-age=3600\r\n\
+content-encoding: br\r\n\
+vary: accept-encoding\r\n\
+x-content-type-options: nosniff\r\n\r\n";
+
+    let with_dictionary = brotlic::compress_with_dictionary(
+        input,
+        HTTP_HEADERS,
+        Quality::best(),
+        WindowSize::default(),
+    )
+    .unwrap();
+    let without_dictionary = brotlic::compress_to_vec(
+        input,
+        Quality::best(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+
+    assert!(with_dictionary.len() < without_dictionary.len());
+    assert_eq!(
+        brotlic::decompress_with_dictionary(&with_dictionary, HTTP_HEADERS).unwrap(),
+        input
+    );
+}
+
+#[test]
+fn test_dictionary_error_kind() {
+    let err = brotlic::compress_with_dictionary(
+        b"hello world",
+        &[],
+        Quality::best(),
+        WindowSize::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), CompressErrorKind::InvalidDictionary);
+
+    let compressed = brotlic::compress_to_vec(
+        b"hello world",
+        Quality::best(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .unwrap();
+
+    let err = brotlic::decompress_with_dictionary(&compressed, &[]).unwrap_err();
+    assert_eq!(err.kind(), DecompressErrorKind::InvalidDictionary);
+}
@@ -0,0 +1,62 @@
+//! Checks that the API available without the `std` feature can be used from a
+//! `no_std` crate.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use brotlic::encode::BrotliOperation;
+use brotlic::{
+    BrotliDecoder, BrotliDecoderOptions, BrotliEncoder, BrotliEncoderOptions, CompressionMode,
+    Quality, WindowSize,
+};
+
+const INPUT: &[u8] = b"no_std no_std no_std no_std no_std no_std no_std no_std";
+
+#[test]
+fn test_one_shot_round_trip() {
+    let quality = Quality::default();
+    let mut compressed = vec![0; brotlic::compress_bound(INPUT.len(), quality).unwrap()];
+    let size = brotlic::compress(
+        INPUT,
+        &mut compressed,
+        quality,
+        WindowSize::default(),
+        CompressionMode::Text,
+    )
+    .unwrap();
+
+    let mut decompressed = [0; INPUT.len()];
+    let written = brotlic::decompress(&compressed[..size], &mut decompressed).unwrap();
+
+    assert_eq!(&decompressed[..written], INPUT);
+    assert_eq!(
+        brotlic::decompress_to_vec(&compressed[..size]).unwrap(),
+        INPUT
+    );
+}
+
+#[test]
+fn test_encoder_decoder_round_trip() {
+    let mut encoder: BrotliEncoder = BrotliEncoderOptions::new()
+        .with_quality(Quality::worst())
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut compressed = Vec::new();
+    let mut input = INPUT;
+
+    while !encoder.is_finished() {
+        input = &input[encoder.give_input(input, BrotliOperation::Finish).unwrap()..];
+        compressed.extend_from_slice(&encoder.take_output_to_vec());
+    }
+
+    let mut decoder: BrotliDecoder = BrotliDecoderOptions::new().build().unwrap();
+    decoder.give_input(&compressed).unwrap();
+
+    assert_eq!(decoder.take_output_to_vec(), INPUT);
+    assert!(decoder.is_finished());
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "serde")]
+
+use std::io::Write;
+
+use brotlic::encode::{BrotliEncoderOptions, LargeWindow};
+use brotlic::{
+    BlockSize, CompressionMode, CompressorWriter, DirectDistanceCodes, LargeWindowSize,
+    PostfixBits, Quality, WindowSize,
+};
+
+mod common;
+
+fn compress<W: brotlic::encode::WindowMode>(
+    input: &[u8],
+    options: &BrotliEncoderOptions<W>,
+) -> Vec<u8> {
+    let encoder = options.build().unwrap();
+    let mut compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    compressor.write_all(input).unwrap();
+    compressor.into_inner().unwrap()
+}
+
+#[test]
+fn test_options_round_trip() {
+    let mut options = BrotliEncoderOptions::new();
+    options
+        .mode(CompressionMode::Text)
+        .quality(9)
+        .unwrap()
+        .window_size(20)
+        .unwrap()
+        .block_size(18)
+        .unwrap()
+        .context_modeling(false)
+        .size_hint_usize(4096)
+        .distance_codes(DirectDistanceCodes::new(4, PostfixBits::new(1).unwrap()).unwrap());
+
+    let json = serde_json::to_string(&options).unwrap();
+    let deserialized: BrotliEncoderOptions = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(json, serde_json::to_string(&deserialized).unwrap());
+
+    let input = common::gen_medium_entropy(4096);
+    assert_eq!(compress(&input, &options), compress(&input, &deserialized));
+}
+
+#[test]
+fn test_mode_as_string() {
+    let mut options = BrotliEncoderOptions::new();
+    options.mode(CompressionMode::Font);
+
+    let value = serde_json::to_value(&options).unwrap();
+
+    assert_eq!(value["mode"], "font");
+}
+
+#[test]
+fn test_large_window_round_trip() {
+    let options = BrotliEncoderOptions::new().large_window_size(28).unwrap();
+
+    let value = serde_json::to_value(&options).unwrap();
+    assert_eq!(value["large_window_size"], 28);
+    assert!(value["window_size"].is_null());
+
+    let deserialized: BrotliEncoderOptions<LargeWindow> = serde_json::from_value(value).unwrap();
+
+    let input = common::gen_min_entropy(4096);
+    assert_eq!(compress(&input, &options), compress(&input, &deserialized));
+}
+
+#[test]
+fn test_large_window_requires_large_window_mode() {
+    let res = serde_json::from_str::<BrotliEncoderOptions>(r#"{"large_window_size":28}"#);
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_invalid_values_are_rejected() {
+    let json = [
+        r#"{"quality":12}"#,
+        r#"{"window_size":25}"#,
+        r#"{"block_size":15}"#,
+        r#"{"mode":"binary"}"#,
+        r#"{"postfix_bits":4}"#,
+        r#"{"stream_offset":1073741825}"#,
+    ];
+
+    for json in json {
+        let res = serde_json::from_str::<BrotliEncoderOptions>(json);
+
+        assert!(res.is_err(), "{json} was accepted");
+    }
+}
+
+#[test]
+fn test_default_options_round_trip() {
+    let options = BrotliEncoderOptions::new();
+    let json = serde_json::to_string(&options).unwrap();
+    let deserialized: BrotliEncoderOptions = serde_json::from_str(&json).unwrap();
+
+    let input = common::gen_max_entropy(1024);
+    assert_eq!(compress(&input, &options), compress(&input, &deserialized));
+}
+
+#[test]
+fn test_parameter_types_round_trip() {
+    let quality = Quality::new(7).unwrap();
+    let window_size = WindowSize::new(22).unwrap();
+    let large_window_size = LargeWindowSize::new(30).unwrap();
+    let block_size = BlockSize::new(20).unwrap();
+
+    assert_eq!(serde_json::to_string(&quality).unwrap(), "7");
+    assert_eq!(serde_json::to_string(&window_size).unwrap(), "22");
+    assert_eq!(serde_json::to_string(&large_window_size).unwrap(), "30");
+    assert_eq!(serde_json::to_string(&block_size).unwrap(), "20");
+
+    assert_eq!(serde_json::from_str::<Quality>("7").unwrap(), quality);
+    assert_eq!(
+        serde_json::from_str::<WindowSize>("22").unwrap(),
+        window_size
+    );
+    assert_eq!(
+        serde_json::from_str::<LargeWindowSize>("30").unwrap(),
+        large_window_size
+    );
+    assert_eq!(serde_json::from_str::<BlockSize>("20").unwrap(), block_size);
+
+    for (mode, name) in [
+        (CompressionMode::Generic, r#""generic""#),
+        (CompressionMode::Text, r#""text""#),
+        (CompressionMode::Font, r#""font""#),
+    ] {
+        assert_eq!(serde_json::to_string(&mode).unwrap(), name);
+        assert_eq!(serde_json::from_str::<CompressionMode>(name).unwrap(), mode);
+    }
+}
+
+#[test]
+fn test_invalid_parameter_types_are_rejected() {
+    assert!(serde_json::from_str::<Quality>("12").is_err());
+    assert!(serde_json::from_str::<WindowSize>("9").is_err());
+    assert!(serde_json::from_str::<WindowSize>("25").is_err());
+    assert!(serde_json::from_str::<LargeWindowSize>("31").is_err());
+    assert!(serde_json::from_str::<BlockSize>("25").is_err());
+    assert!(serde_json::from_str::<Quality>("256").is_err());
+    assert!(serde_json::from_str::<Quality>(r#""7""#).is_err());
+    assert!(serde_json::from_str::<CompressionMode>(r#""Text""#).is_err());
+}
+
+#[test]
+fn test_deserialized_options_equal_presets() {
+    for preset in [
+        BrotliEncoderOptions::fast(),
+        BrotliEncoderOptions::balanced(),
+        BrotliEncoderOptions::best(),
+        BrotliEncoderOptions::text(),
+    ] {
+        let json = serde_json::to_string(&preset).unwrap();
+        let deserialized: BrotliEncoderOptions = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, preset);
+    }
+
+    let parsed: BrotliEncoderOptions =
+        serde_json::from_str(r#"{"quality":1,"window_size":10}"#).unwrap();
+
+    assert_eq!(parsed, BrotliEncoderOptions::fast());
+    assert_ne!(parsed, BrotliEncoderOptions::balanced());
+}
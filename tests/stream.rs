@@ -1,6 +1,13 @@
-use std::io::{Read, Write};
+#![cfg(feature = "std")]
 
-use brotlic::{CompressorReader, CompressorWriter, DecompressorReader, DecompressorWriter};
+use std::io::{BufReader, IoSlice, Read, Write};
+
+use brotlic::decode::{DecodeError, MultiStreamDecompressorReader};
+use brotlic::encode::{BrotliEncoder, BrotliOperation, LargeWindow};
+use brotlic::{
+    BrotliDecoder, BrotliDecoderOptions, BrotliEncoderOptions, CompressorReader, CompressorWriter,
+    DecompressorReader, DecompressorWriter, LargeWindowSize, Quality, WindowSize,
+};
 
 mod common;
 
@@ -127,3 +134,965 @@ fn test_read_comp_medium_entropy_large() {
 fn test_read_comp_max_entropy_large() {
     read_comp_write_decomp_verify(common::gen_max_entropy(8192).as_slice());
 }
+
+fn compress_with_options(input: &[u8], options: &BrotliEncoderOptions) -> Vec<u8> {
+    let encoder = options.build().unwrap();
+    let mut compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    compressor.write_all(input).unwrap();
+    compressor.into_inner().unwrap()
+}
+
+fn decompress_all(input: &[u8]) -> Vec<u8> {
+    let mut decompressor = DecompressorReader::new(input);
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+    decompressed
+}
+
+#[test]
+fn test_size_hint_output() {
+    const LEN: usize = 1_000_000;
+
+    let input = common::gen_medium_entropy(LEN);
+
+    let unhinted = compress_with_options(&input, &BrotliEncoderOptions::new());
+    let hinted = compress_with_options(&input, BrotliEncoderOptions::new().size_hint_usize(LEN));
+
+    assert_eq!(input, decompress_all(&unhinted));
+    assert_eq!(input, decompress_all(&hinted));
+}
+
+#[test]
+fn test_write_comp_borrowed_vec() {
+    let input = common::gen_medium_entropy(4096);
+    let mut output = b"prefix".to_vec();
+
+    let mut compressor = CompressorWriter::new(&mut output);
+    compressor.write_all(input.as_slice()).unwrap();
+    let inner: &mut Vec<u8> = compressor.into_inner().unwrap();
+    assert!(inner.starts_with(b"prefix"));
+
+    let (prefix, compressed) = output.split_at(6);
+    assert_eq!(prefix, b"prefix");
+    assert_eq!(input, decompress_all(compressed));
+}
+
+#[test]
+fn test_large_window_round_trip() {
+    let input = common::gen_medium_entropy(4096);
+
+    let encoder: BrotliEncoder<LargeWindow> = BrotliEncoderOptions::new()
+        .large_window_size(LargeWindowSize::best())
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut compressor: CompressorWriter<_, LargeWindow> =
+        CompressorWriter::with_encoder(encoder, Vec::new());
+    compressor.write_all(input.as_slice()).unwrap();
+    let compressed = compressor.into_inner().unwrap();
+
+    let decoder = BrotliDecoderOptions::new()
+        .large_window_size(true)
+        .build()
+        .unwrap();
+    let mut decompressor = DecompressorReader::with_decoder(decoder, compressed.as_slice());
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(input, decompressed);
+}
+
+#[test]
+fn test_write_comp_new_vec() {
+    let input = common::gen_medium_entropy(4096);
+
+    let mut compressor = CompressorWriter::new_vec(Quality::new(5).unwrap(), WindowSize::best());
+    compressor.write_all(input.as_slice()).unwrap();
+    let compressed = compressor.into_inner().unwrap();
+
+    assert_eq!(input, decompress_all(&compressed));
+}
+
+#[test]
+fn test_build_to_writer_and_reader() {
+    let input = common::gen_medium_entropy(4096);
+    let mut options = BrotliEncoderOptions::new();
+    options.quality(7).unwrap();
+
+    let mut compressor = options.build_to_writer(Vec::new()).unwrap();
+    compressor.write_all(input.as_slice()).unwrap();
+    let written = compressor.into_inner().unwrap();
+
+    let mut compressor = options.build_to_reader(input.as_slice()).unwrap();
+    let mut read = Vec::new();
+    compressor.read_to_end(&mut read).unwrap();
+
+    assert_eq!(input, decompress_all(&written));
+    assert_eq!(input, decompress_all(&read));
+}
+
+#[test]
+fn test_decompressor_reader_is_finished() {
+    let input = common::gen_medium_entropy(4096);
+    let compressed = compress_with_options(&input, &BrotliEncoderOptions::new());
+
+    let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    assert!(!decompressor.is_finished());
+
+    let mut buf = vec![0; input.len()];
+    decompressor.read_exact(&mut buf).unwrap();
+    assert_eq!(input, buf);
+
+    assert_eq!(decompressor.read(&mut [0; 16]).unwrap(), 0);
+    assert!(decompressor.is_finished());
+    assert!(decompressor.into_inner().is_ok());
+}
+
+#[test]
+fn test_write_decomp_never_returns_zero() {
+    let input = common::gen_min_entropy(4 * 1024 * 1024);
+    let mut options = BrotliEncoderOptions::new();
+    options.window_size(16).unwrap();
+    let compressed = compress_with_options(&input, &options);
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    let mut remaining = compressed.as_slice();
+
+    while !remaining.is_empty() {
+        let written = decompressor.write(remaining).unwrap();
+
+        assert_ne!(written, 0);
+        remaining = &remaining[written..];
+    }
+
+    assert_eq!(input, decompressor.into_inner().unwrap());
+}
+
+#[test]
+fn test_write_comp_copy_from() {
+    let input = common::gen_medium_entropy(256 * 1024);
+
+    let mut compressor = CompressorWriter::new(Vec::new());
+    let bytes_read = compressor.copy_from(&mut input.as_slice()).unwrap();
+    let compressed = compressor.into_inner().unwrap();
+
+    assert_eq!(bytes_read, input.len() as u64);
+    assert_eq!(input, decompress_all(&compressed));
+}
+
+#[test]
+fn test_write_comp_compress_from_reader() {
+    let input = common::gen_medium_entropy(256 * 1024);
+
+    let mut compressor = CompressorWriter::new(Vec::new());
+    let bytes_read = compressor
+        .compress_from_reader(&mut input.as_slice())
+        .unwrap();
+    let compressed = compressor.into_inner().unwrap();
+
+    assert_eq!(bytes_read, input.len() as u64);
+    assert_eq!(input, decompress_all(&compressed));
+}
+
+#[test]
+fn test_write_comp_copy_from_with_capacity() {
+    let input = common::gen_max_entropy(100_000);
+
+    for capacity in [0, 1, 1000, 64 * 1024] {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        let bytes_read = compressor
+            .copy_from_with_capacity(capacity, &mut input.as_slice())
+            .unwrap();
+        let compressed = compressor.into_inner().unwrap();
+
+        assert_eq!(bytes_read, input.len() as u64);
+        assert_eq!(input, decompress_all(&compressed));
+    }
+}
+
+#[test]
+fn test_write_decomp_multi_stream() {
+    let parts = [
+        common::gen_min_entropy(10_000),
+        common::gen_medium_entropy(10_000),
+        common::gen_max_entropy(10_000),
+    ];
+
+    let mut concatenated = Vec::new();
+    for part in &parts {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(part).unwrap();
+        concatenated.extend(compressor.into_inner().unwrap());
+    }
+
+    for chunk_size in [1, 7, concatenated.len()] {
+        let mut decompressor = DecompressorWriter::multi_stream(Vec::new());
+
+        for chunk in concatenated.chunks(chunk_size) {
+            decompressor.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(decompressor.into_inner().unwrap(), parts.concat());
+    }
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    let written = decompressor.write(&concatenated).unwrap();
+
+    assert!(written < concatenated.len());
+    assert_eq!(decompressor.into_inner().unwrap(), parts[0]);
+}
+
+#[test]
+fn test_write_decomp_multi_stream_truncated() {
+    let mut concatenated = Vec::new();
+    for part in [&b"hello "[..], b"world"] {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(part).unwrap();
+        concatenated.extend(compressor.into_inner().unwrap());
+    }
+
+    let mut decompressor = DecompressorWriter::multi_stream(Vec::new());
+    decompressor
+        .write_all(&concatenated[..concatenated.len() - 1])
+        .unwrap();
+
+    let err = decompressor.into_inner().unwrap_err();
+    assert_eq!(err.error().kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let decompressor = DecompressorWriter::multi_stream(Vec::<u8>::new());
+    assert!(decompressor.into_inner().unwrap().is_empty());
+}
+
+#[test]
+fn test_compressor_writer_total_out() {
+    let input = common::gen_medium_entropy(256 * 1024);
+    let mut compressor = CompressorWriter::new(Vec::new());
+
+    assert_eq!(compressor.total_out(), 0);
+
+    compressor.write_all(&input).unwrap();
+    compressor.flush().unwrap();
+
+    assert!(compressor.total_out() > 0);
+    assert_eq!(compressor.total_out(), compressor.get_ref().len() as u64);
+}
+
+#[test]
+fn test_compressor_writer_counters() {
+    let input = common::gen_min_entropy(256 * 1024);
+    let mut compressor = CompressorWriter::new(Vec::new());
+
+    assert_eq!(compressor.total_in(), 0);
+    assert_eq!(compressor.compression_ratio(), None);
+
+    compressor.write_all(&input[..1024]).unwrap();
+    compressor.flush().unwrap();
+    let total_out = compressor.total_out();
+
+    compressor.write_all(&input[1024..]).unwrap();
+    compressor.flush().unwrap();
+
+    assert_eq!(compressor.total_in(), input.len() as u64);
+    assert!(compressor.total_out() > total_out);
+    assert!(compressor.compression_ratio().unwrap() < 0.1);
+}
+
+#[test]
+fn test_decompressor_total_out() {
+    let input = common::gen_medium_entropy(256 * 1024);
+    let compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        brotlic::CompressionMode::Generic,
+    )
+    .unwrap();
+
+    let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    let mut buf = [0; 1000];
+
+    decompressor.read_exact(&mut buf).unwrap();
+    assert_eq!(decompressor.total_out(), buf.len());
+
+    decompressor.read_to_end(&mut Vec::new()).unwrap();
+    assert_eq!(decompressor.total_out(), input.len());
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+
+    decompressor.write_all(&compressed).unwrap();
+    assert_eq!(decompressor.total_out(), input.len());
+}
+
+/// Writer that counts how often it has been written to.
+#[derive(Debug, Default)]
+struct CountingWriter {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writes += 1;
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_compressor_writer_with_capacity() {
+    let input = common::gen_medium_entropy(1024 * 1024);
+    let options = BrotliEncoderOptions::new().with_quality(1).unwrap();
+    let capacity = 64 * 1024;
+
+    let mut unbuffered =
+        CompressorWriter::with_encoder(options.build().unwrap(), CountingWriter::default());
+    let mut buffered = CompressorWriter::with_encoder_and_capacity(
+        options.build().unwrap(),
+        CountingWriter::default(),
+        capacity,
+    );
+
+    for chunk in input.chunks(100) {
+        unbuffered.write_all(chunk).unwrap();
+        buffered.write_all(chunk).unwrap();
+    }
+
+    unbuffered.flush().unwrap();
+    buffered.flush().unwrap();
+
+    assert_eq!(buffered.get_ref().data, unbuffered.get_ref().data);
+
+    let unbuffered = unbuffered.into_inner().unwrap();
+    let buffered = buffered.into_inner().unwrap();
+
+    assert_eq!(buffered.data, unbuffered.data);
+    assert!(buffered.writes <= buffered.data.len().div_ceil(capacity) + 2);
+    assert!(buffered.writes < unbuffered.writes);
+}
+
+#[test]
+fn test_decompressor_writer_with_capacity() {
+    let input = common::gen_min_entropy(1024 * 1024);
+    let compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        brotlic::CompressionMode::Generic,
+    )
+    .unwrap();
+
+    let mut decompressor = DecompressorWriter::with_decoder_and_capacity(
+        brotlic::BrotliDecoder::new(),
+        CountingWriter::default(),
+        100_000,
+    );
+
+    decompressor.write_all(&compressed).unwrap();
+
+    assert!(decompressor.get_ref().data.len() < input.len());
+
+    decompressor.flush().unwrap();
+
+    assert_eq!(decompressor.get_ref().data, input);
+    assert_eq!(decompressor.get_ref().writes, input.len().div_ceil(100_000));
+
+    let mut output = Vec::new();
+    let mut decompressor = DecompressorWriter::with_decoder_and_capacity(
+        brotlic::BrotliDecoder::new(),
+        &mut output,
+        4096,
+    );
+
+    decompressor.write_all(&compressed).unwrap();
+    drop(decompressor);
+
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_compressor_reader_buf_read() {
+    use std::io::BufRead;
+
+    let input = common::gen_medium_entropy(256 * 1024);
+
+    let mut decompressor = DecompressorReader::new(CompressorReader::new(input.as_slice()));
+    let mut decompressed = Vec::new();
+
+    decompressor.read_to_end(&mut decompressed).unwrap();
+    assert_eq!(decompressed, input);
+
+    // mix buffered and unbuffered reads
+    let mut compressor = CompressorReader::new(input.as_slice());
+    let mut compressed = Vec::new();
+
+    let buffered = compressor.fill_buf().unwrap().to_vec();
+    compressor.consume(10);
+    compressed.extend_from_slice(&buffered[..10]);
+
+    let mut buf = [0; 5];
+    compressor.read_exact(&mut buf).unwrap();
+    compressed.extend_from_slice(&buf);
+    assert_eq!(buf, buffered[10..15]);
+
+    compressor.read_to_end(&mut compressed).unwrap();
+
+    assert!(compressor.fill_buf().unwrap().is_empty());
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+    assert!(compressor.into_inner().is_ok());
+}
+
+#[test]
+fn test_compressor_writer_write_vectored() {
+    let a = common::gen_medium_entropy(100 * 1024);
+    let b = common::gen_min_entropy(50 * 1024);
+
+    let expected = {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(&[&a[..], &b[..]].concat()).unwrap();
+        compressor.into_inner().unwrap()
+    };
+
+    let mut compressor = CompressorWriter::new(Vec::new());
+    let written = compressor
+        .write_vectored(&[IoSlice::new(&a), IoSlice::new(&[]), IoSlice::new(&b)])
+        .unwrap();
+
+    assert_eq!(written, a.len() + b.len());
+    assert_eq!(compressor.into_inner().unwrap(), expected);
+}
+
+#[test]
+fn test_decompressor_writer_write_vectored() {
+    let input = common::gen_max_entropy(64 * 1024);
+    let compressed = {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(&input).unwrap();
+        compressor.into_inner().unwrap()
+    };
+    let (a, b) = compressed.split_at(compressed.len() / 3);
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    let written = decompressor
+        .write_vectored(&[IoSlice::new(a), IoSlice::new(b)])
+        .unwrap();
+
+    assert_eq!(written, compressed.len());
+    assert_eq!(decompressor.into_inner().unwrap(), input);
+}
+
+#[test]
+fn test_into_inner_without_finish() {
+    let input = common::gen_medium_entropy(64 * 1024);
+
+    let mut compressor =
+        CompressorWriter::with_encoder_and_capacity(BrotliEncoder::new(), Vec::new(), 1024 * 1024);
+    compressor.write_all(&input).unwrap();
+
+    let abandoned = compressor.into_inner_without_finish();
+    assert!(brotlic::decompress_to_vec(&abandoned).is_err());
+
+    let compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        brotlic::CompressionMode::Generic,
+    )
+    .unwrap();
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    decompressor
+        .write_all(&compressed[..compressed.len() / 2])
+        .unwrap();
+
+    let partial = decompressor.into_inner_without_finish();
+    assert!(input.starts_with(&partial));
+    assert!(partial.len() < input.len());
+}
+
+/// A writer that accepts at most one byte per call.
+#[derive(Debug)]
+struct ByteWriter(Vec<u8>);
+
+impl Write for ByteWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.extend_from_slice(&buf[..buf.len().min(1)]);
+        Ok(buf.len().min(1))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_compressor_writer_write_consumes_all() {
+    let input = common::gen_max_entropy(8 * 1024 * 1024);
+    // at quality 1 the encoder does not accept all of the input at once
+    let encoder = BrotliEncoderOptions::new()
+        .with_quality(1)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let mut compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+    assert_eq!(compressor.write(&input).unwrap(), input.len());
+    assert_eq!(compressor.total_in(), input.len() as u64);
+
+    let compressed = compressor.into_inner().unwrap();
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+
+    let input = common::gen_medium_entropy(16 * 1024);
+    let mut compressor = CompressorWriter::new(ByteWriter(Vec::new()));
+
+    for chunk in input.chunks(1000) {
+        assert_eq!(compressor.write(chunk).unwrap(), chunk.len());
+    }
+
+    let compressed = compressor.into_inner().unwrap().0;
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+/// A writer that fails exactly once, on its `fail_at`-th call to `write`.
+#[derive(Debug)]
+struct FailOnceWriter {
+    buf: Vec<u8>,
+    writes: usize,
+    fail_at: usize,
+}
+
+impl Write for FailOnceWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writes += 1;
+
+        if self.writes == self.fail_at {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "write failed",
+            ));
+        }
+
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_compressor_writer_write_error_mid_stream() {
+    let input = common::gen_max_entropy(4 * 1024 * 1024);
+    // at quality 1 the encoder produces output while consuming a single buffer
+    let encoder = BrotliEncoderOptions::new()
+        .with_quality(1)
+        .unwrap()
+        .build()
+        .unwrap();
+    let writer = FailOnceWriter {
+        buf: Vec::new(),
+        writes: 0,
+        fail_at: 3,
+    };
+
+    let mut compressor = CompressorWriter::with_encoder(encoder, writer);
+    let mut remaining = input.as_slice();
+    let mut failures = 0;
+
+    while !remaining.is_empty() {
+        let len = remaining.len().min(64 * 1024);
+
+        match compressor.write(&remaining[..len]) {
+            Ok(n) => remaining = &remaining[n..],
+            Err(_) => failures += 1,
+        }
+    }
+
+    assert_eq!(failures, 1);
+    assert_eq!(compressor.total_in(), input.len() as u64);
+
+    let compressed = compressor.into_inner().unwrap().buf;
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+#[test]
+fn test_compressor_writer_try_finish() {
+    let input = common::gen_medium_entropy(16 * 1024);
+    let mut compressor = CompressorWriter::new(Vec::new());
+
+    compressor.write_all(&input).unwrap();
+    compressor.try_finish().unwrap();
+    assert!(compressor.get_encoder().is_finished());
+
+    let len = compressor.get_ref().len();
+    compressor.try_finish().unwrap();
+    assert_eq!(compressor.get_ref().len(), len);
+
+    let compressed = compressor.into_inner().unwrap();
+    assert_eq!(compressed.len(), len);
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+#[test]
+fn test_compressor_writer_null_sink() {
+    let input = common::gen_medium_entropy(64 * 1024);
+
+    let mut compressor = CompressorWriter::new(Vec::new());
+    compressor.write_all(&input).unwrap();
+    let compressed = compressor.into_inner().unwrap();
+
+    let mut compressor = CompressorWriter::new(brotlic::null_sink());
+    compressor.write_all(&input).unwrap();
+    let sink = compressor.into_inner().unwrap();
+
+    assert_eq!(sink.bytes_written(), compressed.len());
+}
+
+#[test]
+fn test_compressor_writer_stats() {
+    let input = common::gen_medium_entropy(64 * 1024);
+    let mut compressor =
+        CompressorWriter::with_encoder_and_capacity(BrotliEncoder::new(), Vec::new(), 4096);
+
+    for chunk in input.chunks(16 * 1024) {
+        compressor.write_all(chunk).unwrap();
+        compressor.flush().unwrap();
+    }
+
+    let stats = *compressor.stats();
+    assert_eq!(stats.bytes_in, input.len() as u64);
+    assert_eq!(stats.bytes_out, compressor.get_ref().len() as u64);
+    assert_eq!(stats.flush_count, 4);
+    assert_eq!(stats.finish_count, 0);
+
+    compressor.try_finish().unwrap();
+    compressor.try_finish().unwrap();
+
+    let stats = *compressor.stats();
+    assert_eq!(stats.bytes_out, compressor.get_ref().len() as u64);
+    assert_eq!(stats.flush_count, 4);
+    assert_eq!(stats.finish_count, 1);
+
+    let compressed = compressor.into_inner().unwrap();
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+#[test]
+fn test_decompressor_stats() {
+    let parts = [
+        common::gen_min_entropy(10_000),
+        common::gen_max_entropy(10_000),
+    ];
+
+    let mut concatenated = Vec::new();
+    for part in &parts {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(part).unwrap();
+        concatenated.extend(compressor.into_inner().unwrap());
+    }
+
+    for chunk_size in [1, 7, concatenated.len()] {
+        let mut decompressor = DecompressorWriter::multi_stream(Vec::new());
+
+        for chunk in concatenated.chunks(chunk_size) {
+            decompressor.write_all(chunk).unwrap();
+        }
+
+        let stats = *decompressor.stats();
+        assert_eq!(stats.bytes_in, concatenated.len() as u64);
+        assert_eq!(stats.bytes_out, parts.concat().len() as u64);
+        assert_eq!(stats.finish_count, 2);
+    }
+
+    let mut decompressor =
+        DecompressorReader::multi_stream(BufReader::with_capacity(100, concatenated.as_slice()));
+    decompressor.read_to_end(&mut Vec::new()).unwrap();
+
+    let stats = *decompressor.stats();
+    assert_eq!(stats.bytes_in, concatenated.len() as u64);
+    assert_eq!(stats.bytes_out, parts.concat().len() as u64);
+    assert_eq!(stats.finish_count, 2);
+}
+
+#[test]
+fn test_compress_all_into_matches_compressor_writer() {
+    for input in [
+        Vec::new(),
+        common::gen_min_entropy(1),
+        common::gen_medium_entropy(64 * 1024),
+        common::gen_max_entropy(1024 * 1024),
+    ] {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(&input).unwrap();
+        let expected = compressor.into_inner().unwrap();
+
+        let mut encoder = BrotliEncoder::new();
+        let mut compressed = Vec::new();
+        encoder
+            .compress_all_into(&input, &mut compressed, BrotliOperation::Finish)
+            .unwrap();
+
+        assert!(encoder.is_finished());
+        assert_eq!(compressed, expected);
+
+        let mut decoder = BrotliDecoder::new();
+        let mut decompressed = Vec::new();
+
+        for chunk in compressed.chunks(1000) {
+            let res = decoder
+                .decompress_all_into(chunk, &mut decompressed)
+                .unwrap();
+            assert_eq!(res.bytes_read, chunk.len());
+            assert!(!res.pending_output);
+        }
+
+        assert!(decoder.is_finished());
+        assert_eq!(decompressed, input);
+    }
+}
+
+#[test]
+fn test_decompressor_writer_decode_error() {
+    let input = common::gen_medium_entropy(16 * 1024);
+    let mut compressed = brotlic::compress_to_vec(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        brotlic::CompressionMode::Generic,
+    )
+    .unwrap();
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    decompressor.write_all(&compressed).unwrap();
+    assert_eq!(decompressor.decode_error(), None);
+
+    compressed[0] = 0x11;
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    let err = decompressor.write_all(&compressed).unwrap_err();
+
+    assert_eq!(
+        decompressor.decode_error(),
+        Some(DecodeError::FormatWindowBits)
+    );
+    assert_eq!(err.to_string(), DecodeError::FormatWindowBits.to_string());
+}
+
+#[test]
+fn test_get_encoder_and_decoder() {
+    let input = common::gen_medium_entropy(16 * 1024);
+
+    let mut compressor = CompressorWriter::new(Vec::new());
+    compressor.write_all(&input).unwrap();
+    compressor.flush().unwrap();
+
+    assert!(!compressor.get_encoder().is_finished());
+    assert_eq!(
+        compressor.get_encoder().total_out() as u64,
+        compressor.total_out()
+    );
+    assert!(!compressor.get_encoder_mut().has_output());
+
+    let compressed = compressor.into_inner().unwrap();
+
+    let mut compressor = CompressorReader::new(input.as_slice());
+    assert!(!compressor.get_encoder().is_finished());
+    compressor.read_to_end(&mut Vec::new()).unwrap();
+    assert!(compressor.get_encoder().is_finished());
+    assert!(!compressor.get_encoder_mut().has_output());
+
+    let mut decompressor = DecompressorWriter::new(Vec::new());
+    decompressor.write_all(&compressed).unwrap();
+    assert!(decompressor.get_decoder().is_finished());
+    assert!(!decompressor.get_decoder_mut().has_output());
+
+    let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    assert!(!decompressor.get_decoder().is_finished());
+    decompressor.read_to_end(&mut Vec::new()).unwrap();
+    assert!(decompressor.get_decoder().is_finished());
+    assert_eq!(decompressor.get_decoder_mut().total_out(), input.len());
+}
+
+#[test]
+fn test_multi_stream_decompressor_reader() {
+    let first = common::gen_medium_entropy(64 * 1024);
+    let second = common::gen_max_entropy(16 * 1024);
+
+    let mut concatenated = Vec::new();
+
+    for part in [&first, &second] {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(part).unwrap();
+        concatenated.extend(compressor.into_inner().unwrap());
+    }
+
+    let mut decompressor: MultiStreamDecompressorReader<_> =
+        DecompressorReader::multi_stream(BufReader::with_capacity(100, concatenated.as_slice()));
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(decompressed, [first.as_slice(), second.as_slice()].concat());
+    assert!(decompressor.is_finished());
+    assert!(decompressor.into_inner().is_ok());
+
+    let mut single = DecompressorReader::new(concatenated.as_slice());
+    let mut decompressed = Vec::new();
+    single.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(decompressed, first);
+}
+
+fn compress_with_window(input: &[u8], window_size: u8) -> Vec<u8> {
+    let options = BrotliEncoderOptions::new()
+        .with_window_size(window_size)
+        .unwrap();
+
+    compress_with_options(input, &options)
+}
+
+#[test]
+fn test_multi_stream_keeps_window_limit() {
+    let input = common::gen_medium_entropy(64 * 1024);
+    let small = compress_with_window(&input, 16);
+    let large = compress_with_window(&input, 22);
+    let options = BrotliDecoderOptions::for_untrusted_input(16);
+
+    let within_limit = [small.as_slice(), &small].concat();
+    let exceeding_limit = [small.as_slice(), &large].concat();
+
+    let mut decompressor = DecompressorReader::multi_stream_with_decoder(
+        options.build().unwrap(),
+        within_limit.as_slice(),
+    );
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+    assert_eq!(decompressed, input.repeat(2));
+
+    let mut decompressor = DecompressorReader::multi_stream_with_decoder(
+        options.build().unwrap(),
+        exceeding_limit.as_slice(),
+    );
+    let err = decompressor.read_to_end(&mut Vec::new()).unwrap_err();
+    let err = err.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+    assert_eq!(*err, DecodeError::FormatWindowBits);
+
+    let mut decompressor =
+        DecompressorWriter::multi_stream_with_decoder(options.build().unwrap(), Vec::new());
+    decompressor.write_all(&within_limit).unwrap();
+    assert_eq!(decompressor.into_inner().unwrap(), input.repeat(2));
+
+    let mut decompressor =
+        DecompressorWriter::multi_stream_with_decoder(options.build().unwrap(), Vec::new());
+    let err = decompressor.write_all(&exceeding_limit).unwrap_err();
+    let err = err.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+    assert_eq!(*err, DecodeError::FormatWindowBits);
+}
+
+#[test]
+fn test_multi_stream_decompressor_reader_truncated() {
+    let mut concatenated = Vec::new();
+
+    for part in [&b"hello "[..], &b"world"[..]] {
+        let mut compressor = CompressorWriter::new(Vec::new());
+        compressor.write_all(part).unwrap();
+        concatenated.extend(compressor.into_inner().unwrap());
+    }
+
+    concatenated.pop();
+
+    let mut decompressor = DecompressorReader::multi_stream(concatenated.as_slice());
+    let err = decompressor.read_to_end(&mut Vec::new()).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_compress_stream() {
+    let options = BrotliEncoderOptions::new();
+
+    for input in common::gen_inputs() {
+        for chunk_size in [0, 1, 1000, 65536] {
+            let mut compressed = Vec::new();
+            let written = brotlic::compress_stream(
+                &mut input.as_slice(),
+                &mut compressed,
+                &options,
+                chunk_size,
+            )
+            .unwrap();
+
+            assert_eq!(written, compressed.len() as u64);
+            assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+
+            let mut decompressed = Vec::new();
+            let written = brotlic::decompress_stream(
+                &mut compressed.as_slice(),
+                &mut decompressed,
+                &BrotliDecoderOptions::new(),
+                chunk_size,
+            )
+            .unwrap();
+
+            assert_eq!(written, input.len() as u64);
+            assert_eq!(decompressed, input);
+        }
+    }
+}
+
+#[test]
+fn test_decompress_stream_incomplete() {
+    let input = common::gen_medium_entropy(4096);
+    let mut compressed = Vec::new();
+    brotlic::compress_to_writer(&input, &mut compressed, &BrotliEncoderOptions::new()).unwrap();
+
+    let mut truncated = &compressed[..compressed.len() / 2];
+    let err = brotlic::decompress_stream(
+        &mut truncated,
+        &mut Vec::new(),
+        &BrotliDecoderOptions::new(),
+        1024,
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_decompress_stream_trailing_data() {
+    let input = common::gen_medium_entropy(4096);
+    let mut compressed = Vec::new();
+    brotlic::compress_to_writer(&input, &mut compressed, &BrotliEncoderOptions::new()).unwrap();
+    compressed.extend_from_slice(b"trailing");
+
+    let err = brotlic::decompress_stream(
+        &mut compressed.as_slice(),
+        &mut Vec::new(),
+        &BrotliDecoderOptions::new(),
+        65536,
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_decompress_stream_trailing_data_after_chunk() {
+    let input = common::gen_medium_entropy(4096);
+    let mut compressed = Vec::new();
+    brotlic::compress_to_writer(&input, &mut compressed, &BrotliEncoderOptions::new()).unwrap();
+    let chunk_size = compressed.len();
+    compressed.extend_from_slice(b"trailing");
+
+    let err = brotlic::decompress_stream(
+        &mut compressed.as_slice(),
+        &mut Vec::new(),
+        &BrotliDecoderOptions::new(),
+        chunk_size,
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
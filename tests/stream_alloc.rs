@@ -0,0 +1,166 @@
+#![cfg(feature = "std")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use brotlic::{
+    BrotliDecoderOptions, BrotliEncoderOptions, CompressorReader, CompressorWriter,
+    DecompressorReader, DecompressorWriter,
+};
+
+mod common;
+
+#[derive(Clone, Default)]
+struct CountingAlloc {
+    allocated: Arc<AtomicUsize>,
+    deallocated: Arc<AtomicUsize>,
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocated.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.deallocated.fetch_add(1, Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn compress_in(input: &[u8]) -> Vec<u8> {
+    let mut compressor = CompressorWriter::new_in(Vec::new(), System);
+    compressor.write_all(input).unwrap();
+    compressor.into_inner().unwrap()
+}
+
+#[test]
+fn test_compressor_writer_new_in() {
+    let input = common::gen_min_entropy(4096);
+    let compressed = compress_in(input.as_slice());
+
+    let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(input, decompressed);
+}
+
+#[test]
+fn test_decompressor_reader_new_in() {
+    let input = common::gen_max_entropy(4096);
+    let compressed = compress_in(input.as_slice());
+
+    let mut decompressor = DecompressorReader::new_in(compressed.as_slice(), System);
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(input, decompressed);
+}
+
+#[test]
+fn test_compressor_reader_new_in() {
+    let input = common::gen_medium_entropy(4096);
+
+    let mut compressor = CompressorReader::new_in(input.as_slice(), System);
+    let mut compressed = Vec::new();
+    compressor.read_to_end(&mut compressed).unwrap();
+
+    let mut decompressor = DecompressorReader::new(compressed.as_slice());
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).unwrap();
+
+    assert_eq!(input, decompressed);
+}
+
+#[test]
+fn test_decompressor_writer_new_in() {
+    let input = common::gen_max_entropy(4096);
+    let compressed = compress_in(input.as_slice());
+
+    let mut decompressor = DecompressorWriter::new_in(Vec::new(), System);
+    decompressor.write_all(compressed.as_slice()).unwrap();
+
+    assert_eq!(input, decompressor.into_inner().unwrap());
+}
+
+#[test]
+fn test_custom_allocator_is_used() {
+    let alloc = CountingAlloc::default();
+    let input = common::gen_medium_entropy(4096);
+
+    let compressed = {
+        let mut compressor = CompressorWriter::new_in(Vec::new(), alloc.clone());
+        compressor.write_all(input.as_slice()).unwrap();
+        compressor.into_inner().unwrap()
+    };
+
+    let decompressed = {
+        let mut decompressor = DecompressorReader::new_in(compressed.as_slice(), alloc.clone());
+        let mut decompressed = Vec::new();
+        decompressor.read_to_end(&mut decompressed).unwrap();
+        decompressed
+    };
+
+    assert_eq!(input, decompressed);
+
+    let allocated = alloc.allocated.load(Ordering::SeqCst);
+    assert!(allocated > 0);
+    assert_eq!(allocated, alloc.deallocated.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_options_build_in() {
+    let alloc = CountingAlloc::default();
+    let input = common::gen_medium_entropy(4096);
+
+    let compressed = {
+        let encoder = BrotliEncoderOptions::new()
+            .large_window_size(28)
+            .unwrap()
+            .build_in(alloc.clone())
+            .unwrap();
+        let mut compressor = CompressorWriter::with_encoder(encoder, Vec::new());
+        compressor.write_all(input.as_slice()).unwrap();
+        compressor.into_inner().unwrap()
+    };
+
+    let encoder_allocated = alloc.allocated.load(Ordering::SeqCst);
+    assert!(encoder_allocated > 0);
+    assert_eq!(encoder_allocated, alloc.deallocated.load(Ordering::SeqCst));
+
+    let decompressed = {
+        let decoder = BrotliDecoderOptions::new()
+            .with_large_window_size(true)
+            .build_in(alloc.clone())
+            .unwrap();
+        let mut decompressor = DecompressorReader::with_decoder(decoder, compressed.as_slice());
+        let mut decompressed = Vec::new();
+        decompressor.read_to_end(&mut decompressed).unwrap();
+        decompressed
+    };
+
+    assert_eq!(input, decompressed);
+
+    let allocated = alloc.allocated.load(Ordering::SeqCst);
+    assert!(allocated > encoder_allocated);
+    assert_eq!(allocated, alloc.deallocated.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_options_build_in_invalid() {
+    let alloc = CountingAlloc::default();
+
+    let res = BrotliDecoderOptions::new()
+        .with_large_window_size(true)
+        .with_max_window_bits(22)
+        .build_in(alloc.clone());
+
+    assert!(res.is_err());
+    assert_eq!(
+        alloc.allocated.load(Ordering::SeqCst),
+        alloc.deallocated.load(Ordering::SeqCst)
+    );
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "std")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use brotlic::BrotliEncoderOptions;
+
+mod common;
+
+/// Counts the bytes allocated by the current thread.
+struct CountingAlloc;
+
+std::thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocated_by<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATED.with(Cell::get);
+    let result = f();
+
+    (ALLOCATED.with(Cell::get) - before, result)
+}
+
+#[test]
+fn test_thread_local_encoder_reuses_memory() {
+    let options = BrotliEncoderOptions::best();
+    let mut compress = brotlic::thread_local_encoder(&options);
+    let input = common::gen_medium_entropy(65536);
+    let (created, _) = allocated_by(|| compress(&input).unwrap());
+
+    for input in [
+        common::gen_min_entropy(65536),
+        common::gen_medium_entropy(65536),
+        common::gen_max_entropy(65536),
+    ] {
+        compress(&input).unwrap();
+
+        let (reused, compressed) = allocated_by(|| compress(&input).unwrap());
+        assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+
+        // only the output is allocated once the encoder is pooled
+        assert!(
+            reused < created / 10,
+            "{reused} bytes allocated, {created} when created"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "tokio")]
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use brotlic::{
+    CompressionMode, CompressorReader, CompressorWriter, DecompressorReader, DecompressorWriter,
+    Quality, WindowSize,
+};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_test::io::Builder;
+
+mod common;
+
+fn compress(input: &[u8]) -> Vec<u8> {
+    let mut compressor = CompressorWriter::new(Vec::new());
+    Write::write_all(&mut compressor, input).unwrap();
+    compressor.into_inner().unwrap()
+}
+
+/// Writer that is pending on every other call and accepts at most 7 bytes.
+#[derive(Debug, Default)]
+struct TrickleWriter {
+    data: Vec<u8>,
+    ready: bool,
+}
+
+impl AsyncWrite for TrickleWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let n = buf.len().min(7);
+        self.data.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_async_write_comp() {
+    let input = common::gen_medium_entropy(256 * 1024);
+    let expected = compress(&input);
+
+    let mock = Builder::new().write(&expected).build();
+    let mut compressor = CompressorWriter::new_async(mock);
+
+    compressor.write_all(&input).await.unwrap();
+    compressor.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_async_write_comp_trickle() {
+    let input = common::gen_max_entropy(4096);
+
+    let mut compressor = CompressorWriter::new_async(TrickleWriter::default());
+
+    compressor.write_all(&input[..1000]).await.unwrap();
+    compressor.flush().await.unwrap();
+    compressor.write_all(&input[1000..]).await.unwrap();
+    compressor.shutdown().await.unwrap();
+
+    // flushing and finishing is counted once, even though the writer was
+    // polled again after being pending
+    let (writer, _, stats) = compressor.into_parts();
+    assert_eq!(stats.flush_count, 1);
+    assert_eq!(stats.finish_count, 1);
+    assert_eq!(stats.bytes_in, input.len() as u64);
+    assert_eq!(stats.bytes_out, writer.data.len() as u64);
+
+    assert_eq!(brotlic::decompress_to_vec(&writer.data).unwrap(), input);
+}
+
+#[tokio::test]
+async fn test_async_write_decomp() {
+    let input = common::gen_medium_entropy(256 * 1024);
+    let compressed = compress(&input);
+
+    let mock = Builder::new().write(&input).build();
+    let mut decompressor = DecompressorWriter::new(mock);
+
+    decompressor.write_all(&compressed).await.unwrap();
+    decompressor.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_async_write_decomp_trickle() {
+    let input = common::gen_min_entropy(4096);
+    let compressed = compress(&input);
+
+    let mut decompressor = DecompressorWriter::new(TrickleWriter::default());
+
+    decompressor.write_all(&compressed).await.unwrap();
+    decompressor.shutdown().await.unwrap();
+
+    let (writer, _, stats) = decompressor.into_parts();
+    assert_eq!(stats.bytes_in, compressed.len() as u64);
+    assert_eq!(stats.bytes_out, input.len() as u64);
+    assert_eq!(stats.finish_count, 1);
+
+    assert_eq!(writer.data, input);
+}
+
+#[tokio::test]
+async fn test_async_write_decomp_unfinished() {
+    let compressed = compress(&common::gen_medium_entropy(4096));
+    let truncated = &compressed[..compressed.len() / 2];
+
+    let mut decompressor = DecompressorWriter::new(TrickleWriter::default());
+    decompressor.write_all(truncated).await.unwrap();
+
+    let err = decompressor.shutdown().await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[tokio::test]
+async fn test_async_read_comp() {
+    let input = common::gen_medium_entropy(256 * 1024);
+
+    let mock = Builder::new().read(&input).build();
+    let mut compressor = CompressorReader::new(BufReader::new(mock));
+    let mut compressed = Vec::new();
+
+    compressor.read_to_end(&mut compressed).await.unwrap();
+
+    assert_eq!(brotlic::decompress_to_vec(&compressed).unwrap(), input);
+}
+
+#[tokio::test]
+async fn test_async_read_decomp() {
+    let input = common::gen_max_entropy(256 * 1024);
+    let compressed = compress(&input);
+
+    let (first, second) = compressed.split_at(compressed.len() / 3);
+    let mock = Builder::new().read(first).read(second).build();
+    let mut decompressor = DecompressorReader::new(BufReader::new(mock));
+    let mut decompressed = Vec::new();
+
+    decompressor.read_to_end(&mut decompressed).await.unwrap();
+
+    assert_eq!(decompressed, input);
+    assert!(decompressor.is_finished());
+}
+
+#[tokio::test]
+async fn test_compress_async() {
+    let input = common::gen_medium_entropy(64 * 1024);
+
+    let compressed = brotlic::compress_async(
+        &input,
+        Quality::default(),
+        WindowSize::default(),
+        CompressionMode::Generic,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(brotlic::decompress_async(&compressed).await.unwrap(), input);
+}
+
+#[tokio::test]
+async fn test_decompress_async_corrupt() {
+    let compressed = compress(&common::gen_medium_entropy(4096));
+
+    assert!(
+        brotlic::decompress_async(&compressed[..compressed.len() / 2])
+            .await
+            .is_err()
+    );
+}